@@ -1856,6 +1856,26 @@ typedef struct foxglove_mcap_attachment {
 } foxglove_mcap_attachment;
 #endif
 
+#if !defined(__wasm__)
+/**
+ * Custom reader function pointer for streaming attachment data into an MCAP file.
+ *
+ * The read function is called synchronously from `foxglove_mcap_attach_from_reader`, until the
+ * declared length of the attachment has been read.
+ */
+typedef struct foxglove_attachment_reader {
+  /**
+   * User-provided context pointer, passed to the read function
+   */
+  void *context;
+  /**
+   * Read function: read up to `len` bytes into `data`
+   * Returns number of bytes read, or 0 at the end of the data, or sets error on failure
+   */
+  size_t (*read_fn)(void *context, uint8_t *data, size_t len, int32_t *error);
+} foxglove_attachment_reader;
+#endif
+
 /**
  * A collection of metadata items for a channel.
  */
@@ -4423,6 +4443,50 @@ foxglove_error foxglove_mcap_attach(struct foxglove_mcap_writer *writer,
                                     const struct foxglove_mcap_attachment *FOXGLOVE_NONNULL attachment);
 #endif
 
+#if !defined(__wasm__)
+/**
+ * Write an attachment to an MCAP file, streaming its data from a reader.
+ *
+ * Unlike `foxglove_mcap_attach`, the data is not required to be in memory up front. It is copied
+ * from the reader into the MCAP file in fixed-size pieces. Messages logged to the same writer
+ * wait until the attachment is complete, so the reader should be fast, such as a local file.
+ *
+ * The `data` field of the attachment is ignored, and `data_len` is the number of bytes that the
+ * reader must provide. If the reader fails, or ends before providing `data_len` bytes, the
+ * remainder of the attachment is filled with zeros so that the file remains valid, and an error
+ * is returned.
+ *
+ * Returns 0 on success, or returns a FoxgloveError code on error.
+ *
+ * # Safety
+ * `writer` must be a valid pointer to a `FoxgloveMcapWriter` created via `foxglove_mcap_open`.
+ * `attachment` must be a valid pointer to a `FoxgloveMcapAttachment`.
+ * The `name` and `media_type` fields of the attachment must be valid UTF-8 strings.
+ * `reader` must be a valid pointer to a `FoxgloveAttachmentReader` with a non-null `read_fn`.
+ */
+foxglove_error foxglove_mcap_attach_from_reader(struct foxglove_mcap_writer *writer,
+                                                const struct foxglove_mcap_attachment *FOXGLOVE_NONNULL attachment,
+                                                const struct foxglove_attachment_reader *FOXGLOVE_NONNULL reader);
+#endif
+
+#if !defined(__wasm__)
+/**
+ * Write the contents of a file to an MCAP file as an attachment, streaming the data.
+ *
+ * The attachment is named after the file, its create time is the file's modification time, and
+ * its log time is the current time.
+ *
+ * Returns 0 on success, or returns a FoxgloveError code on error.
+ *
+ * # Safety
+ * `writer` must be a valid pointer to a `FoxgloveMcapWriter` created via `foxglove_mcap_open`.
+ * `path` and `media_type` must be valid UTF-8 strings.
+ */
+foxglove_error foxglove_mcap_attach_file(struct foxglove_mcap_writer *writer,
+                                         const struct foxglove_string *FOXGLOVE_NONNULL path,
+                                         const struct foxglove_string *FOXGLOVE_NONNULL media_type);
+#endif
+
 #if !defined(__wasm__)
 /**
  * Create a new channel. The channel must later be freed with `foxglove_channel_free`.
@@ -6,7 +6,7 @@ use crate::{
     FoxgloveString,
 };
 use mcap::{Compression, WriteOptions};
-use std::io::{Seek, SeekFrom, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 
 #[repr(u8)]
 pub enum FoxgloveMcapCompression {
@@ -228,6 +228,75 @@ impl McapWriterVariant {
             McapWriterVariant::Custom(writer) => writer.attach(attachment),
         }
     }
+
+    fn attach_from_reader(
+        &self,
+        header: foxglove::McapAttachmentHeader,
+        length: u64,
+        reader: impl Read,
+    ) -> Result<(), foxglove::FoxgloveError> {
+        match self {
+            McapWriterVariant::File(writer) => writer.attach_from_reader(header, length, reader),
+            McapWriterVariant::Custom(writer) => writer.attach_from_reader(header, length, reader),
+        }
+    }
+
+    fn attach_file(&self, path: &str, media_type: &str) -> Result<(), foxglove::FoxgloveError> {
+        match self {
+            McapWriterVariant::File(writer) => writer.attach_file(path, media_type),
+            McapWriterVariant::Custom(writer) => writer.attach_file(path, media_type),
+        }
+    }
+}
+
+/// Custom reader function pointer for streaming attachment data into an MCAP file.
+///
+/// The read function is called synchronously from `foxglove_mcap_attach_from_reader`, until the
+/// declared length of the attachment has been read.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FoxgloveAttachmentReader {
+    /// User-provided context pointer, passed to the read function
+    pub context: *mut std::ffi::c_void,
+    /// Read function: read up to `len` bytes into `data`
+    /// Returns number of bytes read, or 0 at the end of the data, or sets error on failure
+    pub read_fn: Option<
+        unsafe extern "C" fn(
+            context: *mut std::ffi::c_void,
+            data: *mut u8,
+            len: usize,
+            error: *mut i32,
+        ) -> usize,
+    >,
+}
+
+struct CustomReader {
+    callbacks: FoxgloveAttachmentReader,
+}
+
+impl Read for CustomReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read_fn = self
+            .callbacks
+            .read_fn
+            .expect("read_fn checked in do_foxglove_mcap_attach_from_reader");
+
+        let mut error = 0;
+        let read = unsafe {
+            read_fn(
+                self.callbacks.context,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &raw mut error,
+            )
+        };
+
+        if error != 0 {
+            return Err(std::io::Error::from_raw_os_error(error));
+        }
+
+        Ok(read.min(buf.len()))
+    }
 }
 
 /// An MCAP attachment to store in an MCAP file.
@@ -511,6 +580,130 @@ unsafe fn do_foxglove_mcap_attach(
     writer_handle.attach(&mcap_attachment)
 }
 
+/// Write an attachment to an MCAP file, streaming its data from a reader.
+///
+/// Unlike `foxglove_mcap_attach`, the data is not required to be in memory up front. It is copied
+/// from the reader into the MCAP file in fixed-size pieces. Messages logged to the same writer
+/// wait until the attachment is complete, so the reader should be fast, such as a local file.
+///
+/// The `data` field of the attachment is ignored, and `data_len` is the number of bytes that the
+/// reader must provide. If the reader fails, or ends before providing `data_len` bytes, the
+/// remainder of the attachment is filled with zeros so that the file remains valid, and an error
+/// is returned.
+///
+/// Returns 0 on success, or returns a FoxgloveError code on error.
+///
+/// # Safety
+/// `writer` must be a valid pointer to a `FoxgloveMcapWriter` created via `foxglove_mcap_open`.
+/// `attachment` must be a valid pointer to a `FoxgloveMcapAttachment`.
+/// The `name` and `media_type` fields of the attachment must be valid UTF-8 strings.
+/// `reader` must be a valid pointer to a `FoxgloveAttachmentReader` with a non-null `read_fn`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn foxglove_mcap_attach_from_reader(
+    writer: Option<&mut FoxgloveMcapWriter>,
+    attachment: &FoxgloveMcapAttachment,
+    reader: &FoxgloveAttachmentReader,
+) -> FoxgloveError {
+    let Some(writer) = writer else {
+        tracing::error!("foxglove_mcap_attach_from_reader called with null writer");
+        return FoxgloveError::ValueError;
+    };
+
+    match unsafe { do_foxglove_mcap_attach_from_reader(writer, attachment, reader) } {
+        Ok(()) => FoxgloveError::Ok,
+        Err(e) => {
+            tracing::error!("foxglove_mcap_attach_from_reader failed: {e}");
+            e.into()
+        }
+    }
+}
+
+unsafe fn do_foxglove_mcap_attach_from_reader(
+    writer: &mut FoxgloveMcapWriter,
+    attachment: &FoxgloveMcapAttachment,
+    reader: &FoxgloveAttachmentReader,
+) -> Result<(), foxglove::FoxgloveError> {
+    let name = unsafe { attachment.name.as_utf8_str() }.map_err(|e| {
+        foxglove::FoxgloveError::Utf8Error(format!("attachment name is invalid: {e}"))
+    })?;
+
+    let media_type = unsafe { attachment.media_type.as_utf8_str() }.map_err(|e| {
+        foxglove::FoxgloveError::Utf8Error(format!("attachment media_type is invalid: {e}"))
+    })?;
+
+    if reader.read_fn.is_none() {
+        return Err(foxglove::FoxgloveError::ValueError(
+            "read_fn must be non-null".to_string(),
+        ));
+    }
+
+    let Some(writer_handle) = writer.0.as_ref() else {
+        return Err(foxglove::FoxgloveError::SinkClosed);
+    };
+
+    let header = foxglove::McapAttachmentHeader {
+        log_time: attachment.log_time,
+        create_time: attachment.create_time,
+        name: name.to_string(),
+        media_type: media_type.to_string(),
+    };
+
+    writer_handle.attach_from_reader(
+        header,
+        attachment.data_len as u64,
+        CustomReader { callbacks: *reader },
+    )
+}
+
+/// Write the contents of a file to an MCAP file as an attachment, streaming the data.
+///
+/// The attachment is named after the file, its create time is the file's modification time, and
+/// its log time is the current time.
+///
+/// Returns 0 on success, or returns a FoxgloveError code on error.
+///
+/// # Safety
+/// `writer` must be a valid pointer to a `FoxgloveMcapWriter` created via `foxglove_mcap_open`.
+/// `path` and `media_type` must be valid UTF-8 strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn foxglove_mcap_attach_file(
+    writer: Option<&mut FoxgloveMcapWriter>,
+    path: &FoxgloveString,
+    media_type: &FoxgloveString,
+) -> FoxgloveError {
+    let Some(writer) = writer else {
+        tracing::error!("foxglove_mcap_attach_file called with null writer");
+        return FoxgloveError::ValueError;
+    };
+
+    match unsafe { do_foxglove_mcap_attach_file(writer, path, media_type) } {
+        Ok(()) => FoxgloveError::Ok,
+        Err(e) => {
+            tracing::error!("foxglove_mcap_attach_file failed: {e}");
+            e.into()
+        }
+    }
+}
+
+unsafe fn do_foxglove_mcap_attach_file(
+    writer: &mut FoxgloveMcapWriter,
+    path: &FoxgloveString,
+    media_type: &FoxgloveString,
+) -> Result<(), foxglove::FoxgloveError> {
+    let path = unsafe { path.as_utf8_str() }
+        .map_err(|e| foxglove::FoxgloveError::Utf8Error(format!("path is invalid: {e}")))?;
+
+    let media_type = unsafe { media_type.as_utf8_str() }.map_err(|e| {
+        foxglove::FoxgloveError::Utf8Error(format!("attachment media_type is invalid: {e}"))
+    })?;
+
+    let Some(writer_handle) = writer.0.as_ref() else {
+        return Err(foxglove::FoxgloveError::SinkClosed);
+    };
+
+    writer_handle.attach_file(path, media_type)
+}
+
 pub struct FoxgloveChannel(foxglove::RawChannel);
 
 /// Create a new channel. The channel must later be freed with `foxglove_channel_free`.
@@ -873,6 +1066,7 @@ pub unsafe extern "C" fn foxglove_channel_log(
         unsafe { std::slice::from_raw_parts(data, data_len) },
         foxglove::PartialMetadata {
             log_time: log_time.copied(),
+            ..Default::default()
         },
         sink_id,
     );
@@ -932,8 +1126,195 @@ pub(crate) fn log_msg_to_channel<T: foxglove::Encode>(
         msg,
         foxglove::PartialMetadata {
             log_time: log_time.copied(),
+            ..Default::default()
         },
         sink_id,
     );
     FoxgloveError::Ok
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mcap_options(path: &str) -> FoxgloveMcapOptions {
+        FoxgloveMcapOptions {
+            context: foxglove_context_new(),
+            path: path.into(),
+            truncate: true,
+            custom_writer: std::ptr::null(),
+            compression: FoxgloveMcapCompression::None,
+            profile: "".into(),
+            chunk_size: 0,
+            use_chunks: false,
+            disable_seeking: false,
+            emit_statistics: true,
+            emit_summary_offsets: true,
+            emit_message_indexes: true,
+            emit_chunk_indexes: true,
+            emit_attachment_indexes: true,
+            emit_metadata_indexes: true,
+            repeat_channels: true,
+            repeat_schemas: true,
+            sink_channel_filter_context: std::ptr::null(),
+            sink_channel_filter: None,
+        }
+    }
+
+    fn open(options: &FoxgloveMcapOptions) -> &'static mut FoxgloveMcapWriter {
+        let mut writer: *mut FoxgloveMcapWriter = std::ptr::null_mut();
+        let result = unsafe { foxglove_mcap_open(options, &raw mut writer) };
+        assert_eq!(result, FoxgloveError::Ok);
+        unsafe { &mut *writer }
+    }
+
+    fn attachment(name: &str, data_len: usize) -> FoxgloveMcapAttachment {
+        FoxgloveMcapAttachment {
+            log_time: 1,
+            create_time: 2,
+            name: name.into(),
+            media_type: "text/plain".into(),
+            data: std::ptr::null(),
+            data_len,
+        }
+    }
+
+    unsafe extern "C" fn read_slice(
+        context: *mut c_void,
+        data: *mut u8,
+        len: usize,
+        _error: *mut i32,
+    ) -> usize {
+        let source = unsafe { &mut *(context as *mut &[u8]) };
+        let n = len.min(source.len());
+        unsafe { std::ptr::copy_nonoverlapping(source.as_ptr(), data, n) };
+        *source = &source[n..];
+        n
+    }
+
+    fn read_attachments(path: &std::path::Path) -> Vec<(String, Vec<u8>)> {
+        let contents = std::fs::read(path).unwrap();
+        mcap::read::LinearReader::new(&contents)
+            .unwrap()
+            .filter_map(|record| match record.unwrap() {
+                mcap::records::Record::Attachment { header, data, .. } => {
+                    Some((header.name, data.into_owned()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_attach_from_reader_and_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+        let source_path = dir.path().join("source.txt");
+        std::fs::write(&source_path, b"from file").unwrap();
+
+        let options = mcap_options(path.to_str().unwrap());
+        let writer = open(&options);
+
+        let mut source: &[u8] = b"from reader";
+        let reader = FoxgloveAttachmentReader {
+            context: &raw mut source as *mut c_void,
+            read_fn: Some(read_slice),
+        };
+        let result = unsafe {
+            foxglove_mcap_attach_from_reader(
+                Some(&mut *writer),
+                &attachment("reader.txt", 11),
+                &reader,
+            )
+        };
+        assert_eq!(result, FoxgloveError::Ok);
+
+        let result = unsafe {
+            foxglove_mcap_attach_file(
+                Some(&mut *writer),
+                &source_path.to_str().unwrap().into(),
+                &"text/plain".into(),
+            )
+        };
+        assert_eq!(result, FoxgloveError::Ok);
+
+        assert_eq!(
+            unsafe { foxglove_mcap_close(Some(writer)) },
+            FoxgloveError::Ok
+        );
+        unsafe { foxglove_context_free(options.context) };
+
+        assert_eq!(
+            read_attachments(&path),
+            vec![
+                ("reader.txt".to_string(), b"from reader".to_vec()),
+                ("source.txt".to_string(), b"from file".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attach_from_reader_short_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+
+        let options = mcap_options(path.to_str().unwrap());
+        let writer = open(&options);
+
+        let mut source: &[u8] = b"abc";
+        let reader = FoxgloveAttachmentReader {
+            context: &raw mut source as *mut c_void,
+            read_fn: Some(read_slice),
+        };
+        let result = unsafe {
+            foxglove_mcap_attach_from_reader(Some(&mut *writer), &attachment("short", 5), &reader)
+        };
+        assert_eq!(result, FoxgloveError::ValueError);
+
+        assert_eq!(
+            unsafe { foxglove_mcap_close(Some(writer)) },
+            FoxgloveError::Ok
+        );
+        unsafe { foxglove_context_free(options.context) };
+
+        // The remainder is zero-filled so the file stays valid.
+        assert_eq!(
+            read_attachments(&path),
+            vec![("short".to_string(), b"abc\0\0".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_attach_from_reader_null_read_fn() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+
+        let options = mcap_options(path.to_str().unwrap());
+        let writer = open(&options);
+
+        let reader = FoxgloveAttachmentReader {
+            context: std::ptr::null_mut(),
+            read_fn: None,
+        };
+        let result = unsafe {
+            foxglove_mcap_attach_from_reader(Some(&mut *writer), &attachment("none", 1), &reader)
+        };
+        assert_eq!(result, FoxgloveError::ValueError);
+
+        let result = unsafe {
+            foxglove_mcap_attach_file(
+                Some(&mut *writer),
+                &dir.path().join("missing").to_str().unwrap().into(),
+                &"text/plain".into(),
+            )
+        };
+        assert_eq!(result, FoxgloveError::IoError);
+
+        assert_eq!(
+            unsafe { foxglove_mcap_close(Some(writer)) },
+            FoxgloveError::Ok
+        );
+        unsafe { foxglove_context_free(options.context) };
+        assert!(read_attachments(&path).is_empty());
+    }
+}
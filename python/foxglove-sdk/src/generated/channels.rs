@@ -158,7 +158,10 @@ impl ArrowPrimitiveChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::ArrowPrimitive, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -267,7 +270,10 @@ impl CameraCalibrationChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::CameraCalibration, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -376,7 +382,10 @@ impl CircleAnnotationChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::CircleAnnotation, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -485,7 +494,10 @@ impl ColorChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Color, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -589,7 +601,10 @@ impl CompressedImageChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::CompressedImage, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -698,7 +713,10 @@ impl CompressedVideoChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::CompressedVideo, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -807,7 +825,10 @@ impl CylinderPrimitiveChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::CylinderPrimitive, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -916,7 +937,10 @@ impl CubePrimitiveChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::CubePrimitive, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1025,7 +1049,10 @@ impl FrameTransformChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::FrameTransform, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1134,7 +1161,10 @@ impl FrameTransformsChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::FrameTransforms, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1243,7 +1273,10 @@ impl GeoJsonChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::GeoJson, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1347,7 +1380,10 @@ impl GridChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Grid, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1451,7 +1487,10 @@ impl VoxelGridChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::VoxelGrid, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1560,7 +1599,10 @@ impl ImageAnnotationsChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::ImageAnnotations, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1669,7 +1711,10 @@ impl KeyValuePairChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::KeyValuePair, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1778,7 +1823,10 @@ impl LaserScanChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::LaserScan, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1887,7 +1935,10 @@ impl LinePrimitiveChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::LinePrimitive, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -1996,7 +2047,10 @@ impl LocationFixChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::LocationFix, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -2105,7 +2159,10 @@ impl LocationFixesChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::LocationFixes, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -2214,7 +2271,10 @@ impl LogChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Log, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -2318,7 +2378,10 @@ impl SceneEntityDeletionChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::SceneEntityDeletion, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -2427,7 +2490,10 @@ impl SceneEntityChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::SceneEntity, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -2536,7 +2602,10 @@ impl SceneUpdateChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::SceneUpdate, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -2645,7 +2714,10 @@ impl ModelPrimitiveChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::ModelPrimitive, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -2754,7 +2826,10 @@ impl PackedElementFieldChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::PackedElementField, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -2863,7 +2938,10 @@ impl Point2Channel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Point2, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -2967,7 +3045,10 @@ impl Point3Channel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Point3, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -3071,7 +3152,10 @@ impl Point3InFrameChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Point3InFrame, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -3180,7 +3264,10 @@ impl PointCloudChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::PointCloud, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -3289,7 +3376,10 @@ impl PointsAnnotationChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::PointsAnnotation, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -3398,7 +3488,10 @@ impl PoseChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Pose, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -3502,7 +3595,10 @@ impl PoseInFrameChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::PoseInFrame, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -3611,7 +3707,10 @@ impl PosesInFrameChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::PosesInFrame, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -3720,7 +3819,10 @@ impl QuaternionChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Quaternion, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -3829,7 +3931,10 @@ impl RawAudioChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::RawAudio, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -3938,7 +4043,10 @@ impl RawImageChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::RawImage, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -4047,7 +4155,10 @@ impl SpherePrimitiveChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::SpherePrimitive, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -4156,7 +4267,10 @@ impl TextAnnotationChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::TextAnnotation, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -4265,7 +4379,10 @@ impl TextPrimitiveChannel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::TextPrimitive, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -4379,7 +4496,10 @@ impl TriangleListPrimitiveChannel {
         log_time: Option<u64>,
         sink_id: Option<u64>,
     ) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -4488,7 +4608,10 @@ impl Vector2Channel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Vector2, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
@@ -4592,7 +4715,10 @@ impl Vector3Channel {
     /// :param sink_id: The ID of the sink to log to. If omitted, the message is logged to all sinks.
     #[pyo3(signature = (msg, *, log_time=None, sink_id=None))]
     fn log(&self, msg: &schemas::Vector3, log_time: Option<u64>, sink_id: Option<u64>) {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);
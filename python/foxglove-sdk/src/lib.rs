@@ -99,7 +99,10 @@ impl BaseChannel {
 
     #[pyo3(signature = (msg, log_time=None, sink_id=None))]
     fn log(&self, msg: &[u8], log_time: Option<u64>, sink_id: Option<u64>) -> PyResult<()> {
-        let metadata = PartialMetadata { log_time };
+        let metadata = PartialMetadata {
+            log_time,
+            ..Default::default()
+        };
         let sink_id = sink_id.and_then(NonZeroU64::new).map(foxglove::SinkId::new);
         self.0.log_with_meta_to_sink(msg, metadata, sink_id);
         Ok(())
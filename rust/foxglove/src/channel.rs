@@ -15,6 +15,7 @@ mod raw_channel;
 
 pub use channel_descriptor::ChannelDescriptor;
 pub use lazy_channel::{LazyChannel, LazyRawChannel};
+pub(crate) use raw_channel::LatchedReplay;
 pub use raw_channel::RawChannel;
 
 /// Stack buffer size to use for encoding messages.
@@ -141,6 +142,9 @@ impl<T: Encode> Channel<T> {
 
     /// Encodes the message and logs it on the channel with additional metadata.
     ///
+    /// The metadata may specify the log time, publish time, and sequence number of the message.
+    /// Fields that are omitted are filled in with defaults; see [`PartialMetadata`] for details.
+    ///
     /// The buffering behavior depends on the log sink; see [`McapWriter`][crate::McapWriter] and
     /// [`WebSocketServer`][crate::WebSocketServer] for details.
     pub fn log_with_meta(&self, msg: &T, metadata: PartialMetadata) {
@@ -160,10 +164,11 @@ impl<T: Encode> Channel<T> {
         metadata: PartialMetadata,
         sink_id: Option<SinkId>,
     ) {
-        if self.has_sinks() {
+        if self.inner.should_log(sink_id) {
             self.log_to_sinks(msg, metadata, sink_id);
         } else {
-            self.inner.log_warn_if_closed();
+            // The message isn't encoded, so its size is unknown.
+            self.inner.record_unsent(0, metadata);
         }
     }
 
@@ -1,6 +1,6 @@
 //! A raw channel.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::{Acquire, Release};
 use std::sync::{Arc, Weak};
@@ -10,10 +10,15 @@ use parking_lot::Mutex;
 use tracing::warn;
 
 use super::{ChannelDescriptor, ChannelId};
-use crate::log_sink_set::LogSinkSet;
+use crate::clock::ClockSource;
+use crate::context::Dispatcher;
+use crate::log_sink_set::{LogSinkSet, ERROR_LOGGING_MESSAGE};
 use crate::sink::SmallSinkVec;
+use crate::stats::StatsCounter;
 use crate::throttler::Throttler;
-use crate::{nanoseconds_since_epoch, Context, Metadata, PartialMetadata, Schema, SinkId};
+use crate::{
+    ChannelStats, Context, FoxgloveError, Metadata, PartialMetadata, Schema, Sink, SinkId,
+};
 
 /// Interval for throttled warnings.
 static WARN_THROTTLER_INTERVAL: Duration = Duration::from_secs(10);
@@ -35,10 +40,55 @@ static WARN_THROTTLER_INTERVAL: Duration = Duration::from_secs(10);
 /// You should choose a unique topic name per channel for compatibility with the Foxglove app.
 pub struct RawChannel {
     descriptor: ChannelDescriptor,
+    weak_self: Weak<RawChannel>,
     context: Weak<Context>,
+    dispatcher: Weak<Dispatcher>,
+    clock: Arc<ClockSource>,
     sinks: LogSinkSet,
+    stats: StatsCounter,
     closed: AtomicBool,
     warn_throttler: Mutex<Throttler>,
+    latch: Option<Latch>,
+}
+
+/// Retains the most recent messages logged on a latched channel.
+struct Latch {
+    capacity: usize,
+    messages: Mutex<VecDeque<(Vec<u8>, Metadata)>>,
+}
+
+/// Latched messages to be replayed to sinks that have newly subscribed to a channel.
+pub(crate) struct LatchedReplay {
+    channel: Arc<RawChannel>,
+    sinks: SmallSinkVec,
+    messages: Vec<(Vec<u8>, Metadata)>,
+}
+
+impl LatchedReplay {
+    /// Delivers the latched messages to the newly subscribed sinks.
+    ///
+    /// If the context has a background dispatcher, the messages are queued behind any messages
+    /// that were previously dispatched on the channel.
+    pub fn deliver(self, dispatcher: Option<&Dispatcher>) {
+        let sinks = Arc::new(self.sinks);
+        for (msg, metadata) in &self.messages {
+            match dispatcher {
+                Some(dispatcher) => {
+                    dispatcher.dispatch(self.channel.clone(), sinks.clone(), msg, *metadata, None)
+                }
+                None => {
+                    for sink in sinks.iter() {
+                        if let Err(err) = self
+                            .channel
+                            .log_to_sink_counting_errors(sink, msg, metadata)
+                        {
+                            warn!("{ERROR_LOGGING_MESSAGE}: {:?}", err);
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl RawChannel {
@@ -48,8 +98,9 @@ impl RawChannel {
         message_encoding: String,
         schema: Option<Schema>,
         metadata: BTreeMap<String, String>,
+        latched: usize,
     ) -> Arc<Self> {
-        Arc::new(Self {
+        Arc::new_cyclic(|weak_self| Self {
             descriptor: ChannelDescriptor::new(
                 ChannelId::next(),
                 topic,
@@ -57,10 +108,18 @@ impl RawChannel {
                 metadata,
                 schema,
             ),
+            weak_self: weak_self.clone(),
             context: Arc::downgrade(context),
+            dispatcher: context.dispatcher().map(Arc::downgrade).unwrap_or_default(),
+            clock: context.clock_source().clone(),
             sinks: LogSinkSet::new(),
+            stats: StatsCounter::default(),
             closed: AtomicBool::new(false),
             warn_throttler: Mutex::new(Throttler::new(WARN_THROTTLER_INTERVAL)),
+            latch: (latched > 0).then(|| Latch {
+                capacity: latched,
+                messages: Mutex::new(VecDeque::with_capacity(latched)),
+            }),
         })
     }
 
@@ -93,9 +152,18 @@ impl RawChannel {
         self.descriptor.metadata()
     }
 
+    /// Returns the number of recent messages retained for replay to new subscribers.
+    ///
+    /// This is zero unless the channel was built with [`ChannelBuilder::latched`][latched].
+    ///
+    /// [latched]: crate::ChannelBuilder::latched
+    pub fn latched(&self) -> usize {
+        self.latch.as_ref().map_or(0, |l| l.capacity)
+    }
+
     /// Returns true if one channel is substantially the same as the other.
     pub(crate) fn matches(&self, other: &Self) -> bool {
-        self.descriptor.matches(&other.descriptor)
+        self.descriptor.matches(&other.descriptor) && self.latched() == other.latched()
     }
 
     /// Closes the channel, removing it from the context.
@@ -125,7 +193,7 @@ impl RawChannel {
     ///
     /// A channel may be closed either by an explicit call to [`RawChannel::close`], or due to the
     /// context being dropped.
-    fn is_closed(&self) -> bool {
+    pub(crate) fn is_closed(&self) -> bool {
         self.closed.load(Acquire)
     }
 
@@ -136,9 +204,67 @@ impl RawChannel {
         }
     }
 
+    /// Returns a snapshot of the sinks that are subscribed to this channel.
+    pub(crate) fn sinks(&self) -> Arc<SmallSinkVec> {
+        self.sinks.load()
+    }
+
+    /// Returns a snapshot of throughput statistics for this channel.
+    pub fn stats(&self) -> ChannelStats {
+        self.stats.channel_stats(self.topic())
+    }
+
+    /// Logs a message to a sink, recording an error in the channel's statistics if it fails.
+    pub(crate) fn log_to_sink_counting_errors(
+        &self,
+        sink: &Arc<dyn Sink>,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        let result = sink.log(self, msg, metadata);
+        if result.is_err() {
+            self.stats.record_error();
+        }
+        result
+    }
+
     /// Updates the set of sinks that are subscribed to this channel.
-    pub(crate) fn update_sinks(&self, sinks: SmallSinkVec) {
+    ///
+    /// For latched channels, returns the retained messages to be replayed to any newly subscribed
+    /// sinks. The update and the snapshot are taken under the latch lock, so that each message is
+    /// delivered to a new subscriber exactly once, either by replay or by a concurrent log call.
+    ///
+    /// The replay is delivered after the context lock is released, so a message logged
+    /// concurrently may reach the new subscriber before the replayed messages.
+    pub(crate) fn update_sinks(&self, sinks: SmallSinkVec) -> Option<LatchedReplay> {
+        let Some(latch) = &self.latch else {
+            self.sinks.store(sinks);
+            return None;
+        };
+        let messages = latch.messages.lock();
+        let old = self.sinks.load();
+        let added: SmallSinkVec = sinks
+            .iter()
+            .filter(|s| !old.iter().any(|o| o.id() == s.id()))
+            .cloned()
+            .collect();
         self.sinks.store(sinks);
+        if added.is_empty() || messages.is_empty() {
+            return None;
+        }
+        Some(LatchedReplay {
+            channel: self.weak_self.upgrade()?,
+            sinks: added,
+            messages: messages.iter().cloned().collect(),
+        })
+    }
+
+    /// Returns true if a message logged with the given sink ID should be processed.
+    ///
+    /// Messages are processed if there are subscribed sinks, or if the message needs to be
+    /// retained by a latched channel.
+    pub(crate) fn should_log(&self, sink_id: Option<SinkId>) -> bool {
+        self.has_sinks() || (sink_id.is_none() && self.latch.is_some() && !self.is_closed())
     }
 
     /// Returns true if at least one sink is subscribed to this channel.
@@ -173,6 +299,9 @@ impl RawChannel {
 
     /// Logs a message with additional metadata.
     ///
+    /// The metadata may specify the log time, publish time, and sequence number of the message.
+    /// Fields that are omitted are filled in with defaults; see [`PartialMetadata`] for details.
+    ///
     /// The buffering behavior depends on the log sink; see [`McapWriter`][crate::McapWriter] and
     /// [`WebSocketServer`][crate::WebSocketServer] for details.
     pub fn log_with_meta(&self, msg: &[u8], opts: PartialMetadata) {
@@ -192,28 +321,64 @@ impl RawChannel {
         opts: PartialMetadata,
         sink_id: Option<SinkId>,
     ) {
-        if self.has_sinks() {
+        if self.should_log(sink_id) {
             self.log_to_sinks(msg, opts, sink_id);
         } else {
+            self.record_unsent(msg.len(), opts);
+        }
+    }
+
+    /// Records a message that is not logged to any sink, or warns if the channel is closed.
+    pub(crate) fn record_unsent(&self, bytes: usize, opts: PartialMetadata) {
+        if self.is_closed() {
             self.log_warn_if_closed();
+        } else {
+            let log_time = opts.log_time.unwrap_or_else(|| self.clock.now());
+            self.stats.record(bytes, log_time);
         }
     }
 
     /// Logs a message with additional metadata.
     pub(crate) fn log_to_sinks(&self, msg: &[u8], opts: PartialMetadata, sink_id: Option<SinkId>) {
+        let log_time = opts.log_time.unwrap_or_else(|| self.clock.now());
         let metadata = Metadata {
-            log_time: opts.log_time.unwrap_or_else(nanoseconds_since_epoch),
+            log_time,
+            publish_time: opts.publish_time.unwrap_or(log_time),
+            sequence: opts.sequence,
+        };
+        self.stats.record(msg.len(), metadata.log_time);
+
+        // Latched channels retain messages that are logged to all sinks. The sinks are loaded
+        // under the latch lock; see `update_sinks`.
+        let sinks = match &self.latch {
+            Some(latch) if sink_id.is_none() => {
+                let mut messages = latch.messages.lock();
+                if messages.len() >= latch.capacity {
+                    messages.pop_front();
+                }
+                messages.push_back((msg.to_vec(), metadata));
+                self.sinks.load()
+            }
+            _ => self.sinks.load(),
         };
+        if sinks.is_empty() {
+            return;
+        }
+
+        // If the context has a background dispatcher, hand off the message.
+        if let Some(dispatcher) = self.dispatcher.upgrade() {
+            if let Some(channel) = self.weak_self.upgrade() {
+                dispatcher.dispatch(channel, sinks, msg, metadata, sink_id);
+                return;
+            }
+        }
 
-        match sink_id {
-            Some(id) => {
-                self.sinks.for_each_filtered(
-                    |sink| sink.id() == id,
-                    |sink| sink.log(self, msg, &metadata),
-                );
+        for sink in sinks.iter() {
+            if sink_id.is_some_and(|id| id != sink.id()) {
+                continue;
             }
-            None => {
-                self.sinks.for_each(|sink| sink.log(self, msg, &metadata));
+            if let Err(err) = self.log_to_sink_counting_errors(sink, msg, &metadata) {
+                warn!("{ERROR_LOGGING_MESSAGE}: {:?}", err);
             }
         }
     }
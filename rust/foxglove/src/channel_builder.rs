@@ -11,6 +11,7 @@ pub struct ChannelBuilder {
     message_encoding: Option<String>,
     schema: Option<Schema>,
     metadata: BTreeMap<String, String>,
+    latched: usize,
     context: Arc<Context>,
 }
 
@@ -24,6 +25,7 @@ impl ChannelBuilder {
             message_encoding: None,
             schema: None,
             metadata: BTreeMap::new(),
+            latched: 0,
             context: Context::get_default(),
         }
     }
@@ -59,6 +61,27 @@ impl ChannelBuilder {
         self
     }
 
+    /// Retains the last `n` messages logged on the channel, and replays them to sinks that
+    /// subscribe later.
+    ///
+    /// This is useful for data that is logged infrequently, such as static transforms,
+    /// calibrations, maps, and robot descriptions, similar to latched topics in ROS. When a sink
+    /// subscribes to a latched channel, such as a newly-connected WebSocket client or a new MCAP
+    /// writer, it receives the retained messages with their original metadata. Messages logged
+    /// after the subscription completes are delivered after the replay, but a message logged
+    /// concurrently with the subscription may arrive first. Messages logged to a specific sink are
+    /// not retained.
+    ///
+    /// Channels with the same topic and schema are only deduplicated if they also retain the same
+    /// number of messages.
+    ///
+    /// Latched messages are copied, so this should only be used for low-rate channels with a
+    /// small value of `n`. The default is zero, which disables latching.
+    pub fn latched(mut self, n: usize) -> Self {
+        self.latched = n;
+        self
+    }
+
     /// Sets the context for this channel.
     pub fn context(mut self, ctx: &Arc<Context>) -> Self {
         self.context = ctx.clone();
@@ -76,6 +99,7 @@ impl ChannelBuilder {
                 .ok_or_else(|| FoxgloveError::MessageEncodingRequired)?,
             self.schema,
             self.metadata,
+            self.latched,
         );
         channel = self.context.add_channel(channel);
         Ok(channel)
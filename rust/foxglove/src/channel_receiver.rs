@@ -0,0 +1,396 @@
+//! In-process typed subscriptions.
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::throttler::Throttler;
+use crate::{ChannelId, Context, Decode, FoxgloveError, Metadata, RawChannel, Sink, SinkId};
+
+/// The default number of messages that may be buffered by a [`ChannelReceiver`].
+pub(crate) const DEFAULT_RECEIVER_CAPACITY: usize = 1024;
+
+/// Interval for throttled decode warnings.
+static WARN_THROTTLER_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct QueueState {
+    items: VecDeque<(Vec<u8>, Metadata)>,
+    /// Set when the sink is dropped.
+    closed: bool,
+    /// The waker for a pending [`futures::Stream::poll_next`] call.
+    waker: Option<Waker>,
+}
+
+/// A bounded message queue shared between the sink and the receiver.
+struct Queue {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    not_empty: Condvar,
+    dropped: AtomicU64,
+    topic: String,
+    warn_throttler: Mutex<Throttler>,
+}
+
+impl Queue {
+    /// Pushes a message, evicting the oldest message if the queue is full.
+    fn push(&self, msg: Vec<u8>, metadata: Metadata) {
+        let mut state = self.state.lock();
+        if state.items.len() >= self.capacity {
+            state.items.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        state.items.push_back((msg, metadata));
+        let waker = state.waker.take();
+        drop(state);
+        self.not_empty.notify_one();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Decodes a message, logging a throttled warning if it cannot be decoded.
+    fn decode<T: Decode>(&self, msg: &[u8]) -> Option<T> {
+        match T::decode(msg) {
+            Ok(decoded) => Some(decoded),
+            Err(err) => {
+                if self.warn_throttler.lock().try_acquire() {
+                    tracing::warn!("Failed to decode message on {}: {err}", self.topic);
+                }
+                None
+            }
+        }
+    }
+
+    fn close(&self) {
+        let mut state = self.state.lock();
+        state.closed = true;
+        let waker = state.waker.take();
+        drop(state);
+        self.not_empty.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// The sink that feeds a [`ChannelReceiver`].
+struct ReceiverSink {
+    id: SinkId,
+    topic: String,
+    queue: Arc<Queue>,
+}
+
+impl Sink for ReceiverSink {
+    fn id(&self) -> SinkId {
+        self.id
+    }
+
+    fn log(
+        &self,
+        _channel: &RawChannel,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        self.queue.push(msg.to_vec(), *metadata);
+        Ok(())
+    }
+
+    fn add_channels(&self, channels: &[&Arc<RawChannel>]) -> Option<Vec<ChannelId>> {
+        Some(
+            channels
+                .iter()
+                .filter(|c| c.topic() == self.topic)
+                .map(|c| c.id())
+                .collect(),
+        )
+    }
+
+    fn auto_subscribe(&self) -> bool {
+        false
+    }
+
+    fn dropped_messages(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for ReceiverSink {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+/// Receives decoded messages logged to a topic in a [`Context`].
+///
+/// Created with [`Context::subscribe`]. The receiver registers a sink which subscribes to every
+/// channel in the context with a matching topic, including channels that are created later.
+/// Messages are buffered as they are logged, and decoded when they are received. Messages that
+/// cannot be decoded as `T` are skipped.
+///
+/// The buffer is bounded. When it is full, the oldest message is discarded to make room for the
+/// new one, and the loss is reported by [`ChannelReceiver::dropped_messages`].
+///
+/// The receiver can be consumed as a blocking iterator with [`ChannelReceiver::iter`], or, with
+/// the `stream` feature, as a [`Stream`][stream]. Both end once the receiver is disconnected,
+/// which happens when the context is dropped. Dropping the receiver removes its sink from the
+/// context.
+///
+/// ```
+/// use foxglove::schemas::Log;
+/// use foxglove::Context;
+///
+/// let ctx = Context::new();
+/// let receiver = ctx.subscribe::<Log>("/log");
+///
+/// let channel = ctx.channel_builder("/log").build::<Log>();
+/// channel.log(&Log {
+///     message: "hello".into(),
+///     ..Log::default()
+/// });
+///
+/// let (log, _metadata) = receiver.try_recv().unwrap();
+/// assert_eq!(log.message, "hello");
+/// ```
+///
+/// [stream]: https://docs.rs/futures/latest/futures/stream/trait.Stream.html
+#[must_use]
+pub struct ChannelReceiver<T> {
+    sink_id: SinkId,
+    context: Weak<Context>,
+    queue: Arc<Queue>,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Debug for ChannelReceiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChannelReceiver")
+            .field("sink_id", &self.sink_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Decode> ChannelReceiver<T> {
+    pub(crate) fn new(context: &Arc<Context>, topic: String, capacity: usize) -> Self {
+        let queue = Arc::new(Queue {
+            state: Mutex::default(),
+            capacity: capacity.max(1),
+            not_empty: Condvar::new(),
+            dropped: AtomicU64::new(0),
+            topic: topic.clone(),
+            warn_throttler: Mutex::new(Throttler::new(WARN_THROTTLER_INTERVAL)),
+        });
+        let sink = Arc::new(ReceiverSink {
+            id: SinkId::next(),
+            topic,
+            queue: queue.clone(),
+        });
+        let sink_id = sink.id;
+        context.add_sink(sink);
+        Self {
+            sink_id,
+            context: Arc::downgrade(context),
+            queue,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the ID of the sink that feeds this receiver.
+    pub fn sink_id(&self) -> SinkId {
+        self.sink_id
+    }
+
+    /// Returns the number of messages that were discarded because the buffer was full.
+    pub fn dropped_messages(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns a message if one is available, without blocking.
+    pub fn try_recv(&self) -> Option<(T, Metadata)> {
+        loop {
+            let (msg, metadata) = self.queue.state.lock().items.pop_front()?;
+            if let Some(decoded) = self.queue.decode(&msg) {
+                return Some((decoded, metadata));
+            }
+        }
+    }
+
+    /// Blocks until a message is available.
+    ///
+    /// Returns `None` if the receiver is disconnected and no buffered messages remain.
+    pub fn recv(&self) -> Option<(T, Metadata)> {
+        self.recv_deadline(None)
+    }
+
+    /// Blocks until a message is available, or the timeout elapses.
+    ///
+    /// Returns `None` if the timeout elapses, or if the receiver is disconnected and no buffered
+    /// messages remain.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<(T, Metadata)> {
+        self.recv_deadline(Some(Instant::now() + timeout))
+    }
+
+    /// Returns a blocking iterator over received messages.
+    ///
+    /// The iterator ends when the receiver is disconnected.
+    pub fn iter(&self) -> impl Iterator<Item = (T, Metadata)> + '_ {
+        std::iter::from_fn(|| self.recv())
+    }
+
+    fn recv_deadline(&self, deadline: Option<Instant>) -> Option<(T, Metadata)> {
+        loop {
+            let (msg, metadata) = {
+                let mut state = self.queue.state.lock();
+                loop {
+                    if let Some(item) = state.items.pop_front() {
+                        break item;
+                    }
+                    if state.closed {
+                        return None;
+                    }
+                    match deadline {
+                        Some(deadline) => {
+                            if self
+                                .queue
+                                .not_empty
+                                .wait_until(&mut state, deadline)
+                                .timed_out()
+                            {
+                                return None;
+                            }
+                        }
+                        None => self.queue.not_empty.wait(&mut state),
+                    }
+                }
+            };
+            if let Some(decoded) = self.queue.decode(&msg) {
+                return Some((decoded, metadata));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<T: Decode> futures::Stream for ChannelReceiver<T> {
+    type Item = (T, Metadata);
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::task::Poll;
+        loop {
+            let (msg, metadata) = {
+                let mut state = self.queue.state.lock();
+                match state.items.pop_front() {
+                    Some(item) => item,
+                    None if state.closed => return Poll::Ready(None),
+                    None => {
+                        state.waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            };
+            if let Some(decoded) = self.queue.decode(&msg) {
+                return Poll::Ready(Some((decoded, metadata)));
+            }
+        }
+    }
+}
+
+impl<T> Drop for ChannelReceiver<T> {
+    fn drop(&mut self) {
+        if let Some(context) = self.context.upgrade() {
+            context.remove_sink(self.sink_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::Log;
+    use crate::ChannelBuilder;
+
+    fn log(message: &str) -> Log {
+        Log {
+            message: message.into(),
+            ..Log::default()
+        }
+    }
+
+    #[test]
+    fn test_receive_by_topic() {
+        let ctx = Context::new();
+        let early = ctx.channel_builder("/a").build::<Log>();
+        let receiver = ctx.subscribe::<Log>("/a");
+        let other = ctx.channel_builder("/b").build::<Log>();
+        let late = ChannelBuilder::new("/a")
+            .context(&ctx)
+            .message_encoding("protobuf")
+            .build_raw()
+            .unwrap();
+
+        early.log_with_meta(&log("one"), crate::PartialMetadata::with_log_time(1));
+        other.log(&log("ignored"));
+        late.log(&prost::Message::encode_to_vec(&log("two")));
+        late.log(b"\xff\xff not a log");
+
+        let (msg, metadata) = receiver.try_recv().unwrap();
+        assert_eq!(msg.message, "one");
+        assert_eq!(metadata.log_time, 1);
+        let (msg, _) = receiver.try_recv().unwrap();
+        assert_eq!(msg.message, "two");
+        assert!(receiver.try_recv().is_none());
+        assert!(receiver.recv_timeout(Duration::from_millis(1)).is_none());
+
+        // Dropping the receiver removes the sink.
+        let sink_id = receiver.sink_id();
+        assert!(ctx.sinks().iter().any(|s| s.id() == sink_id));
+        drop(receiver);
+        assert!(!ctx.sinks().iter().any(|s| s.id() == sink_id));
+    }
+
+    #[test]
+    fn test_receiver_overflow_and_disconnect() {
+        let ctx = Context::new();
+        ctx.enable_sink_stats();
+        let receiver = ctx.subscribe_with_capacity::<Log>("/a", 2);
+        let channel = ctx.channel_builder("/a").build::<Log>();
+        for message in ["one", "two", "three"] {
+            channel.log(&log(message));
+        }
+        assert_eq!(receiver.dropped_messages(), 1);
+        assert_eq!(ctx.stats().sinks[&receiver.sink_id()].dropped, 1);
+
+        let thread = std::thread::spawn(move || {
+            receiver
+                .iter()
+                .map(|(msg, _)| msg.message)
+                .collect::<Vec<_>>()
+        });
+        drop(channel);
+        drop(ctx);
+        assert_eq!(thread.join().unwrap(), vec!["two", "three"]);
+    }
+
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn test_receiver_stream() {
+        use futures::StreamExt;
+
+        let ctx = Context::new();
+        let mut receiver = ctx.subscribe::<Log>("/a");
+        let channel = ctx.channel_builder("/a").build::<Log>();
+        let task = tokio::spawn(async move { receiver.next().await.map(|(msg, _)| msg.message) });
+        tokio::task::yield_now().await;
+        channel.log(&log("hello"));
+        assert_eq!(task.await.unwrap().as_deref(), Some("hello"));
+    }
+}
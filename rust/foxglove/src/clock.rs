@@ -0,0 +1,183 @@
+//! Clock sources for default timestamps.
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+
+use crate::{nanoseconds_since_epoch, ToUnixNanos};
+
+/// A source of timestamps, in nanoseconds since the Unix epoch.
+///
+/// Each [`Context`][crate::Context] has a clock, which is used to timestamp messages that are
+/// logged without an explicit log time. By default, this is the [`SystemClock`]. When running
+/// against a simulator or replaying data, set a different clock with
+/// [`Context::set_clock`][crate::Context::set_clock], so that default timestamps follow the
+/// simulation.
+pub trait Clock: Send + Sync {
+    /// Returns the current time, in nanoseconds since the Unix epoch.
+    fn now(&self) -> u64;
+}
+
+/// A clock that reads the system wall-clock time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        nanoseconds_since_epoch()
+    }
+}
+
+/// A clock that only changes when it is explicitly set or stepped.
+///
+/// This is useful for simulations that advance in discrete steps, and for tests.
+#[derive(Debug, Default)]
+pub struct ManualClock(AtomicU64);
+
+impl ManualClock {
+    /// Creates a new manual clock with the given start time.
+    pub fn new(start: impl ToUnixNanos) -> Self {
+        Self(AtomicU64::new(start.to_unix_nanos()))
+    }
+
+    /// Sets the current time.
+    pub fn set(&self, time: impl ToUnixNanos) {
+        self.0.store(time.to_unix_nanos(), Ordering::Relaxed);
+    }
+
+    /// Advances the current time by the given duration, and returns the new time.
+    pub fn advance(&self, step: Duration) -> u64 {
+        let step = u64::try_from(step.as_nanos()).unwrap_or(u64::MAX);
+        let prev = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |t| {
+                Some(t.saturating_add(step))
+            })
+            .unwrap_or_default();
+        prev.saturating_add(step)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A clock that applies a fixed offset to another clock.
+#[derive(Clone)]
+pub struct OffsetClock {
+    inner: Arc<dyn Clock>,
+    offset: i64,
+}
+
+impl Debug for OffsetClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OffsetClock")
+            .field("offset", &self.offset)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OffsetClock {
+    /// Creates a clock that reads `inner`, offset by `offset` nanoseconds.
+    pub fn new(inner: Arc<dyn Clock>, offset: i64) -> Self {
+        Self { inner, offset }
+    }
+
+    /// Creates a clock that advances with the system clock, starting from `start`.
+    pub fn starting_at(start: impl ToUnixNanos) -> Self {
+        let offset = i128::from(start.to_unix_nanos()) - i128::from(nanoseconds_since_epoch());
+        let offset = i64::try_from(offset).unwrap_or(if offset < 0 { i64::MIN } else { i64::MAX });
+        Self::new(Arc::new(SystemClock), offset)
+    }
+
+    /// Returns the offset, in nanoseconds.
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+}
+
+impl Clock for OffsetClock {
+    fn now(&self) -> u64 {
+        self.inner.now().saturating_add_signed(self.offset)
+    }
+}
+
+/// A shared, replaceable clock.
+pub(crate) struct ClockSource(ArcSwap<Arc<dyn Clock>>);
+
+impl Default for ClockSource {
+    fn default() -> Self {
+        Self(ArcSwap::from_pointee(Arc::new(SystemClock)))
+    }
+}
+
+impl ClockSource {
+    /// Returns the current time from the clock.
+    pub fn now(&self) -> u64 {
+        self.0.load().now()
+    }
+
+    /// Returns the clock.
+    pub fn get(&self) -> Arc<dyn Clock> {
+        Arc::clone(&self.0.load())
+    }
+
+    /// Replaces the clock.
+    pub fn set(&self, clock: Arc<dyn Clock>) {
+        self.0.store(Arc::new(clock));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::RecordingSink;
+    use crate::{ChannelBuilder, Context, PartialMetadata};
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::new(100u64);
+        assert_eq!(clock.now(), 100);
+        assert_eq!(clock.advance(Duration::from_nanos(5)), 105);
+        clock.set(7u64);
+        assert_eq!(clock.now(), 7);
+
+        let offset = OffsetClock::new(Arc::new(clock), -2);
+        assert_eq!(offset.now(), 5);
+
+        let start = 1_000_000_000u64;
+        let offset = OffsetClock::starting_at(start);
+        assert!((start..start + 60_000_000_000).contains(&offset.now()));
+    }
+
+    #[test]
+    fn test_context_clock() {
+        let ctx = Context::new();
+        let clock = Arc::new(ManualClock::new(42u64));
+        ctx.set_clock(clock.clone());
+        assert_eq!(ctx.now(), 42);
+
+        let sink = Arc::new(RecordingSink::new());
+        ctx.add_sink(sink.clone());
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        ch.log(b"a");
+        clock.advance(Duration::from_nanos(1));
+        ch.log(b"b");
+        ch.log_with_meta(b"c", PartialMetadata::with_log_time(7));
+        let times: Vec<_> = sink
+            .take_messages()
+            .into_iter()
+            .map(|m| m.metadata.log_time)
+            .collect();
+        assert_eq!(times, vec![42, 43, 7]);
+    }
+}
@@ -3,8 +3,10 @@ use std::sync::Arc;
 use crate::{
     get_runtime_handle,
     sink_channel_filter::{SinkChannelFilter, SinkChannelFilterFn},
+    sink_message_filter::{SinkMessageFilter, SinkMessageFilterFn},
     websocket::{self, Server, ShutdownHandle},
-    ChannelDescriptor, Context, FoxgloveError, WebSocketServer, WebSocketServerHandle,
+    ChannelDescriptor, Context, FoxgloveError, Metadata, RawChannel, WebSocketServer,
+    WebSocketServerHandle,
 };
 
 pub use websocket::{ChannelView, Client, ClientChannel};
@@ -89,6 +91,7 @@ pub struct CloudSink {
     supported_encodings: Vec<String>,
     context: Arc<Context>,
     channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+    message_filter: Option<Arc<dyn SinkMessageFilter>>,
     runtime: Option<tokio::runtime::Handle>,
 }
 
@@ -113,6 +116,7 @@ impl Default for CloudSink {
             supported_encodings: Vec::new(),
             context: Context::get_default(),
             channel_filter: None,
+            message_filter: None,
             runtime: None,
         }
     }
@@ -188,6 +192,24 @@ impl CloudSink {
         self
     }
 
+    /// Sets a [`SinkMessageFilter`].
+    ///
+    /// The filter is consulted for each message on a subscribed channel, and returns a boolean
+    /// indicating whether the message should be logged.
+    pub fn message_filter(mut self, filter: Arc<dyn SinkMessageFilter>) -> Self {
+        self.message_filter = Some(filter);
+        self
+    }
+
+    /// Sets a message filter. See [`SinkMessageFilter`] for more information.
+    pub fn message_filter_fn(
+        mut self,
+        filter: impl Fn(&RawChannel, &[u8], &Metadata) -> bool + Sync + Send + 'static,
+    ) -> Self {
+        self.message_filter = Some(Arc::new(SinkMessageFilterFn(filter)));
+        self
+    }
+
     /// Starts the CloudSink, which maintains a connection in the background.
     ///
     /// Returns a handle that can optionally be used to manage the sink.
@@ -203,6 +225,9 @@ impl CloudSink {
         if let Some(listener) = self.listener {
             server = server.listener(Arc::new(CloudSinkListenerAdapter { listener }));
         }
+        if let Some(filter) = self.message_filter {
+            server = server.message_filter(filter);
+        }
         let handle = server.start().await?;
         Ok(CloudSinkHandle::new(handle))
     }
@@ -2,25 +2,65 @@ use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
 
 use parking_lot::RwLock;
 use smallvec::SmallVec;
 use tracing::warn;
 
-use crate::{ChannelBuilder, ChannelId, McapWriteOptions, McapWriter, RawChannel, Sink, SinkId};
-
+use crate::channel::LatchedReplay;
+use crate::channel_receiver::DEFAULT_RECEIVER_CAPACITY;
+use crate::clock::ClockSource;
+use crate::sink::MeteredSink;
+use crate::stats::{DiagnosticsHandle, DIAGNOSTICS_TOPIC};
+use crate::{
+    ChannelBuilder, ChannelId, ChannelReceiver, Clock, ContextStats, Decode, McapWriteOptions,
+    McapWriter, RawChannel, Sink, SinkId,
+};
+
+mod dispatcher;
 mod lazy_context;
+mod observer;
 mod subscriptions;
 
+pub(crate) use dispatcher::Dispatcher;
+pub use dispatcher::{DispatchOptions, OverflowPolicy};
 pub use lazy_context::LazyContext;
+pub use observer::ContextObserver;
+use observer::{ContextEvent, PendingEvents};
 use subscriptions::Subscriptions;
 
+/// A sink registered with the context.
+struct RegisteredSink {
+    /// The sink, as provided to [`Context::add_sink`].
+    sink: Arc<dyn Sink>,
+    /// A wrapper which records statistics for the sink, if sink statistics are enabled.
+    metered: Option<Arc<MeteredSink>>,
+}
+
+impl RegisteredSink {
+    /// Returns the sink to which messages are delivered.
+    fn target(&self) -> Arc<dyn Sink> {
+        match &self.metered {
+            Some(metered) => metered.clone(),
+            None => self.sink.clone(),
+        }
+    }
+}
+
 #[derive(Default)]
 struct ContextInner {
     channels: HashMap<ChannelId, Arc<RawChannel>>,
     channels_by_topic: HashMap<String, SmallVec<[Arc<RawChannel>; 1]>>,
-    sinks: HashMap<SinkId, Arc<dyn Sink>>,
+    sinks: HashMap<SinkId, RegisteredSink>,
+    /// Whether newly added sinks are wrapped to record statistics.
+    sink_stats: bool,
     subs: Subscriptions,
+    observers: Vec<Arc<dyn ContextObserver>>,
+    /// Events awaiting delivery to observers. Only recorded when there are observers.
+    events: Vec<ContextEvent>,
+    /// Latched messages awaiting replay to newly subscribed sinks.
+    replays: Vec<LatchedReplay>,
 }
 impl ContextInner {
     /// Returns the channel for the specified topic, if there is one.
@@ -31,6 +71,25 @@ impl ContextInner {
         self.channels_by_topic.get(topic)?.first()
     }
 
+    /// Records an event for delivery to observers.
+    fn push_event(&mut self, event: impl FnOnce() -> ContextEvent) {
+        if !self.observers.is_empty() {
+            self.events.push(event());
+        }
+    }
+
+    /// Takes pending events, along with the observers to which they should be delivered.
+    fn take_events(&mut self) -> Option<PendingEvents> {
+        if self.events.is_empty() {
+            None
+        } else {
+            Some(PendingEvents {
+                events: std::mem::take(&mut self.events),
+                observers: self.observers.clone(),
+            })
+        }
+    }
+
     /// Adds a channel to the context.
     fn add_channel(&mut self, channel: Arc<RawChannel>) -> Arc<RawChannel> {
         let topic = channel.topic();
@@ -52,18 +111,20 @@ impl ContextInner {
         // Add the channel to the indexes.
         self.channels.insert(channel.id(), channel.clone());
         topic_channels.push(channel.clone());
+        self.push_event(|| ContextEvent::ChannelAdded(channel.clone()));
 
         // Notify sinks of new channel. Sinks that dynamically manage subscriptions may return true
         // from `add_channel` to add a subscription synchronously.
-        for sink in self.sinks.values() {
+        for registered in self.sinks.values() {
+            let sink = &registered.sink;
             if sink.add_channel(&channel) && !sink.auto_subscribe() {
-                self.subs.subscribe_channels(sink, &[channel.id()]);
+                self.subs
+                    .subscribe_channels(&registered.target(), &[channel.id()]);
             }
         }
 
         // Connect channel sinks.
-        let sinks = self.subs.get_subscribers(channel.id());
-        channel.update_sinks(sinks);
+        self.update_channel_sinks([&channel]);
         channel
     }
 
@@ -84,15 +145,27 @@ impl ContextInner {
         // Remove subscriptions for this channel.
         self.subs.remove_channel_subscriptions(channel.id());
 
+        self.close_channel(&channel);
+        true
+    }
+
+    /// Closes a channel that has been removed from the indexes, and notifies sinks.
+    fn close_channel(&mut self, channel: &Arc<RawChannel>) {
+        if !self.observers.is_empty() {
+            for sink in channel.sinks().iter() {
+                self.events
+                    .push(ContextEvent::Unsubscribe(channel.clone(), sink.id()));
+            }
+        }
+
         // Close the channel and remove sinks.
         channel.remove_from_context();
 
         // Notify sinks of removed channel.
-        for sink in self.sinks.values() {
-            sink.remove_channel(&channel);
+        for registered in self.sinks.values() {
+            registered.sink.remove_channel(channel);
         }
-
-        true
+        self.push_event(|| ContextEvent::ChannelRemoved(channel.clone()));
     }
 
     /// Adds a sink to the context.
@@ -101,7 +174,17 @@ impl ContextInner {
         let Entry::Vacant(entry) = self.sinks.entry(sink_id) else {
             return false;
         };
-        entry.insert(sink.clone());
+        // If enabled, wrap the sink to record throughput statistics on its behalf.
+        let original = sink.clone();
+        let registered = RegisteredSink {
+            metered: self
+                .sink_stats
+                .then(|| Arc::new(MeteredSink::new(sink.clone()))),
+            sink,
+        };
+        let sink = registered.target();
+        entry.insert(registered);
+        self.push_event(|| ContextEvent::SinkAdded(original));
 
         // Notify sink of existing channels. Sinks that dynamically manage subscriptions may return
         // a set of channel IDs that they want to subscribe to immediately.
@@ -115,7 +198,7 @@ impl ContextInner {
         // Add requested subscriptions.
         if sink.auto_subscribe() {
             if self.subs.subscribe_global(sink.clone()) {
-                self.update_channel_sinks(&channels);
+                self.update_all_channel_sinks();
             }
         } else if let Some(mut ids) = ids {
             ids.retain(|id| self.channels.contains_key(id));
@@ -131,16 +214,23 @@ impl ContextInner {
     fn remove_sink(&mut self, sink_id: SinkId) -> bool {
         // Remove sink's subscriptions. If this wasn't a no-op, update channel sinks.
         if self.subs.remove_subscriber(sink_id) {
-            self.update_channel_sinks(self.channels.values());
+            self.update_all_channel_sinks();
         }
 
-        self.sinks.remove(&sink_id).is_some()
+        let Some(sink) = self.sinks.remove(&sink_id) else {
+            return false;
+        };
+        self.push_event(|| ContextEvent::SinkRemoved(sink.sink));
+        true
     }
 
     /// Subscribes a sink to the specified channels.
     fn subscribe_channels(&mut self, sink_id: SinkId, channel_ids: &[ChannelId]) {
-        if let Some(sink) = self.sinks.get(&sink_id) {
-            if self.subs.subscribe_channels(sink, channel_ids) {
+        if let Some(registered) = self.sinks.get(&sink_id) {
+            if self
+                .subs
+                .subscribe_channels(&registered.target(), channel_ids)
+            {
                 self.update_channel_sinks_by_ids(channel_ids);
             }
         }
@@ -154,33 +244,60 @@ impl ContextInner {
     }
 
     /// Updates the set of connected sinks on the specified channels, given by their IDs.
-    fn update_channel_sinks_by_ids(&self, channel_ids: &[ChannelId]) {
-        let channels = channel_ids.iter().filter_map(|id| self.channels.get(id));
-        self.update_channel_sinks(channels);
+    fn update_channel_sinks_by_ids(&mut self, channel_ids: &[ChannelId]) {
+        let channels: Vec<_> = channel_ids
+            .iter()
+            .filter_map(|id| self.channels.get(id).cloned())
+            .collect();
+        self.update_channel_sinks(&channels);
+    }
+
+    /// Updates the set of connected sinks on all channels.
+    fn update_all_channel_sinks(&mut self) {
+        let channels: Vec<_> = self.channels.values().cloned().collect();
+        self.update_channel_sinks(&channels);
     }
 
     /// Updates the set of connected sinks on the specified channels.
-    fn update_channel_sinks(&self, channels: impl IntoIterator<Item = impl AsRef<RawChannel>>) {
+    ///
+    /// If there are observers, records subscription events for any changes.
+    fn update_channel_sinks<'a>(
+        &mut self,
+        channels: impl IntoIterator<Item = &'a Arc<RawChannel>>,
+    ) {
         for channel in channels {
-            let channel = channel.as_ref();
             let sinks = self.subs.get_subscribers(channel.id());
-            channel.update_sinks(sinks);
+            if !self.observers.is_empty() {
+                let old = channel.sinks();
+                for sink in old.iter() {
+                    if !sinks.iter().any(|s| s.id() == sink.id()) {
+                        self.events
+                            .push(ContextEvent::Unsubscribe(channel.clone(), sink.id()));
+                    }
+                }
+                for sink in &sinks {
+                    if !old.iter().any(|s| s.id() == sink.id()) {
+                        self.events
+                            .push(ContextEvent::Subscribe(channel.clone(), sink.id()));
+                    }
+                }
+            }
+            if let Some(replay) = channel.update_sinks(sinks) {
+                self.replays.push(replay);
+            }
         }
     }
 
     /// Removes all channels and sinks from the context.
     fn clear(&mut self) {
-        for (_, channel) in self.channels.drain() {
-            // Close the channel and remove sinks.
-            channel.remove_from_context();
-
-            // Notify sink of removed channel.
-            for sink in self.sinks.values() {
-                sink.remove_channel(&channel);
-            }
+        let channels: Vec<_> = self.channels.drain().map(|(_, c)| c).collect();
+        for channel in &channels {
+            self.close_channel(channel);
         }
         self.channels_by_topic.clear();
-        self.sinks.clear();
+        for (_, sink) in std::mem::take(&mut self.sinks) {
+            self.push_event(|| ContextEvent::SinkRemoved(sink.sink));
+        }
         self.subs.clear();
     }
 }
@@ -222,7 +339,18 @@ impl ContextInner {
 ///     ..Log::default()
 /// });
 /// ```
-pub struct Context(RwLock<ContextInner>);
+///
+/// # Background dispatch
+///
+/// By default, logged messages are delivered to sinks synchronously, on the thread that logs the
+/// message. A context created with [`Context::with_dispatcher`] instead copies each message into a
+/// bounded queue, and delivers it to sinks on background worker threads. This keeps slow sinks,
+/// such as an MCAP writer with compression enabled, from stalling time-sensitive logging threads.
+pub struct Context {
+    inner: RwLock<ContextInner>,
+    dispatcher: Option<Arc<Dispatcher>>,
+    clock: Arc<ClockSource>,
+}
 
 impl Debug for Context {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -234,7 +362,86 @@ impl Context {
     /// Instantiates a new context.
     #[allow(clippy::new_without_default)] // avoid confusion with Context::get_default()
     pub fn new() -> Arc<Self> {
-        Arc::new(Self(RwLock::default()))
+        Arc::new(Self {
+            inner: RwLock::default(),
+            dispatcher: None,
+            clock: Arc::default(),
+        })
+    }
+
+    /// Instantiates a new context that delivers logged messages to sinks on background threads.
+    ///
+    /// Logging a message on a channel in this context copies the message into a bounded queue,
+    /// which is serviced by one or more worker threads. When the queue is full, the configured
+    /// [`OverflowPolicy`] determines whether the logging thread blocks, or whether a message is
+    /// dropped. Dropped messages are counted by [`Context::dropped_messages`].
+    ///
+    /// Use [`Context::flush`] to wait for queued messages to be delivered. Sinks must not log
+    /// messages to the same context from their [`Sink::log`] implementation when using
+    /// [`OverflowPolicy::Block`], as this may deadlock.
+    ///
+    /// ```
+    /// use foxglove::{Context, DispatchOptions, OverflowPolicy};
+    ///
+    /// let ctx = Context::with_dispatcher(
+    ///     DispatchOptions::new()
+    ///         .queue_capacity(4096)
+    ///         .overflow_policy(OverflowPolicy::DropOldest),
+    /// );
+    /// ```
+    pub fn with_dispatcher(options: DispatchOptions) -> Arc<Self> {
+        Arc::new(Self {
+            inner: RwLock::default(),
+            dispatcher: Some(Arc::new(Dispatcher::new(&options))),
+            clock: Arc::default(),
+        })
+    }
+
+    /// Returns the background dispatcher for this context, if there is one.
+    pub(crate) fn dispatcher(&self) -> Option<&Arc<Dispatcher>> {
+        self.dispatcher.as_ref()
+    }
+
+    /// Returns the clock used for default timestamps in this context.
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.get()
+    }
+
+    /// Sets the clock used for default timestamps in this context.
+    ///
+    /// Messages logged without an explicit log time are timestamped with this clock. This takes
+    /// effect immediately for all channels in the context. The default is the [`SystemClock`].
+    ///
+    /// [`SystemClock`]: crate::SystemClock
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+        self.clock.set(clock);
+    }
+
+    /// Returns the current time from this context's clock, in nanoseconds since the Unix epoch.
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    /// Returns the shared clock source for this context.
+    pub(crate) fn clock_source(&self) -> &Arc<ClockSource> {
+        &self.clock
+    }
+
+    /// Blocks until all messages logged before this call have been delivered to sinks.
+    ///
+    /// This has no effect unless the context was created with [`Context::with_dispatcher`]. It
+    /// must not be called from a [`Sink::log`] implementation.
+    pub fn flush(&self) {
+        if let Some(dispatcher) = &self.dispatcher {
+            dispatcher.flush();
+        }
+    }
+
+    /// Returns the number of messages that have been dropped because the dispatch queue was full.
+    ///
+    /// This is always zero unless the context was created with [`Context::with_dispatcher`].
+    pub fn dropped_messages(&self) -> u64 {
+        self.dispatcher.as_ref().map_or(0, |d| d.dropped())
     }
 
     /// Returns a reference to the default context.
@@ -268,12 +475,143 @@ impl Context {
         crate::WebSocketServer::new().context(self)
     }
 
+    /// Subscribes to messages logged to the specified topic, and decodes them as `T`.
+    ///
+    /// The returned [`ChannelReceiver`] buffers up to 1024 messages. See
+    /// [`Context::subscribe_with_capacity`] to configure the buffer size.
+    pub fn subscribe<T: Decode>(self: &Arc<Self>, topic: impl Into<String>) -> ChannelReceiver<T> {
+        ChannelReceiver::new(self, topic.into(), DEFAULT_RECEIVER_CAPACITY)
+    }
+
+    /// Subscribes to messages logged to the specified topic, buffering up to `capacity` messages.
+    ///
+    /// See [`Context::subscribe`].
+    pub fn subscribe_with_capacity<T: Decode>(
+        self: &Arc<Self>,
+        topic: impl Into<String>,
+        capacity: usize,
+    ) -> ChannelReceiver<T> {
+        ChannelReceiver::new(self, topic.into(), capacity)
+    }
+
     /// Returns the channel for the specified topic, if there is one.
     ///
     /// If multiple channels use the same topic name, this will return the first channel that was
     /// added to this context.
     pub fn get_channel_by_topic(&self, topic: &str) -> Option<Arc<RawChannel>> {
-        self.0.read().get_channel_by_topic(topic).cloned()
+        self.inner.read().get_channel_by_topic(topic).cloned()
+    }
+
+    /// Returns a snapshot of the channels in this context.
+    pub fn channels(&self) -> Vec<Arc<RawChannel>> {
+        self.inner.read().channels.values().cloned().collect()
+    }
+
+    /// Returns a snapshot of the sinks in this context.
+    pub fn sinks(&self) -> Vec<Arc<dyn Sink>> {
+        self.inner
+            .read()
+            .sinks
+            .values()
+            .map(|s| s.sink.clone())
+            .collect()
+    }
+
+    /// Returns a snapshot of subscriptions in this context.
+    ///
+    /// The returned map contains an entry for each channel in the context, with the IDs of the
+    /// sinks that are subscribed to it. This includes sinks that are subscribed to all channels
+    /// by virtue of [`Sink::auto_subscribe`].
+    pub fn subscriptions(&self) -> HashMap<ChannelId, Vec<SinkId>> {
+        let inner = self.inner.read();
+        inner
+            .channels
+            .keys()
+            .map(|&id| {
+                let sinks = inner.subs.get_subscribers(id);
+                (id, sinks.iter().map(|s| s.id()).collect())
+            })
+            .collect()
+    }
+
+    /// Returns a snapshot of throughput statistics for the channels and sinks in this context.
+    ///
+    /// Counters are cumulative over the lifetime of each channel and sink. Statistics for a sink
+    /// are only available while it is registered with the context, and only if it was added after
+    /// sink statistics were enabled with [`Context::enable_sink_stats`].
+    pub fn stats(&self) -> ContextStats {
+        let inner = self.inner.read();
+        ContextStats {
+            channels: inner
+                .channels
+                .values()
+                .map(|c| (c.id(), c.stats()))
+                .collect(),
+            sinks: inner
+                .sinks
+                .values()
+                .filter_map(|s| s.metered.as_ref())
+                .map(|s| (s.id(), s.stats()))
+                .collect(),
+        }
+    }
+
+    /// Enables throughput statistics for sinks added to this context from now on.
+    ///
+    /// Sink statistics are disabled by default, since recording them adds a small overhead to
+    /// each message delivered to each sink. Sinks that were already added are not affected.
+    pub fn enable_sink_stats(&self) {
+        self.inner.write().sink_stats = true;
+    }
+
+    /// Starts publishing throughput statistics on a diagnostics channel in this context.
+    ///
+    /// A snapshot of [`Context::stats`] is logged every `interval` as a series of
+    /// [`KeyValuePair`][crate::schemas::KeyValuePair] messages, one per channel and sink, on the
+    /// [`DIAGNOSTICS_TOPIC`][crate::DIAGNOSTICS_TOPIC] topic. Publishing stops when the returned
+    /// handle is dropped.
+    pub fn start_diagnostics(self: &Arc<Self>, interval: Duration) -> DiagnosticsHandle {
+        DiagnosticsHandle::start(self, DIAGNOSTICS_TOPIC, interval)
+    }
+
+    /// Registers an observer to be notified of changes to this context.
+    ///
+    /// The observer is only notified of changes made after it is registered. Use
+    /// [`Context::channels`], [`Context::sinks`], and [`Context::subscriptions`] to obtain the
+    /// current state.
+    pub fn add_observer(&self, observer: Arc<dyn ContextObserver>) {
+        self.inner.write().observers.push(observer);
+    }
+
+    /// Unregisters an observer that was previously registered with [`Context::add_observer`].
+    ///
+    /// Returns false if the observer was not registered.
+    pub fn remove_observer(&self, observer: &Arc<dyn ContextObserver>) -> bool {
+        let mut inner = self.inner.write();
+        let len = inner.observers.len();
+        inner.observers.retain(|o| !Arc::ptr_eq(o, observer));
+        inner.observers.len() != len
+    }
+
+    /// Applies a mutation to the context, and then replays latched messages and notifies
+    /// observers of any resulting events once the lock has been released.
+    fn update<R>(&self, f: impl FnOnce(&mut ContextInner) -> R) -> R {
+        let (result, replays, events) = {
+            let mut inner = self.inner.write();
+            let result = f(&mut inner);
+            (
+                result,
+                std::mem::take(&mut inner.replays),
+                inner.take_events(),
+            )
+        };
+        for replay in replays {
+            replay.deliver(self.dispatcher.as_deref());
+        }
+        if let Some(events) = events {
+            events.deliver();
+        }
+        result
     }
 
     /// Adds a channel to the context, or returns a channel with the same topic and schema.
@@ -282,7 +620,7 @@ impl Context {
     /// consistent. Publicly, the only way to add a channel to a context is by constructing it via
     /// a [`ChannelBuilder`][crate::ChannelBuilder].
     pub(crate) fn add_channel(&self, channel: Arc<RawChannel>) -> Arc<RawChannel> {
-        self.0.write().add_channel(channel)
+        self.update(|inner| inner.add_channel(channel))
     }
 
     /// Removes a channel from the context.
@@ -291,7 +629,7 @@ impl Context {
     /// consistent. Publicly, the only way to remove a channel from a context is by calling
     /// [`RawChannel::close`], or by dropping the context entirely.
     pub(crate) fn remove_channel(&self, channel_id: ChannelId) -> bool {
-        self.0.write().remove_channel(channel_id)
+        self.update(|inner| inner.remove_channel(channel_id))
     }
 
     /// Adds a sink to the context.
@@ -302,36 +640,32 @@ impl Context {
     /// present and future channels on the context. Otherwise, the sink is expected to manage its
     /// subscriptions dynamically with [`Context::subscribe_channels`] and
     /// [`Context::unsubscribe_channels`].
-    #[doc(hidden)] // Hidden until Sink is public
     pub fn add_sink(&self, sink: Arc<dyn Sink>) -> bool {
-        self.0.write().add_sink(sink)
+        self.update(|inner| inner.add_sink(sink))
     }
 
     /// Removes a sink from the context.
-    #[doc(hidden)] // Hidden until Sink is public.
     pub fn remove_sink(&self, sink_id: SinkId) -> bool {
-        self.0.write().remove_sink(sink_id)
+        self.update(|inner| inner.remove_sink(sink_id))
     }
 
     /// Subscribes a sink to the specified channels.
     ///
     /// This method has no effect for sinks that return true from [`Sink::auto_subscribe`].
-    #[doc(hidden)] // Hidden until Sink is public.
     pub fn subscribe_channels(&self, sink_id: SinkId, channel_ids: &[ChannelId]) {
-        self.0.write().subscribe_channels(sink_id, channel_ids);
+        self.update(|inner| inner.subscribe_channels(sink_id, channel_ids));
     }
 
     /// Unsubscribes a sink from the specified channels.
     ///
     /// This method has no effect for sinks that return true from [`Sink::auto_subscribe`].
-    #[doc(hidden)] // Hidden until Sink is public.
     pub fn unsubscribe_channels(&self, sink_id: SinkId, channel_ids: &[ChannelId]) {
-        self.0.write().unsubscribe_channels(sink_id, channel_ids);
+        self.update(|inner| inner.unsubscribe_channels(sink_id, channel_ids));
     }
 
     /// Removes all channels and sinks from the context.
     pub(crate) fn clear(&self) {
-        self.0.write().clear();
+        self.update(|inner| inner.clear());
     }
 }
 
@@ -346,7 +680,7 @@ mod tests {
     use crate::context::*;
     use crate::log_sink_set::ERROR_LOGGING_MESSAGE;
     use crate::testutil::{ErrorSink, MockSink, RecordingSink};
-    use crate::{nanoseconds_since_epoch, PartialMetadata, RawChannel, Schema};
+    use crate::{nanoseconds_since_epoch, PartialMetadata, RawChannel, Schema, Sink, SinkId};
     use crate::{ChannelBuilder, FoxgloveError};
     use std::sync::Arc;
     use tracing_test::traced_test;
@@ -444,9 +778,7 @@ mod tests {
 
         let channel = new_test_channel(&ctx, "topic").unwrap();
         let msg = b"test_message";
-        let opts = PartialMetadata {
-            log_time: Some(nanoseconds_since_epoch()),
-        };
+        let opts = PartialMetadata::with_log_time(nanoseconds_since_epoch());
 
         channel.log_with_meta(msg, opts);
         assert!(logs_contain(ERROR_LOGGING_MESSAGE));
@@ -475,7 +807,7 @@ mod tests {
         let ctx = Context::new();
         let ch = new_test_channel(&ctx, "topic").unwrap();
         assert!(ctx.remove_channel(ch.id()));
-        assert!(ctx.0.read().channels.is_empty());
+        assert!(ctx.inner.read().channels.is_empty());
     }
 
     #[test]
@@ -704,18 +1036,169 @@ mod tests {
 
         // Actual matches.
         let c1 = new_test_channel(&ctx, "dupe").unwrap();
-        assert_eq!(ctx.0.read().channels.len(), 4);
+        assert_eq!(ctx.inner.read().channels.len(), 4);
 
         // Reuses the matching channel.
         let c2 = new_test_channel(&ctx, "dupe").unwrap();
         assert_eq!(c1.id(), c2.id());
         assert_eq!(Arc::as_ptr(&c1), Arc::as_ptr(&c2));
-        assert_eq!(ctx.0.read().channels.len(), 4);
+        assert_eq!(ctx.inner.read().channels.len(), 4);
 
         // No matches, creates a new channel.
         assert!(ctx.remove_channel(c1.id()));
-        assert_eq!(ctx.0.read().channels.len(), 3);
+        assert_eq!(ctx.inner.read().channels.len(), 3);
         let _ = new_test_channel(&ctx, "dupe").unwrap();
-        assert_eq!(ctx.0.read().channels.len(), 4);
+        assert_eq!(ctx.inner.read().channels.len(), 4);
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let ctx = Context::new();
+        let c1 = new_test_channel(&ctx, "t1").unwrap();
+        let c2 = new_test_channel(&ctx, "t2").unwrap();
+        let s1 = Arc::new(RecordingSink::new());
+        let s2 = Arc::new(RecordingSink::new().auto_subscribe(false));
+        ctx.add_sink(s1.clone());
+        ctx.add_sink(s2.clone());
+        ctx.subscribe_channels(s2.id(), &[c2.id()]);
+
+        let mut channels: Vec<_> = ctx.channels().iter().map(|c| c.id()).collect();
+        channels.sort_by_key(|&id| u64::from(id));
+        assert_eq!(channels, vec![c1.id(), c2.id()]);
+
+        let mut sinks: Vec<_> = ctx.sinks().iter().map(|s| s.id()).collect();
+        sinks.sort();
+        assert_eq!(sinks, vec![s1.id(), s2.id()]);
+
+        let mut subs = ctx.subscriptions();
+        assert_eq!(subs.len(), 2);
+        assert_eq!(subs.remove(&c1.id()).unwrap(), vec![s1.id()]);
+        let mut c2_subs = subs.remove(&c2.id()).unwrap();
+        c2_subs.sort();
+        assert_eq!(c2_subs, vec![s1.id(), s2.id()]);
+    }
+
+    #[test]
+    fn test_observer() {
+        #[derive(Default)]
+        struct Recorder(parking_lot::Mutex<Vec<String>>);
+        impl Recorder {
+            fn push(&self, event: String) {
+                self.0.lock().push(event);
+            }
+            fn take(&self) -> Vec<String> {
+                std::mem::take(&mut self.0.lock())
+            }
+        }
+        impl ContextObserver for Recorder {
+            fn on_channel_added(&self, channel: &Arc<RawChannel>) {
+                self.push(format!("channel+ {}", channel.topic()));
+            }
+            fn on_channel_removed(&self, channel: &Arc<RawChannel>) {
+                self.push(format!("channel- {}", channel.topic()));
+            }
+            fn on_sink_added(&self, _sink: &Arc<dyn Sink>) {
+                self.push("sink+".into());
+            }
+            fn on_sink_removed(&self, _sink: &Arc<dyn Sink>) {
+                self.push("sink-".into());
+            }
+            fn on_subscribe(&self, channel: &Arc<RawChannel>, _sink_id: SinkId) {
+                self.push(format!("sub+ {}", channel.topic()));
+            }
+            fn on_unsubscribe(&self, channel: &Arc<RawChannel>, _sink_id: SinkId) {
+                self.push(format!("sub- {}", channel.topic()));
+            }
+        }
+
+        let ctx = Context::new();
+        let recorder = Arc::new(Recorder::default());
+        let observer: Arc<dyn ContextObserver> = recorder.clone();
+        ctx.add_observer(observer.clone());
+
+        let c1 = new_test_channel(&ctx, "t1").unwrap();
+        let s1 = Arc::new(RecordingSink::new());
+        ctx.add_sink(s1.clone());
+        let c2 = new_test_channel(&ctx, "t2").unwrap();
+        assert_eq!(
+            recorder.take(),
+            vec!["channel+ t1", "sink+", "sub+ t1", "channel+ t2", "sub+ t2"]
+        );
+
+        let s2 = Arc::new(RecordingSink::new().auto_subscribe(false));
+        ctx.add_sink(s2.clone());
+        ctx.subscribe_channels(s2.id(), &[c1.id()]);
+        // Redundant subscriptions are not reported.
+        ctx.subscribe_channels(s2.id(), &[c1.id()]);
+        ctx.unsubscribe_channels(s2.id(), &[c1.id()]);
+        assert_eq!(recorder.take(), vec!["sink+", "sub+ t1", "sub- t1"]);
+
+        // Removals report unsubscriptions first.
+        c2.close();
+        ctx.remove_sink(s1.id());
+        assert_eq!(
+            recorder.take(),
+            vec!["sub- t2", "channel- t2", "sub- t1", "sink-"]
+        );
+
+        assert!(ctx.remove_observer(&observer));
+        assert!(!ctx.remove_observer(&observer));
+        ctx.remove_sink(s2.id());
+        assert!(recorder.take().is_empty());
+    }
+
+    #[test]
+    fn test_latched_channel() {
+        let ctx = Context::new();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .latched(2)
+            .build_raw()
+            .unwrap();
+        assert_eq!(ch.latched(), 2);
+
+        // Messages are retained even when there are no sinks.
+        ch.log_with_meta(b"1", PartialMetadata::with_log_time(1));
+        ch.log_with_meta(b"2", PartialMetadata::with_log_time(2));
+        ch.log_with_meta(b"3", PartialMetadata::with_log_time(3));
+
+        let s1 = Arc::new(RecordingSink::new());
+        ctx.add_sink(s1.clone());
+        ch.log_with_meta(b"4", PartialMetadata::with_log_time(4));
+        // Messages logged to a specific sink are not retained.
+        ch.log_to_sink(b"x", Some(s1.id()));
+        let msgs = s1.take_messages();
+        let payloads: Vec<_> = msgs.iter().map(|m| m.msg.as_slice()).collect();
+        assert_eq!(payloads, vec![b"2", b"3", b"4", b"x"]);
+        // Replayed messages retain their original metadata.
+        assert_eq!(msgs[0].metadata.log_time, 2);
+
+        // Dynamic subscribers receive the retained messages when they subscribe, but not when
+        // they re-subscribe redundantly.
+        let s2 = Arc::new(RecordingSink::new().auto_subscribe(false));
+        ctx.add_sink(s2.clone());
+        assert!(s2.take_messages().is_empty());
+        ctx.subscribe_channels(s2.id(), &[ch.id()]);
+        ctx.subscribe_channels(s2.id(), &[ch.id()]);
+        let msgs: Vec<_> = s2.take_messages().into_iter().map(|m| m.msg).collect();
+        assert_eq!(msgs, vec![b"3".to_vec(), b"4".to_vec()]);
+        assert!(s1.take_messages().is_empty());
+
+        // Channels are only deduplicated if they have the same latch capacity.
+        let dup = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .latched(2)
+            .build_raw()
+            .unwrap();
+        assert!(Arc::ptr_eq(&ch, &dup));
+        let other = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        assert!(!Arc::ptr_eq(&ch, &other));
+        assert_eq!(other.latched(), 0);
     }
 }
@@ -0,0 +1,532 @@
+//! Background message dispatch.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::log_sink_set::ERROR_LOGGING_MESSAGE;
+use crate::sink::SmallSinkVec;
+use crate::{Metadata, RawChannel, SinkId};
+
+/// The default number of messages that may be queued for each worker thread.
+const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+thread_local! {
+    /// Set on dispatcher worker threads.
+    static IS_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The policy applied when a message is logged while the dispatch queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Block the logging thread until there is space in the queue.
+    ///
+    /// Messages logged from a dispatcher worker thread, for example by a sink, are dropped instead,
+    /// since the worker may be the only thread that can make room in the queue.
+    #[default]
+    Block,
+    /// Discard the message being logged.
+    DropNewest,
+    /// Discard the oldest message in the queue to make room for the message being logged.
+    DropOldest,
+}
+
+/// Options for background message dispatch.
+///
+/// See [`Context::with_dispatcher`][crate::Context::with_dispatcher] for details.
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct DispatchOptions {
+    queue_capacity: usize,
+    workers: usize,
+    overflow_policy: OverflowPolicy,
+}
+
+impl Default for DispatchOptions {
+    fn default() -> Self {
+        Self {
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            workers: 1,
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+impl DispatchOptions {
+    /// Creates a new set of dispatch options with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of messages that may be queued for each worker thread.
+    ///
+    /// The default is 1024. The capacity must be at least 1.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets the number of worker threads that deliver messages to sinks.
+    ///
+    /// Messages on a given channel are always delivered by the same worker, so the order of
+    /// messages within a channel is preserved. The default is 1. The number of workers must be at
+    /// least 1.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Sets the policy applied when a message is logged while the queue is full.
+    ///
+    /// The default is [`OverflowPolicy::Block`].
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+}
+
+/// A message awaiting delivery.
+struct Envelope {
+    channel: Arc<RawChannel>,
+    sinks: Arc<SmallSinkVec>,
+    msg: Vec<u8>,
+    metadata: Metadata,
+    sink_id: Option<SinkId>,
+}
+
+impl Envelope {
+    /// Delivers the message to its sinks.
+    fn deliver(self) {
+        for sink in self.sinks.iter() {
+            if self.sink_id.is_some_and(|id| id != sink.id()) {
+                continue;
+            }
+            if let Err(err) =
+                self.channel
+                    .log_to_sink_counting_errors(sink, &self.msg, &self.metadata)
+            {
+                tracing::warn!("{ERROR_LOGGING_MESSAGE}: {:?}", err);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    items: VecDeque<Envelope>,
+    /// The number of messages that have been accepted into the queue.
+    accepted: u64,
+    /// The number of accepted messages that have since been delivered or evicted.
+    retired: u64,
+    shutdown: bool,
+}
+
+/// A bounded queue serviced by a single worker thread.
+struct Queue {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    /// Signaled when a message is pushed, or on shutdown.
+    not_empty: Condvar,
+    /// Signaled when a message is popped, or on shutdown.
+    not_full: Condvar,
+    /// Signaled when a message is retired.
+    retired: Condvar,
+}
+
+impl Queue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::default(),
+            capacity,
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            retired: Condvar::new(),
+        }
+    }
+
+    /// Pushes a message onto the queue, applying the overflow policy if the queue is full.
+    ///
+    /// Returns false if a message was dropped.
+    fn push(&self, envelope: Envelope, policy: OverflowPolicy) -> bool {
+        let mut state = self.state.lock();
+        let mut evicted = None;
+        if state.items.len() >= self.capacity {
+            match policy {
+                // Blocking a worker could deadlock if it is waiting on its own queue.
+                OverflowPolicy::Block if IS_WORKER.get() => return false,
+                OverflowPolicy::Block => {
+                    while state.items.len() >= self.capacity && !state.shutdown {
+                        self.not_full.wait(&mut state);
+                    }
+                    if state.shutdown {
+                        return false;
+                    }
+                }
+                OverflowPolicy::DropNewest => return false,
+                OverflowPolicy::DropOldest => {
+                    evicted = state.items.pop_front();
+                    state.retired += 1;
+                    self.retired.notify_all();
+                }
+            }
+        }
+        state.items.push_back(envelope);
+        state.accepted += 1;
+        self.not_empty.notify_one();
+        drop(state);
+
+        // Release the evicted message outside of the lock.
+        evicted.is_none()
+    }
+
+    /// Blocks until all messages accepted before this call have been retired.
+    fn flush(&self) {
+        let mut state = self.state.lock();
+        let target = state.accepted;
+        while state.retired < target {
+            self.retired.wait(&mut state);
+        }
+    }
+
+    /// Signals the worker to exit once the queue has been drained.
+    fn shutdown(&self) {
+        self.state.lock().shutdown = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    /// Worker thread main loop.
+    fn run(&self) {
+        IS_WORKER.set(true);
+        let mut state = self.state.lock();
+        loop {
+            if let Some(envelope) = state.items.pop_front() {
+                self.not_full.notify_one();
+                parking_lot::MutexGuard::unlocked(&mut state, || envelope.deliver());
+                state.retired += 1;
+                self.retired.notify_all();
+            } else if state.shutdown {
+                break;
+            } else {
+                self.not_empty.wait(&mut state);
+            }
+        }
+    }
+}
+
+/// Delivers logged messages to sinks on a pool of worker threads.
+pub(crate) struct Dispatcher {
+    queues: Vec<Arc<Queue>>,
+    workers: Vec<JoinHandle<()>>,
+    overflow_policy: OverflowPolicy,
+    dropped: AtomicU64,
+}
+
+impl Dispatcher {
+    pub fn new(options: &DispatchOptions) -> Self {
+        let queues: Vec<_> = (0..options.workers)
+            .map(|_| Arc::new(Queue::new(options.queue_capacity)))
+            .collect();
+        let workers = queues
+            .iter()
+            .enumerate()
+            .map(|(i, queue)| {
+                let queue = queue.clone();
+                std::thread::Builder::new()
+                    .name(format!("foxglove-dispatch-{i}"))
+                    .spawn(move || queue.run())
+                    .expect("failed to spawn dispatch worker")
+            })
+            .collect();
+        Self {
+            queues,
+            workers,
+            overflow_policy: options.overflow_policy,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Enqueues a message for delivery to the provided set of sinks.
+    ///
+    /// If a sink ID is provided, only that sink will receive the message.
+    pub fn dispatch(
+        &self,
+        channel: Arc<RawChannel>,
+        sinks: Arc<SmallSinkVec>,
+        msg: &[u8],
+        metadata: Metadata,
+        sink_id: Option<SinkId>,
+    ) {
+        let index = u64::from(channel.id()) as usize % self.queues.len();
+        let envelope = Envelope {
+            channel,
+            sinks,
+            msg: msg.to_vec(),
+            metadata,
+            sink_id,
+        };
+        if !self.queues[index].push(envelope, self.overflow_policy) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Blocks until all messages dispatched before this call have been delivered.
+    pub fn flush(&self) {
+        for queue in &self.queues {
+            queue.flush();
+        }
+    }
+
+    /// Returns the number of messages that have been dropped due to queue overflow.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        for queue in &self.queues {
+            queue.shutdown();
+        }
+        let current = std::thread::current().id();
+        for worker in self.workers.drain(..) {
+            // The dispatcher may be dropped from one of its own workers, if a sink holds the last
+            // reference to the context. Don't attempt to join ourselves.
+            if worker.thread().id() != current {
+                let _ = worker.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testutil::RecordingSink;
+    use crate::{ChannelBuilder, Context, FnSink};
+
+    fn new_channel(ctx: &Arc<Context>, topic: &str) -> Arc<RawChannel> {
+        ChannelBuilder::new(topic)
+            .context(ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_dispatch_preserves_channel_order() {
+        let ctx = Context::with_dispatcher(DispatchOptions::new().workers(3));
+        let sink = Arc::new(RecordingSink::new());
+        ctx.add_sink(sink.clone());
+
+        let channels: Vec<_> = (0..4)
+            .map(|i| new_channel(&ctx, &format!("/{i}")))
+            .collect();
+        for i in 0..100u8 {
+            for ch in &channels {
+                ch.log(&[i]);
+            }
+        }
+        ctx.flush();
+
+        let msgs = sink.take_messages();
+        assert_eq!(msgs.len(), 400);
+        for ch in &channels {
+            let payloads: Vec<_> = msgs
+                .iter()
+                .filter(|m| m.channel_id == ch.id())
+                .map(|m| m.msg[0])
+                .collect();
+            assert_eq!(payloads, (0..100).collect::<Vec<_>>());
+        }
+        assert_eq!(ctx.dropped_messages(), 0);
+    }
+
+    /// Returns a sink that blocks on the first message until `release` is dropped, along with a
+    /// receiver that is signaled when the sink starts blocking.
+    fn blocking_sink(
+        ctx: &Arc<Context>,
+    ) -> (Arc<RecordingSink>, mpsc::Sender<()>, mpsc::Receiver<()>) {
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let (blocked_tx, blocked_rx) = mpsc::channel();
+        let release_rx = Mutex::new(Some(release_rx));
+        let gate = Arc::new(FnSink::new(move |_, _, _| {
+            if let Some(rx) = release_rx.lock().take() {
+                blocked_tx.send(()).unwrap();
+                let _ = rx.recv();
+            }
+            Ok(())
+        }));
+        let recorder = Arc::new(RecordingSink::new());
+        ctx.add_sink(gate);
+        ctx.add_sink(recorder.clone());
+        (recorder, release_tx, blocked_rx)
+    }
+
+    #[test]
+    fn test_dispatch_block() {
+        let ctx = Context::with_dispatcher(
+            DispatchOptions::new()
+                .queue_capacity(1)
+                .overflow_policy(OverflowPolicy::Block),
+        );
+        let (recorder, release, blocked) = blocking_sink(&ctx);
+        let ch = new_channel(&ctx, "/t");
+
+        // The first message occupies the worker; the second fills the queue.
+        ch.log(b"0");
+        blocked.recv_timeout(Duration::from_secs(5)).unwrap();
+        ch.log(b"1");
+
+        // The third message blocks the logging thread until the worker makes room.
+        let (logged_tx, logged_rx) = mpsc::channel();
+        let logger = std::thread::spawn({
+            let ch = ch.clone();
+            move || {
+                ch.log(b"2");
+                logged_tx.send(()).unwrap();
+            }
+        });
+        assert_eq!(
+            logged_rx.recv_timeout(Duration::from_millis(50)),
+            Err(mpsc::RecvTimeoutError::Timeout)
+        );
+
+        drop(release);
+        logged_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        logger.join().unwrap();
+        ctx.flush();
+        assert_eq!(ctx.dropped_messages(), 0);
+        let msgs: Vec<_> = recorder
+            .take_messages()
+            .into_iter()
+            .map(|m| m.msg)
+            .collect();
+        assert_eq!(msgs, vec![b"0", b"1", b"2"]);
+    }
+
+    #[test]
+    fn test_dispatch_block_from_worker() {
+        let ctx = Context::with_dispatcher(
+            DispatchOptions::new()
+                .queue_capacity(1)
+                .overflow_policy(OverflowPolicy::Block),
+        );
+        let ch = new_channel(&ctx, "/t");
+        let echo = new_channel(&ctx, "/echo");
+
+        // A sink that blocks on the first message, and then logs each message back into the same
+        // context from the worker thread.
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let (blocked_tx, blocked_rx) = mpsc::channel();
+        let release_rx = Mutex::new(Some(release_rx));
+        ctx.add_sink(Arc::new(FnSink::new({
+            let echo = echo.clone();
+            move |channel, msg, _| {
+                if channel.id() != echo.id() {
+                    if let Some(rx) = release_rx.lock().take() {
+                        blocked_tx.send(()).unwrap();
+                        let _ = rx.recv();
+                    }
+                    echo.log(msg);
+                }
+                Ok(())
+            }
+        })));
+        let recorder = Arc::new(RecordingSink::new());
+        ctx.add_sink(recorder.clone());
+
+        // The first message occupies the worker; the second fills the queue, so the echo of the
+        // first message is dropped rather than deadlocking the worker.
+        ch.log(b"0");
+        blocked_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        ch.log(b"1");
+        drop(release_tx);
+
+        let (flushed_tx, flushed_rx) = mpsc::channel();
+        std::thread::spawn({
+            let ctx = ctx.clone();
+            move || {
+                ctx.flush();
+                flushed_tx.send(()).unwrap();
+            }
+        });
+        flushed_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(ctx.dropped_messages(), 1);
+        let msgs: Vec<_> = recorder
+            .take_messages()
+            .into_iter()
+            .map(|m| (m.channel_id == echo.id(), m.msg))
+            .collect();
+        assert_eq!(
+            msgs,
+            vec![
+                (false, b"0".to_vec()),
+                (false, b"1".to_vec()),
+                (true, b"1".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dispatch_drop_newest() {
+        let ctx = Context::with_dispatcher(
+            DispatchOptions::new()
+                .queue_capacity(2)
+                .overflow_policy(OverflowPolicy::DropNewest),
+        );
+        let (recorder, release, blocked) = blocking_sink(&ctx);
+        let ch = new_channel(&ctx, "/t");
+
+        // The first message occupies the worker; the next two fill the queue.
+        ch.log(b"0");
+        blocked.recv_timeout(Duration::from_secs(5)).unwrap();
+        for msg in [b"1", b"2", b"3", b"4"] {
+            ch.log(msg);
+        }
+        assert_eq!(ctx.dropped_messages(), 2);
+
+        drop(release);
+        ctx.flush();
+        let msgs: Vec<_> = recorder
+            .take_messages()
+            .into_iter()
+            .map(|m| m.msg)
+            .collect();
+        assert_eq!(msgs, vec![b"0", b"1", b"2"]);
+    }
+
+    #[test]
+    fn test_dispatch_drop_oldest() {
+        let ctx = Context::with_dispatcher(
+            DispatchOptions::new()
+                .queue_capacity(2)
+                .overflow_policy(OverflowPolicy::DropOldest),
+        );
+        let (recorder, release, blocked) = blocking_sink(&ctx);
+        let ch = new_channel(&ctx, "/t");
+
+        ch.log(b"0");
+        blocked.recv_timeout(Duration::from_secs(5)).unwrap();
+        for msg in [b"1", b"2", b"3", b"4"] {
+            ch.log(msg);
+        }
+        assert_eq!(ctx.dropped_messages(), 2);
+
+        drop(release);
+        ctx.flush();
+        let msgs: Vec<_> = recorder
+            .take_messages()
+            .into_iter()
+            .map(|m| m.msg)
+            .collect();
+        assert_eq!(msgs, vec![b"0", b"3", b"4"]);
+    }
+}
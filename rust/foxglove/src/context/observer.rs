@@ -0,0 +1,83 @@
+//! Context lifecycle observers.
+
+use std::sync::Arc;
+
+use crate::{RawChannel, Sink, SinkId};
+
+/// Receives notifications about changes to a [`Context`][crate::Context].
+///
+/// Observers are registered with [`Context::add_observer`][crate::Context::add_observer]. All
+/// methods have default no-op implementations, so implementors only need to override the events
+/// they're interested in.
+///
+/// Notifications are delivered synchronously, on the thread that made the change, after the
+/// context's internal lock has been released. It is therefore safe to query the context from a
+/// callback. Notifications for a single change are delivered in order, but notifications for
+/// concurrent changes made on different threads may interleave.
+///
+/// When a channel or sink is removed, an unsubscribe notification is delivered for each of its
+/// subscriptions before the removal notification.
+///
+/// Observers should not hold a strong reference to the context they observe, since this would
+/// prevent the context from being dropped.
+pub trait ContextObserver: Send + Sync {
+    /// Called when a channel is added to the context.
+    fn on_channel_added(&self, _channel: &Arc<RawChannel>) {}
+
+    /// Called when a channel is removed from the context.
+    fn on_channel_removed(&self, _channel: &Arc<RawChannel>) {}
+
+    /// Called when a sink is added to the context.
+    fn on_sink_added(&self, _sink: &Arc<dyn Sink>) {}
+
+    /// Called when a sink is removed from the context.
+    fn on_sink_removed(&self, _sink: &Arc<dyn Sink>) {}
+
+    /// Called when a sink subscribes to a channel.
+    fn on_subscribe(&self, _channel: &Arc<RawChannel>, _sink_id: SinkId) {}
+
+    /// Called when a sink unsubscribes from a channel.
+    fn on_unsubscribe(&self, _channel: &Arc<RawChannel>, _sink_id: SinkId) {}
+}
+
+/// A context change, recorded while the context is locked, to be delivered to observers once the
+/// lock has been released.
+pub(super) enum ContextEvent {
+    ChannelAdded(Arc<RawChannel>),
+    ChannelRemoved(Arc<RawChannel>),
+    SinkAdded(Arc<dyn Sink>),
+    SinkRemoved(Arc<dyn Sink>),
+    Subscribe(Arc<RawChannel>, SinkId),
+    Unsubscribe(Arc<RawChannel>, SinkId),
+}
+
+/// Events awaiting delivery to a set of observers.
+pub(super) struct PendingEvents {
+    pub events: Vec<ContextEvent>,
+    pub observers: Vec<Arc<dyn ContextObserver>>,
+}
+
+impl PendingEvents {
+    /// Delivers each event to each observer, in order.
+    pub fn deliver(self) {
+        for event in &self.events {
+            for observer in &self.observers {
+                event.notify(observer.as_ref());
+            }
+        }
+    }
+}
+
+impl ContextEvent {
+    /// Delivers the event to an observer.
+    pub fn notify(&self, observer: &dyn ContextObserver) {
+        match self {
+            Self::ChannelAdded(channel) => observer.on_channel_added(channel),
+            Self::ChannelRemoved(channel) => observer.on_channel_removed(channel),
+            Self::SinkAdded(sink) => observer.on_sink_added(sink),
+            Self::SinkRemoved(sink) => observer.on_sink_removed(sink),
+            Self::Subscribe(channel, sink_id) => observer.on_subscribe(channel, *sink_id),
+            Self::Unsubscribe(channel, sink_id) => observer.on_unsubscribe(channel, *sink_id),
+        }
+    }
+}
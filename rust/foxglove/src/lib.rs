@@ -269,6 +269,28 @@
 //! # }
 //! ```
 //!
+//! ### Custom sinks
+//!
+//! You can log to your own destinations by implementing the [`Sink`] trait, and registering the
+//! sink with [`Context::add_sink`]. For simple cases, the SDK provides a few composable adapters:
+//!
+//! - [`FnSink`] invokes a closure for each logged message.
+//! - [`FilteredSink`] restricts an inner sink to channels that pass a [`SinkChannelFilter`].
+//! - [`TeeSink`] forwards each message to several child sinks.
+//!
+//! ```
+//! use std::sync::Arc;
+//! use foxglove::{Context, FilteredSink, FnSink};
+//!
+//! let ctx = Context::new();
+//! let sink = FnSink::new(|channel, msg, _metadata| {
+//!     println!("{}: {} bytes", channel.topic(), msg.len());
+//!     Ok(())
+//! });
+//! let sink = FilteredSink::from_fn(Arc::new(sink), |channel| channel.topic() == "/log");
+//! ctx.add_sink(Arc::new(sink));
+//! ```
+//!
 //! # Feature flags
 //!
 //! The Foxglove SDK defines the following feature flags:
@@ -309,6 +331,8 @@ use thiserror::Error;
 mod app_url;
 mod channel;
 mod channel_builder;
+mod channel_receiver;
+mod clock;
 mod context;
 pub mod convert;
 mod decode;
@@ -317,6 +341,7 @@ pub mod library_version;
 #[doc(hidden)]
 pub mod log_macro;
 mod log_sink_set;
+mod mcap_player;
 mod mcap_writer;
 mod metadata;
 #[doc(hidden)]
@@ -327,6 +352,8 @@ pub mod schemas;
 mod schemas_wkt;
 mod sink;
 mod sink_channel_filter;
+mod sink_message_filter;
+mod stats;
 
 #[cfg(test)]
 mod tests;
@@ -338,6 +365,11 @@ mod time;
 #[cfg(feature = "stream")]
 pub mod stream;
 
+#[cfg(feature = "tracing-subscriber")]
+mod tracing_layer;
+#[cfg(feature = "tracing-subscriber")]
+pub use tracing_layer::{LogLayer, LogLayerBuilder};
+
 #[cfg(feature = "img2yuv-core")]
 #[allow(unused)]
 mod img2yuv;
@@ -347,23 +379,32 @@ pub use app_url::AppUrl;
 pub use bytes;
 pub use channel::{Channel, ChannelDescriptor, ChannelId, LazyChannel, LazyRawChannel, RawChannel};
 pub use channel_builder::ChannelBuilder;
-pub use context::{Context, LazyContext};
-#[doc(hidden)]
+pub use channel_receiver::ChannelReceiver;
+pub use clock::{Clock, ManualClock, OffsetClock, SystemClock};
+pub use context::{Context, ContextObserver, DispatchOptions, LazyContext, OverflowPolicy};
 pub use decode::Decode;
 pub use encode::Encode;
+pub use mcap_player::{McapPlayer, PlaybackMode};
 pub use mcap_writer::{
-    McapAttachment, McapCompression, McapWriteOptions, McapWriter, McapWriterHandle,
+    recover, ChannelSummary, FinishedRecording, McapAttachment, McapAttachmentHeader,
+    McapCompression, McapSegment, McapSyncPolicy, McapWriteOptions, McapWriter, McapWriterHandle,
+    RecordingController, RecordingSummary, RecoveryReport, RetentionMode, RetentionPolicy,
+    RetentionReport, RingBufferOptions, RingBufferSink, RotatingMcapWriterHandle, RotationPolicy,
 };
 pub use metadata::{Metadata, PartialMetadata, ToUnixNanos};
 pub use schema::Schema;
-pub use sink::{Sink, SinkId};
+pub use sink::{FilteredSink, FnSink, Sink, SinkId, TeeSink};
 pub use sink_channel_filter::SinkChannelFilter;
+pub use sink_message_filter::SinkMessageFilter;
+pub use stats::{ChannelStats, ContextStats, DiagnosticsHandle, SinkStats, DIAGNOSTICS_TOPIC};
 pub use std::collections::BTreeMap;
 pub(crate) use time::nanoseconds_since_epoch;
 
 #[cfg(feature = "agent")]
 mod cloud_sink;
 #[cfg(feature = "live_visualization")]
+mod playback_server;
+#[cfg(feature = "live_visualization")]
 mod protocol;
 #[cfg(feature = "live_visualization")]
 mod runtime;
@@ -372,10 +413,14 @@ pub mod websocket;
 #[cfg(feature = "live_visualization")]
 mod websocket_client;
 #[cfg(feature = "live_visualization")]
+mod websocket_relay;
+#[cfg(feature = "live_visualization")]
 mod websocket_server;
 #[cfg(feature = "agent")]
 pub use cloud_sink::{CloudSink, CloudSinkHandle, CloudSinkListener};
 #[cfg(feature = "live_visualization")]
+pub use playback_server::{PlaybackServer, PlaybackServerHandle};
+#[cfg(feature = "live_visualization")]
 pub(crate) use runtime::get_runtime_handle;
 #[cfg(feature = "live_visualization")]
 pub use runtime::shutdown_runtime;
@@ -384,7 +429,14 @@ pub use runtime::shutdown_runtime;
 pub use websocket::ws_protocol;
 #[doc(hidden)]
 #[cfg(feature = "live_visualization")]
-pub use websocket_client::{WebSocketClient, WebSocketClientError};
+pub use websocket_client::WebSocketClient;
+#[cfg(feature = "live_visualization")]
+pub use websocket_client::{
+    ClientPublisher, RemoteChannel, RemoteMessage, RemoteServerInfo, RemoteService,
+    RemoteSubscription, WebSocketClientError, WebSocketConnection,
+};
+#[cfg(feature = "live_visualization")]
+pub use websocket_relay::{WebSocketRelay, WebSocketRelayHandle};
 #[cfg(feature = "live_visualization")]
 pub use websocket_server::{WebSocketServer, WebSocketServerHandle};
 
@@ -26,6 +26,11 @@ impl LogSinkSet {
         self.0.load().len()
     }
 
+    /// Returns a snapshot of the sinks in the set.
+    pub fn load(&self) -> Arc<SmallSinkVec> {
+        self.0.load_full()
+    }
+
     /// Replaces the set of sinks in the set.
     pub fn store(&self, sinks: SmallSinkVec) {
         self.0.store(Arc::new(sinks));
@@ -44,22 +49,6 @@ impl LogSinkSet {
         }
     }
 
-    /// Iterate over sinks that match the predicate, calling the given function on each,
-    /// logging any errors via tracing::warn!().
-    pub fn for_each_filtered<F, P>(&self, predicate: P, mut f: F)
-    where
-        F: FnMut(&Arc<dyn Sink>) -> Result<(), FoxgloveError>,
-        P: Fn(&Arc<dyn Sink>) -> bool,
-    {
-        for sink in self.0.load().iter() {
-            if predicate(sink) {
-                if let Err(err) = f(sink) {
-                    tracing::warn!("{ERROR_LOGGING_MESSAGE}: {:?}", err);
-                }
-            }
-        }
-    }
-
     /// Clears the set.
     pub fn clear(&self) {
         self.0.store(Arc::default());
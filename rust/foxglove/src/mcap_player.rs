@@ -0,0 +1,374 @@
+//! Replays MCAP recordings into a context.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use mcap::records::Record;
+use mcap::sans_io::linear_reader::{LinearReadEvent, LinearReader};
+
+use crate::{
+    ChannelBuilder, Context, FoxgloveError, ManualClock, PartialMetadata, RawChannel, Schema,
+    ToUnixNanos,
+};
+
+/// The longest time to sleep without checking whether playback has been stopped.
+const MAX_SLEEP: Duration = Duration::from_millis(100);
+
+/// The pace at which an [`McapPlayer`] replays messages.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PlaybackMode {
+    /// Replay messages with the same spacing as their original log times.
+    #[default]
+    RealTime,
+    /// Replay messages at a multiple of real time. For example, `Scaled(2.0)` replays at twice
+    /// the original speed. The factor must be positive.
+    Scaled(f64),
+    /// Replay messages as fast as possible.
+    AsFastAsPossible,
+}
+
+impl PlaybackMode {
+    /// Returns the playback speed relative to real time, or `None` if unbounded.
+    fn speed(self) -> Result<Option<f64>, FoxgloveError> {
+        match self {
+            Self::RealTime => Ok(Some(1.0)),
+            Self::Scaled(speed) if speed.is_finite() && speed > 0.0 => Ok(Some(speed)),
+            Self::Scaled(speed) => Err(FoxgloveError::ValueError(format!(
+                "invalid playback speed: {speed}"
+            ))),
+            Self::AsFastAsPossible => Ok(None),
+        }
+    }
+}
+
+/// Replays an MCAP recording into a [`Context`].
+///
+/// The player creates a [`RawChannel`] for each channel in the recording, with the same topic,
+/// message encoding, schema and metadata, and logs each message with its original log time,
+/// publish time and sequence number. This is useful for testing a pipeline against recorded
+/// data, or for streaming a recording to the Foxglove app.
+///
+/// Messages are replayed in the order in which they appear in the file. Channels are created the
+/// first time they are encountered, and are reused if the recording is played again.
+///
+/// # Example
+/// ```no_run
+/// use foxglove::{McapPlayer, PlaybackMode};
+///
+/// let mut player = McapPlayer::open("recording.mcap")
+///     .expect("failed to open recording")
+///     .mode(PlaybackMode::Scaled(2.0))
+///     .topics(["/imu", "/odom"]);
+/// let count = player.play().expect("playback failed");
+/// println!("replayed {count} messages");
+/// ```
+#[must_use]
+pub struct McapPlayer {
+    path: PathBuf,
+    context: Arc<Context>,
+    mode: PlaybackMode,
+    start_time: Option<u64>,
+    end_time: Option<u64>,
+    topics: Option<HashSet<String>>,
+    clock: Arc<ManualClock>,
+    schemas: HashMap<u16, Schema>,
+    /// Channels created for the recording, keyed by MCAP channel ID. Channels that do not match
+    /// the topic filter are `None`.
+    channels: HashMap<u16, Option<Arc<RawChannel>>>,
+}
+
+impl Debug for McapPlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McapPlayer")
+            .field("path", &self.path)
+            .field("context", &self.context)
+            .field("mode", &self.mode)
+            .field("start_time", &self.start_time)
+            .field("end_time", &self.end_time)
+            .field("topics", &self.topics)
+            .finish_non_exhaustive()
+    }
+}
+
+impl McapPlayer {
+    /// Creates a player for the MCAP file at `path`.
+    ///
+    /// By default, messages are replayed in real time into the default context.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, FoxgloveError> {
+        let path = path.as_ref();
+        // Fail early if the file cannot be read.
+        File::open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            context: Context::get_default(),
+            mode: PlaybackMode::default(),
+            start_time: None,
+            end_time: None,
+            topics: None,
+            clock: Arc::new(ManualClock::default()),
+            schemas: HashMap::new(),
+            channels: HashMap::new(),
+        })
+    }
+
+    /// Sets the context into which messages are replayed.
+    pub fn context(mut self, ctx: &Arc<Context>) -> Self {
+        self.context = ctx.clone();
+        self.channels.clear();
+        self
+    }
+
+    /// Sets the pace at which messages are replayed.
+    pub fn mode(mut self, mode: PlaybackMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Replays only messages with a log time within `start..=end`.
+    pub fn time_range(mut self, start: impl ToUnixNanos, end: impl ToUnixNanos) -> Self {
+        self.start_time = Some(start.to_unix_nanos());
+        self.end_time = Some(end.to_unix_nanos());
+        self
+    }
+
+    /// Replays only messages on the given topics.
+    pub fn topics<I, S>(mut self, topics: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.topics = Some(topics.into_iter().map(Into::into).collect());
+        self.channels.clear();
+        self
+    }
+
+    /// Returns a clock that tracks the log time of the most recently replayed message.
+    ///
+    /// Install it on the context with [`Context::set_clock`] so that default timestamps, and the
+    /// time broadcast by a WebSocket server, follow the recording.
+    pub fn clock(&self) -> Arc<ManualClock> {
+        self.clock.clone()
+    }
+
+    /// Replays the recording, blocking until all messages have been logged, and returns the
+    /// number of messages that were logged.
+    pub fn play(&mut self) -> Result<u64, FoxgloveError> {
+        self.play_until(&AtomicBool::new(false))
+    }
+
+    /// Replays the recording, blocking until all messages have been logged or `stop` is set, and
+    /// returns the number of messages that were logged.
+    pub fn play_until(&mut self, stop: &AtomicBool) -> Result<u64, FoxgloveError> {
+        let speed = self.mode.speed()?;
+        let mut file = BufReader::new(File::open(&self.path)?);
+        let mut reader = LinearReader::new();
+        // The wall time and log time of the first replayed message.
+        let mut origin: Option<(Instant, u64)> = None;
+        let mut count = 0;
+
+        while let Some(event) = reader.next_event() {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let (opcode, data) = match event? {
+                LinearReadEvent::ReadRequest(len) => {
+                    let read = file.read(reader.insert(len))?;
+                    reader.notify_read(read);
+                    continue;
+                }
+                LinearReadEvent::Record { opcode, data } => (opcode, data),
+            };
+            match mcap::parse_record(opcode, data)? {
+                Record::Schema { header, data } => {
+                    self.schemas.entry(header.id).or_insert_with(|| {
+                        Schema::new(header.name, header.encoding, data.into_owned())
+                    });
+                }
+                Record::Channel(record) => {
+                    let id = record.id;
+                    if !self.channels.contains_key(&id) {
+                        let channel = self.create_channel(record)?;
+                        self.channels.insert(id, channel);
+                    }
+                }
+                Record::Message { header, data } => {
+                    let Some(Some(channel)) = self.channels.get(&header.channel_id) else {
+                        continue;
+                    };
+                    let log_time = header.log_time;
+                    if self.start_time.is_some_and(|start| log_time < start)
+                        || self.end_time.is_some_and(|end| log_time > end)
+                    {
+                        continue;
+                    }
+                    if let Some(speed) = speed {
+                        let (wall, start) = *origin.get_or_insert((Instant::now(), log_time));
+                        let offset = log_time.saturating_sub(start) as f64 / speed;
+                        let deadline = wall + Duration::from_nanos(offset as u64);
+                        if !sleep_until(deadline, stop) {
+                            break;
+                        }
+                    }
+                    self.clock.set(log_time);
+                    channel.log_with_meta(
+                        &data,
+                        PartialMetadata::with_log_time(log_time)
+                            .publish_time(header.publish_time)
+                            .sequence(header.sequence),
+                    );
+                    count += 1;
+                }
+                _ => (),
+            }
+        }
+        Ok(count)
+    }
+
+    /// Creates a channel for an MCAP channel record, if it matches the topic filter.
+    fn create_channel(
+        &self,
+        record: mcap::records::Channel,
+    ) -> Result<Option<Arc<RawChannel>>, FoxgloveError> {
+        if self
+            .topics
+            .as_ref()
+            .is_some_and(|topics| !topics.contains(&record.topic))
+        {
+            return Ok(None);
+        }
+        let channel = ChannelBuilder::new(record.topic)
+            .context(&self.context)
+            .message_encoding(record.message_encoding)
+            .schema(self.schemas.get(&record.schema_id).cloned())
+            .metadata(record.metadata)
+            .build_raw()?;
+        Ok(Some(channel))
+    }
+}
+
+/// Sleeps until the deadline, and returns false if `stop` was set in the meantime.
+fn sleep_until(deadline: Instant, stop: &AtomicBool) -> bool {
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        std::thread::sleep(remaining.min(MAX_SLEEP));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::RecordingSink;
+    use crate::{Clock, McapWriter, Metadata};
+
+    /// Writes a recording with messages on `/a` and `/b` at 1ms intervals.
+    fn write_recording(path: &Path) {
+        let ctx = Context::new();
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_new_buffered_file(path)
+            .unwrap();
+        let a = ChannelBuilder::new("/a")
+            .context(&ctx)
+            .message_encoding("json")
+            .schema(Schema::new(
+                "A",
+                "jsonschema",
+                br#"{"type":"object"}"#.to_vec(),
+            ))
+            .add_metadata("key", "value")
+            .build_raw()
+            .unwrap();
+        let b = ChannelBuilder::new("/b")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        for i in 0..4u64 {
+            let time = 1_000_000 * i;
+            a.log_with_meta(
+                b"{}",
+                PartialMetadata::with_log_time(time).sequence(i as u32),
+            );
+            b.log_with_meta(&[i as u8], PartialMetadata::with_log_time(time));
+        }
+        handle.close().unwrap();
+    }
+
+    #[test]
+    fn test_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+        write_recording(&path);
+
+        let ctx = Context::new();
+        let sink = Arc::new(RecordingSink::new());
+        ctx.add_sink(sink.clone());
+        let mut player = McapPlayer::open(&path)
+            .unwrap()
+            .context(&ctx)
+            .mode(PlaybackMode::AsFastAsPossible)
+            .topics(["/a"])
+            .time_range(1_000_000u64, 2_000_000u64);
+        assert_eq!(player.play().unwrap(), 2);
+        assert_eq!(player.clock().now(), 2_000_000);
+
+        let channel = ctx.get_channel_by_topic("/a").unwrap();
+        assert_eq!(channel.message_encoding(), "json");
+        assert_eq!(channel.schema().unwrap().name, "A");
+        assert_eq!(channel.metadata().get("key").unwrap(), "value");
+        assert!(ctx.get_channel_by_topic("/b").is_none());
+
+        let metadata: Vec<_> = sink
+            .take_messages()
+            .into_iter()
+            .map(|m| m.metadata)
+            .collect();
+        assert_eq!(
+            metadata,
+            vec![
+                Metadata {
+                    log_time: 1_000_000,
+                    publish_time: 1_000_000,
+                    sequence: Some(1),
+                },
+                Metadata {
+                    log_time: 2_000_000,
+                    publish_time: 2_000_000,
+                    sequence: Some(2),
+                },
+            ]
+        );
+
+        // Replaying again reuses the channels.
+        assert_eq!(player.play().unwrap(), 2);
+        assert_eq!(sink.take_messages().len(), 2);
+    }
+
+    #[test]
+    fn test_real_time_pacing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+        write_recording(&path);
+
+        let ctx = Context::new();
+        let start = Instant::now();
+        let mut player = McapPlayer::open(&path).unwrap().context(&ctx);
+        assert_eq!(player.play().unwrap(), 8);
+        assert!(start.elapsed() >= Duration::from_millis(3));
+
+        let err = player.mode(PlaybackMode::Scaled(0.0)).play().unwrap_err();
+        assert!(matches!(err, FoxgloveError::ValueError(_)));
+    }
+}
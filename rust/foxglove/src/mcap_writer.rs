@@ -1,15 +1,24 @@
 //! MCAP writer
 
 use std::fs::File;
-use std::io::{BufWriter, Seek};
+use std::io::{BufWriter, Read, Seek};
 use std::path::Path;
 use std::sync::{Arc, Weak};
+use std::time::UNIX_EPOCH;
 use std::{fmt::Debug, io::Write};
 
 use crate::library_version::get_library_version;
 use crate::sink_channel_filter::SinkChannelFilterFn;
-use crate::{ChannelDescriptor, Context, FoxgloveError, Sink, SinkChannelFilter};
+use crate::sink_message_filter::SinkMessageFilterFn;
+use crate::{
+    nanoseconds_since_epoch, ChannelDescriptor, Context, FoxgloveError, Metadata, RawChannel, Sink,
+    SinkChannelFilter, SinkMessageFilter,
+};
 
+/// The header of an attachment whose data is streamed into an MCAP file.
+///
+/// See [`McapWriterHandle::attach_from_reader`].
+pub use mcap::records::AttachmentHeader as McapAttachmentHeader;
 /// An attachment to store in an MCAP file.
 ///
 /// Attachments are arbitrary binary data that can be stored alongside messages.
@@ -21,8 +30,22 @@ pub use mcap::Compression as McapCompression;
 /// Options for use with an [`McapWriter`][crate::McapWriter].
 pub use mcap::WriteOptions as McapWriteOptions;
 
+mod durability;
 mod mcap_sink;
-use mcap_sink::McapSink;
+mod recording_controller;
+mod recording_summary;
+mod retention;
+mod ring_buffer_sink;
+mod rotating;
+use durability::create_file_sink;
+pub use durability::{recover, McapSyncPolicy, RecoveryReport};
+pub(crate) use mcap_sink::McapSink;
+pub use recording_controller::{FinishedRecording, RecordingController};
+pub use recording_summary::{ChannelSummary, RecordingSummary};
+pub use retention::{RetentionMode, RetentionPolicy, RetentionReport};
+pub use ring_buffer_sink::{RingBufferOptions, RingBufferSink};
+use rotating::RotatingMcapSink;
+pub use rotating::{McapSegment, RotatingMcapWriterHandle, RotationPolicy};
 
 /// An MCAP writer for logging events.
 ///
@@ -34,14 +57,17 @@ use mcap_sink::McapSink;
 #[derive(Clone)]
 pub struct McapWriter {
     options: McapWriteOptions,
+    sync_policy: McapSyncPolicy,
     context: Arc<Context>,
     channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+    message_filter: Option<Arc<dyn SinkMessageFilter>>,
 }
 
 impl Debug for McapWriter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("McapWriter")
             .field("options", &self.options)
+            .field("sync_policy", &self.sync_policy)
             .field("context", &self.context)
             .finish_non_exhaustive()
     }
@@ -52,8 +78,10 @@ impl From<McapWriteOptions> for McapWriter {
         let options = value.library(get_library_version());
         Self {
             options,
+            sync_policy: McapSyncPolicy::default(),
             context: Context::get_default(),
             channel_filter: None,
+            message_filter: None,
         }
     }
 }
@@ -83,6 +111,14 @@ impl McapWriter {
         self
     }
 
+    /// Sets when files created by this writer are flushed and synced to disk.
+    ///
+    /// See [`McapSyncPolicy`] for details. The policy does not apply to [`McapWriter::create`].
+    pub fn sync_policy(mut self, policy: McapSyncPolicy) -> Self {
+        self.sync_policy = policy;
+        self
+    }
+
     /// Sets a [`SinkChannelFilter`] for this file.
     pub fn channel_filter(mut self, filter: Arc<dyn SinkChannelFilter>) -> Self {
         self.channel_filter = Some(filter);
@@ -98,6 +134,21 @@ impl McapWriter {
         self
     }
 
+    /// Sets a [`SinkMessageFilter`] for this file.
+    pub fn message_filter(mut self, filter: Arc<dyn SinkMessageFilter>) -> Self {
+        self.message_filter = Some(filter);
+        self
+    }
+
+    /// Sets a message filter for this file. See [`SinkMessageFilter`] for more information.
+    pub fn message_filter_fn(
+        mut self,
+        filter: impl Fn(&RawChannel, &[u8], &Metadata) -> bool + Sync + Send + 'static,
+    ) -> Self {
+        self.message_filter = Some(Arc::new(SinkMessageFilterFn(filter)));
+        self
+    }
+
     /// Begins logging events to the specified writer.
     ///
     /// Returns a handle. When the handle is dropped, the recording will be flushed to the writer
@@ -107,7 +158,12 @@ impl McapWriter {
     where
         W: Write + Seek + Send + 'static,
     {
-        let sink = McapSink::new(writer, self.options, self.channel_filter)?;
+        let sink = McapSink::new(
+            writer,
+            self.options,
+            self.channel_filter,
+            self.message_filter,
+        )?;
         self.context.add_sink(sink.clone());
         Ok(McapWriterHandle {
             sink,
@@ -115,6 +171,35 @@ impl McapWriter {
         })
     }
 
+    /// Begins logging events to the specified writer, through a sink that wraps the MCAP sink.
+    ///
+    /// The sink returned by `wrap` is registered with the context in place of the MCAP sink. It
+    /// must report the same [`SinkId`][crate::SinkId] as the sink it wraps, so that the handle
+    /// can unregister it.
+    #[cfg(feature = "stream")]
+    pub(crate) fn create_wrapped<W>(
+        self,
+        writer: W,
+        wrap: impl FnOnce(Arc<McapSink<W>>) -> Arc<dyn Sink>,
+    ) -> Result<McapWriterHandle<W>, FoxgloveError>
+    where
+        W: Write + Seek + Send + 'static,
+    {
+        let sink = McapSink::new(
+            writer,
+            self.options,
+            self.channel_filter,
+            self.message_filter,
+        )?;
+        let wrapper = wrap(sink.clone());
+        debug_assert_eq!(wrapper.id(), sink.id());
+        self.context.add_sink(wrapper);
+        Ok(McapWriterHandle {
+            sink,
+            context: Arc::downgrade(&self.context),
+        })
+    }
+
     /// Creates a new write-only buffered file, and begins logging events to it.
     ///
     /// If the file already exists, this call will fail with
@@ -129,9 +214,57 @@ impl McapWriter {
     where
         P: AsRef<Path>,
     {
-        let file = File::create_new(path)?;
-        let writer = BufWriter::new(file);
-        self.create(writer)
+        let sink = create_file_sink(
+            path.as_ref(),
+            self.options,
+            self.sync_policy,
+            self.channel_filter,
+            self.message_filter,
+        )?;
+        self.context.add_sink(sink.clone());
+        Ok(McapWriterHandle {
+            sink,
+            context: Arc::downgrade(&self.context),
+        })
+    }
+
+    /// Begins logging events to a sequence of files in the specified directory.
+    ///
+    /// The writer rolls over to a new file whenever a limit in the [`RotationPolicy`] is reached.
+    /// Rollover happens atomically with respect to logging, so no messages are lost at segment
+    /// boundaries. Each segment is a complete MCAP file, which contains the schemas and channels
+    /// for all channels known to the writer, whether or not messages were logged on them.
+    ///
+    /// The directory is created if it does not exist. Segment files are created with the file
+    /// name template from the policy, and this call will fail if a segment file already exists.
+    ///
+    /// ```no_run
+    /// use foxglove::{McapWriter, RotationPolicy};
+    ///
+    /// let handle = McapWriter::new()
+    ///     .create_rotating(
+    ///         "recordings",
+    ///         RotationPolicy::new()
+    ///             .max_bytes(512 * 1024 * 1024)
+    ///             .on_segment_finalized(|segment| println!("wrote {:?}", segment.path)),
+    ///     )
+    ///     .expect("create failed");
+    /// ```
+    pub fn create_rotating(
+        self,
+        dir: impl AsRef<Path>,
+        policy: RotationPolicy,
+    ) -> Result<RotatingMcapWriterHandle, FoxgloveError> {
+        let sink = RotatingMcapSink::new(
+            dir.as_ref(),
+            self.options,
+            self.sync_policy,
+            policy,
+            self.channel_filter,
+            self.message_filter,
+        )?;
+        self.context.add_sink(sink.clone());
+        Ok(RotatingMcapWriterHandle::new(sink, &self.context))
     }
 }
 
@@ -147,15 +280,18 @@ pub struct McapWriterHandle<W: Write + Seek + Send + 'static> {
 }
 
 impl<W: Write + Seek + Send + 'static> McapWriterHandle<W> {
-    /// Stops logging events, flushes buffered data, and returns the writer.
-    pub fn close(self) -> Result<W, FoxgloveError> {
-        // It's safe to unwrap the `Option<W>` because `McapWriterHandle` doesn't implement clone,
+    /// Stops logging events, flushes buffered data, and returns the writer along with a summary of
+    /// the recording.
+    pub fn close(self) -> Result<(W, RecordingSummary), FoxgloveError> {
+        // It's safe to unwrap the `Option` because `McapWriterHandle` doesn't implement clone,
         // and this method consumes self.
         self.finish().map(|w| w.expect("not finished"))
     }
 
-    fn finish(&self) -> Result<Option<W>, FoxgloveError> {
+    fn finish(&self) -> Result<Option<(W, RecordingSummary)>, FoxgloveError> {
         if let Some(context) = self.context.upgrade() {
+            // Deliver any messages still queued for background dispatch before detaching.
+            context.flush();
             context.remove_sink(self.sink.id());
         }
         self.sink.finish()
@@ -205,6 +341,84 @@ impl<W: Write + Seek + Send + 'static> McapWriterHandle<W> {
     pub fn attach(&self, attachment: &McapAttachment<'_>) -> Result<(), FoxgloveError> {
         self.sink.attach(attachment)
     }
+
+    /// Writes an attachment to the MCAP file, streaming `length` bytes of data from `reader`.
+    ///
+    /// Unlike [`McapWriterHandle::attach`], the data does not need to be held in memory. It is
+    /// copied from `reader` into the MCAP file in fixed-size pieces, and the attachment CRC is
+    /// computed as it is written. Messages logged while the attachment is being written wait
+    /// until it is complete, so `reader` should be fast, such as a local file. To avoid stalling
+    /// logging on a slow source, read the data into memory first and use
+    /// [`McapWriterHandle::attach`].
+    ///
+    /// If the reader fails, or ends before providing `length` bytes, the remainder of the
+    /// attachment is filled with zeros so that the file remains valid, and an error is returned.
+    pub fn attach_from_reader(
+        &self,
+        header: McapAttachmentHeader,
+        length: u64,
+        mut reader: impl Read,
+    ) -> Result<(), FoxgloveError> {
+        self.sink.write_attachment(header, length, &mut reader)
+    }
+
+    /// Writes the file at `path` to the MCAP file as an attachment, streaming its contents.
+    ///
+    /// The attachment is named after the file, its create time is the file's modification time,
+    /// and its log time is the current time from the writer's [`Context`] clock. Since the file is
+    /// local, it is read directly into the MCAP file, and messages logged while it is being
+    /// written wait until it is complete.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use foxglove::McapWriter;
+    ///
+    /// let mcap = McapWriter::new()
+    ///     .create_new_buffered_file("test.mcap")
+    ///     .expect("create failed");
+    ///
+    /// mcap.attach_file("calibration.tar", "application/x-tar")
+    ///     .expect("attach failed");
+    /// ```
+    pub fn attach_file(
+        &self,
+        path: impl AsRef<Path>,
+        media_type: &str,
+    ) -> Result<(), FoxgloveError> {
+        let log_time = self
+            .context
+            .upgrade()
+            .map_or_else(nanoseconds_since_epoch, |c| c.now());
+        let (header, length, mut file) = open_attachment_file(path.as_ref(), media_type, log_time)?;
+        self.sink.write_attachment(header, length, &mut file)
+    }
+}
+
+/// Opens a file to be streamed into an MCAP file as an attachment, and returns the attachment
+/// header and length.
+pub(crate) fn open_attachment_file(
+    path: &Path,
+    media_type: &str,
+    log_time: u64,
+) -> Result<(McapAttachmentHeader, u64, File), FoxgloveError> {
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let create_time = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .and_then(|d| u64::try_from(d.as_nanos()).ok())
+        .unwrap_or_default();
+    let header = McapAttachmentHeader {
+        log_time,
+        create_time,
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        media_type: media_type.to_string(),
+    };
+    Ok((header, metadata.len(), file))
 }
 
 impl<W: Write + Seek + Send + 'static> Drop for McapWriterHandle<W> {
@@ -0,0 +1,364 @@
+//! Crash-resilient recording, and recovery of truncated files.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use mcap::records::Record;
+use mcap::sans_io::linear_reader::{LinearReadEvent, LinearReader, LinearReaderOptions};
+
+use super::McapSink;
+use crate::{FoxgloveError, McapWriteOptions, SinkChannelFilter, SinkMessageFilter};
+
+/// The size of a message record, excluding the message data.
+const MESSAGE_RECORD_OVERHEAD: u64 = 1 + 8 + 2 + 4 + 8 + 8;
+
+/// When an MCAP file writer flushes its data to disk.
+///
+/// By default, data is buffered in memory and by the operating system, and a file that is not
+/// closed cleanly (for example, due to a loss of power) may be missing its summary section and
+/// some of its most recent data. With a sync policy, the writer periodically closes the current
+/// chunk, flushes it, and calls [`File::sync_data`], so that all chunks up to that point survive
+/// a crash. Such a file can be repaired with [`recover`].
+///
+/// Syncing applies to files created with
+/// [`McapWriter::create_new_buffered_file`][crate::McapWriter::create_new_buffered_file] and
+/// [`McapWriter::create_rotating`][crate::McapWriter::create_rotating]. Each sync closes the
+/// current chunk, so frequent syncs produce smaller chunks and reduce compression efficiency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum McapSyncPolicy {
+    /// Only sync when the file is closed.
+    #[default]
+    Never,
+    /// Close each chunk once it holds the given number of bytes of uncompressed message data,
+    /// then flush and sync it.
+    ///
+    /// This replaces the chunk size in the [write options][McapWriteOptions::chunk_size].
+    EveryChunk(u64),
+    /// Close the current chunk, flush and sync at most once per interval.
+    ///
+    /// The interval is checked when a message is logged, so an idle writer does not sync.
+    Interval(Duration),
+}
+
+impl McapSyncPolicy {
+    /// Adjusts the write options for the policy.
+    pub(crate) fn write_options(self, options: McapWriteOptions) -> McapWriteOptions {
+        match self {
+            Self::EveryChunk(_) => options.chunk_size(None),
+            _ => options,
+        }
+    }
+}
+
+/// Tracks when a writer needs to sync.
+pub(crate) struct SyncState {
+    policy: McapSyncPolicy,
+    file: File,
+    chunk_bytes: u64,
+    last_sync: Instant,
+}
+
+impl SyncState {
+    /// Records a message of the given size, and returns true if the writer should sync.
+    pub fn on_message(&mut self, len: usize) -> bool {
+        self.chunk_bytes += MESSAGE_RECORD_OVERHEAD + len as u64;
+        match self.policy {
+            McapSyncPolicy::Never => false,
+            McapSyncPolicy::EveryChunk(size) => self.chunk_bytes >= size,
+            McapSyncPolicy::Interval(interval) => self.last_sync.elapsed() >= interval,
+        }
+    }
+
+    /// Syncs the file to disk. The caller must first flush the writer.
+    pub fn sync(&mut self) -> std::io::Result<()> {
+        self.file.sync_data()?;
+        self.chunk_bytes = 0;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+}
+
+/// Creates a new buffered file, and an MCAP sink that writes to it with the sync policy.
+pub(crate) fn create_file_sink(
+    path: &Path,
+    options: McapWriteOptions,
+    policy: McapSyncPolicy,
+    channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+    message_filter: Option<Arc<dyn SinkMessageFilter>>,
+) -> Result<Arc<McapSink<BufWriter<File>>>, FoxgloveError> {
+    let file = File::create_new(path)?;
+    let sync = match policy {
+        McapSyncPolicy::Never => None,
+        _ => Some(SyncState {
+            policy,
+            file: file.try_clone()?,
+            chunk_bytes: 0,
+            last_sync: Instant::now(),
+        }),
+    };
+    let sink = McapSink::new(
+        BufWriter::new(file),
+        policy.write_options(options),
+        channel_filter,
+        message_filter,
+    )?;
+    if let Some(sync) = sync {
+        sink.set_sync(sync);
+    }
+    Ok(sink)
+}
+
+/// The outcome of [`recover`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RecoveryReport {
+    /// The number of messages recovered.
+    pub messages: u64,
+    /// The number of attachments recovered.
+    pub attachments: u64,
+    /// The number of metadata records recovered.
+    pub metadata: u64,
+    /// True if the file was truncated or corrupt, and trailing data was discarded.
+    pub truncated: bool,
+}
+
+/// Repairs an MCAP file that was not closed cleanly, such as after a crash or a loss of power.
+///
+/// Reads the schemas, channels, messages, attachments and metadata from every complete, valid
+/// chunk and record in the file, discarding anything after the first truncated or corrupt
+/// record, and replaces the file with a valid MCAP file that has a complete summary section.
+///
+/// The file is rewritten to a temporary file next to it, which is renamed over the original
+/// once complete. The profile and library from the original header are preserved. A file that was
+/// closed cleanly is rewritten without loss.
+pub fn recover(path: impl AsRef<Path>) -> Result<RecoveryReport, FoxgloveError> {
+    let path = path.as_ref();
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".recovering");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = recover_to(path, &tmp_path);
+    match result {
+        Ok(report) => {
+            std::fs::rename(&tmp_path, path)?;
+            Ok(report)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+/// Reads the recoverable records from `input`, and writes them to a new file at `output`.
+fn recover_to(input: &Path, output: &Path) -> Result<RecoveryReport, FoxgloveError> {
+    let mut file = File::open(input)?;
+    let mut reader = LinearReader::new_with_options(LinearReaderOptions {
+        prevalidate_chunk_crcs: true,
+        ..Default::default()
+    });
+    let mut report = RecoveryReport::default();
+    let mut writer: Option<mcap::Writer<BufWriter<File>>> = None;
+    let mut schemas = HashMap::new();
+    let mut channels = HashMap::new();
+
+    while let Some(event) = reader.next_event() {
+        let (opcode, data) = match event {
+            Ok(LinearReadEvent::ReadRequest(len)) => {
+                let read = file.read(reader.insert(len))?;
+                reader.notify_read(read);
+                continue;
+            }
+            Ok(LinearReadEvent::Record { opcode, data }) => (opcode, data),
+            Err(e) => {
+                tracing::debug!("Discarding trailing data from {}: {e}", input.display());
+                report.truncated = true;
+                break;
+            }
+        };
+        let record = match mcap::parse_record(opcode, data) {
+            Ok(record) => record,
+            Err(e) => {
+                tracing::debug!("Discarding trailing data from {}: {e}", input.display());
+                report.truncated = true;
+                break;
+            }
+        };
+
+        let Some(w) = writer.as_mut() else {
+            let Record::Header(header) = record else {
+                return Err(FoxgloveError::ValueError(format!(
+                    "{} does not begin with an MCAP header",
+                    input.display()
+                )));
+            };
+            let options = McapWriteOptions::default()
+                .profile(header.profile)
+                .library(header.library);
+            writer = Some(options.create(BufWriter::new(File::create(output)?))?);
+            continue;
+        };
+        match record {
+            Record::Schema { header, data } => {
+                let id = w.add_schema(&header.name, &header.encoding, &data)?;
+                schemas.insert(header.id, id);
+            }
+            Record::Channel(channel) => {
+                let schema_id = schemas.get(&channel.schema_id).copied().unwrap_or(0);
+                let id = w.add_channel(
+                    schema_id,
+                    &channel.topic,
+                    &channel.message_encoding,
+                    &channel.metadata,
+                )?;
+                channels.insert(channel.id, id);
+            }
+            Record::Message { mut header, data } => {
+                let Some(&id) = channels.get(&header.channel_id) else {
+                    continue;
+                };
+                header.channel_id = id;
+                w.write_to_known_channel(&header, &data)?;
+                report.messages += 1;
+            }
+            Record::Attachment { header, data, .. } => {
+                w.attach(&mcap::Attachment {
+                    log_time: header.log_time,
+                    create_time: header.create_time,
+                    name: header.name,
+                    media_type: header.media_type,
+                    data,
+                })?;
+                report.attachments += 1;
+            }
+            Record::Metadata(metadata) => {
+                w.write_metadata(&metadata)?;
+                report.metadata += 1;
+            }
+            // The summary section only repeats records from the data section.
+            Record::DataEnd(_) => break,
+            _ => (),
+        }
+    }
+
+    let Some(mut writer) = writer else {
+        return Err(FoxgloveError::ValueError(format!(
+            "{} does not contain an MCAP header",
+            input.display()
+        )));
+    };
+    writer.finish()?;
+    let mut out = writer.into_inner();
+    out.flush()?;
+    out.get_ref().sync_all()?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::read_summary;
+    use crate::{ChannelBuilder, Context, McapWriter};
+
+    #[test]
+    fn test_sync_every_chunk_and_recover() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+        let ctx = Context::new();
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .sync_policy(McapSyncPolicy::EveryChunk(100))
+            .create_new_buffered_file(&path)
+            .unwrap();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        for _ in 0..11 {
+            ch.log(&[0; 60]);
+        }
+
+        // Simulate a crash, by copying the file before it is closed.
+        let crashed = dir.path().join("crashed.mcap");
+        std::fs::copy(&path, &crashed).unwrap();
+        handle.close().unwrap();
+
+        let report = recover(&crashed).unwrap();
+        assert!(report.truncated);
+        // Messages are synced in chunks of two, so the last message has not been synced.
+        assert_eq!(report.messages, 10);
+        let summary = read_summary(&crashed);
+        assert_eq!(summary.stats.unwrap().message_count, 10);
+        assert_eq!(summary.channels.len(), 1);
+    }
+
+    #[test]
+    fn test_recover_partial_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+        let ctx = Context::new();
+        let handle = McapWriter::with_options(McapWriteOptions::new().chunk_size(Some(100)))
+            .context(&ctx)
+            .create_new_buffered_file(&path)
+            .unwrap();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        for _ in 0..10 {
+            ch.log(&[0; 60]);
+        }
+        handle.close().unwrap();
+
+        // Truncate the file partway through the data section.
+        let data = std::fs::read(&path).unwrap();
+        let summary = read_summary(&path);
+        let third_chunk = &summary.chunk_indexes[2];
+        let end = third_chunk.chunk_start_offset + third_chunk.chunk_length / 2;
+        std::fs::write(&path, &data[..end as usize]).unwrap();
+
+        let report = recover(&path).unwrap();
+        assert!(report.truncated);
+        assert!(report.messages > 0 && report.messages < 10);
+        let summary = read_summary(&path);
+        assert_eq!(summary.stats.unwrap().message_count, report.messages);
+
+        // Recovering a complete file does not lose anything.
+        assert!(!recover(&path).unwrap().truncated);
+        assert_eq!(
+            read_summary(&path).stats.unwrap().message_count,
+            report.messages
+        );
+    }
+
+    #[test]
+    fn test_recover_preserves_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+        let mut writer = McapWriteOptions::new()
+            .profile("my-profile")
+            .library("my-library")
+            .create(BufWriter::new(File::create(&path).unwrap()))
+            .unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        recover(&path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        let header = mcap::read::LinearReader::new(&data)
+            .unwrap()
+            .find_map(|r| match r.unwrap() {
+                Record::Header(header) => Some(header),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(header.profile, "my-profile");
+        assert_eq!(header.library, "my-library");
+    }
+}
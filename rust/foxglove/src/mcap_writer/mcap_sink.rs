@@ -1,15 +1,22 @@
 //! [`Sink`] implementation for an MCAP writer.
-use crate::{ChannelId, FoxgloveError, Metadata, RawChannel, Sink, SinkChannelFilter, SinkId};
+use super::durability::SyncState;
+use super::RecordingSummary;
+use crate::{
+    ChannelDescriptor, ChannelId, FoxgloveError, Metadata, RawChannel, Sink, SinkChannelFilter,
+    SinkId, SinkMessageFilter,
+};
 use mcap::WriteOptions;
 use parking_lot::Mutex;
 use std::collections::hash_map::Entry;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
-use std::io::{Seek, Write};
+use std::io::{Read, Seek, Write};
 use std::sync::Arc;
 
 type McapChannelId = u16;
 
+/// The size of the pieces in which attachment data is copied from a reader.
+const ATTACHMENT_COPY_SIZE: usize = 64 * 1024;
 struct WriterState<W: Write + Seek> {
     writer: mcap::Writer<W>,
     // ChannelId -> mcap file channel id.
@@ -20,6 +27,10 @@ struct WriterState<W: Write + Seek> {
     // Current message sequence number for each channel.
     // Indexed by `McapChannelId` to ensure increasing sequence within each MCAP channel.
     channel_sequence: HashMap<McapChannelId, u32>,
+    // Total size of the message payloads written on each MCAP channel.
+    channel_bytes: HashMap<McapChannelId, u64>,
+    // Durability state, if the writer syncs the file to disk.
+    sync: Option<SyncState>,
 }
 
 impl<W: Write + Seek> WriterState<W> {
@@ -28,23 +39,27 @@ impl<W: Write + Seek> WriterState<W> {
             writer,
             channel_map: HashMap::new(),
             channel_sequence: HashMap::new(),
+            channel_bytes: HashMap::new(),
+            sync: None,
         }
     }
 
-    fn next_sequence(&mut self, channel_id: McapChannelId) -> u32 {
-        *self
-            .channel_sequence
-            .entry(channel_id)
-            .and_modify(|seq| *seq += 1)
-            .or_insert(1)
+    /// Returns the sequence number for the next message on the channel.
+    ///
+    /// If the caller provided a sequence number, it is used as-is, and subsequent generated
+    /// sequence numbers continue from there.
+    fn next_sequence(&mut self, channel_id: McapChannelId, provided: Option<u32>) -> u32 {
+        let seq = self.channel_sequence.entry(channel_id).or_insert(0);
+        *seq = provided.unwrap_or_else(|| seq.wrapping_add(1));
+        *seq
     }
 
-    fn log(
+    /// Returns the MCAP channel ID for the channel, adding the schema and channel to the file if
+    /// necessary.
+    fn mcap_channel_id(
         &mut self,
-        channel: &RawChannel,
-        msg: &[u8],
-        metadata: &Metadata,
-    ) -> Result<(), FoxgloveError> {
+        channel: &ChannelDescriptor,
+    ) -> Result<McapChannelId, FoxgloveError> {
         let channel_id = channel.id();
         let mcap_channel_id = match self.channel_map.entry(channel_id) {
             Entry::Occupied(entry) => *entry.get(),
@@ -71,8 +86,17 @@ impl<W: Write + Seek> WriterState<W> {
                 mcap_channel_id
             }
         };
+        Ok(mcap_channel_id)
+    }
 
-        let sequence = self.next_sequence(mcap_channel_id);
+    fn log(
+        &mut self,
+        channel: &ChannelDescriptor,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        let mcap_channel_id = self.mcap_channel_id(channel)?;
+        let sequence = self.next_sequence(mcap_channel_id, metadata.sequence);
 
         self.writer
             .write_to_known_channel(
@@ -80,12 +104,21 @@ impl<W: Write + Seek> WriterState<W> {
                     channel_id: mcap_channel_id,
                     sequence,
                     log_time: metadata.log_time,
-                    // Use log_time as publish_time (required when publish_time unavailable)
-                    publish_time: metadata.log_time,
+                    publish_time: metadata.publish_time,
                 },
                 msg,
             )
-            .map_err(FoxgloveError::from)
+            .map_err(FoxgloveError::from)?;
+        *self.channel_bytes.entry(mcap_channel_id).or_default() += msg.len() as u64;
+
+        if let Some(sync) = &mut self.sync {
+            if sync.on_message(msg.len()) {
+                // Flushing closes the current chunk, and flushes the underlying writer.
+                self.writer.flush()?;
+                sync.sync()?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -93,6 +126,7 @@ pub struct McapSink<W: Write + Seek> {
     sink_id: SinkId,
     inner: Mutex<Option<WriterState<W>>>,
     channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+    message_filter: Option<Arc<dyn SinkMessageFilter>>,
 }
 impl<W: Write + Seek> Debug for McapSink<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -108,25 +142,29 @@ impl<W: Write + Seek> McapSink<W> {
         writer: W,
         options: WriteOptions,
         channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+        message_filter: Option<Arc<dyn SinkMessageFilter>>,
     ) -> Result<Arc<McapSink<W>>, FoxgloveError> {
         let mcap_writer = options.create(writer).map_err(FoxgloveError::from)?;
         let writer = Arc::new(Self {
             sink_id: SinkId::next(),
             inner: Mutex::new(Some(WriterState::new(mcap_writer))),
             channel_filter,
+            message_filter,
         });
         Ok(writer)
     }
 
     /// Finalizes the MCAP recording and flushes it to the file.
     ///
-    /// Returns the inner writer that was passed to [`McapWriter::new`].
-    pub fn finish(&self) -> Result<Option<W>, FoxgloveError> {
+    /// Returns the inner writer that was passed to [`McapWriter::new`], and a summary of the
+    /// recording.
+    pub fn finish(&self) -> Result<Option<(W, RecordingSummary)>, FoxgloveError> {
         let Some(mut writer) = self.inner.lock().take() else {
             return Ok(None);
         };
-        writer.writer.finish()?;
-        Ok(Some(writer.writer.into_inner()))
+        let summary = writer.writer.finish()?;
+        let summary = RecordingSummary::new(&summary, &writer.channel_bytes);
+        Ok(Some((writer.writer.into_inner(), summary)))
     }
 
     /// Writes MCAP metadata to the file.
@@ -185,6 +223,91 @@ impl<W: Write + Seek> McapSink<W> {
             .attach(attachment)
             .map_err(FoxgloveError::from)
     }
+
+    /// Writes an attachment to the MCAP file, streaming `length` bytes of data from `reader`.
+    ///
+    /// The data is copied in fixed-size pieces, and the attachment CRC is computed as it is
+    /// written. Logging to the sink blocks until the attachment is complete, so the reader should
+    /// be fast, such as a local file.
+    ///
+    /// If the reader fails, or ends before providing `length` bytes, the remainder of the
+    /// attachment is filled with zeros so that the file remains valid, and an error is returned.
+    pub fn write_attachment(
+        &self,
+        header: mcap::records::AttachmentHeader,
+        length: u64,
+        reader: &mut dyn Read,
+    ) -> Result<(), FoxgloveError> {
+        let mut guard = self.inner.lock();
+        let writer = guard.as_mut().ok_or(FoxgloveError::SinkClosed)?;
+
+        writer.writer.start_attachment(length, header)?;
+        let mut buf = vec![0; ATTACHMENT_COPY_SIZE];
+        let mut remaining = length;
+        let mut result = Ok(());
+        while remaining > 0 {
+            let len = buf
+                .len()
+                .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+            let read = match reader.read(&mut buf[..len]) {
+                Ok(0) => {
+                    result = Err(FoxgloveError::ValueError(format!(
+                        "attachment data ended {remaining} bytes before the declared length of \
+                         {length} bytes"
+                    )));
+                    break;
+                }
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    result = Err(e.into());
+                    break;
+                }
+            };
+            writer.writer.put_attachment_bytes(&buf[..read])?;
+            remaining -= read as u64;
+        }
+        if remaining > 0 {
+            buf.fill(0);
+            while remaining > 0 {
+                let len = buf
+                    .len()
+                    .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+                writer.writer.put_attachment_bytes(&buf[..len])?;
+                remaining -= len as u64;
+            }
+        }
+        writer.writer.finish_attachment()?;
+        result
+    }
+}
+
+impl<W: Write + Seek> McapSink<W> {
+    /// Adds the schema and channel to the file, if they have not already been added.
+    pub(crate) fn add_channel(&self, channel: &ChannelDescriptor) -> Result<(), FoxgloveError> {
+        let mut guard = self.inner.lock();
+        let writer = guard.as_mut().ok_or(FoxgloveError::SinkClosed)?;
+        writer.mcap_channel_id(channel).map(|_| ())
+    }
+
+    /// Enables syncing the file to disk.
+    pub(crate) fn set_sync(&self, sync: SyncState) {
+        if let Some(writer) = self.inner.lock().as_mut() {
+            writer.sync = Some(sync);
+        }
+    }
+
+    /// Writes a message for the channel to the file, bypassing filters.
+    pub(crate) fn write_message(
+        &self,
+        channel: &ChannelDescriptor,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        let mut guard = self.inner.lock();
+        let writer = guard.as_mut().ok_or(FoxgloveError::SinkClosed)?;
+        writer.log(channel, msg, metadata)
+    }
 }
 
 impl<W: Write + Seek + Send> Sink for McapSink<W> {
@@ -198,10 +321,12 @@ impl<W: Write + Seek + Send> Sink for McapSink<W> {
         msg: &[u8],
         metadata: &Metadata,
     ) -> Result<(), FoxgloveError> {
-        _ = metadata;
-        let mut guard = self.inner.lock();
-        let writer = guard.as_mut().ok_or(FoxgloveError::SinkClosed)?;
-        writer.log(channel, msg, metadata)
+        if let Some(filter) = &self.message_filter {
+            if !filter.should_log(channel, msg, metadata) {
+                return Ok(());
+            }
+        }
+        self.write_message(channel.descriptor(), msg, metadata)
     }
 
     fn auto_subscribe(&self) -> bool {
@@ -222,7 +347,9 @@ impl<W: Write + Seek + Send> Sink for McapSink<W> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{testutil::read_summary, ChannelBuilder, Context, Metadata, Schema};
+    use crate::{
+        testutil::read_summary, ChannelBuilder, Context, Metadata, PartialMetadata, Schema,
+    };
     use mcap::McapError;
     use std::path::Path;
     use tempfile::NamedTempFile;
@@ -270,14 +397,36 @@ mod tests {
         let temp_path = temp_file.path().to_owned();
 
         // Generate some unique metadata for each message
-        let ch1_meta = &[Metadata { log_time: 3 }, Metadata { log_time: 6 }];
+        let ch1_meta = &[
+            Metadata {
+                log_time: 3,
+                publish_time: 1,
+                sequence: None,
+            },
+            Metadata {
+                log_time: 6,
+                publish_time: 4,
+                sequence: None,
+            },
+        ];
         let mut ch1_meta_iter = ch1_meta.iter();
 
-        let ch2_meta = &[Metadata { log_time: 9 }, Metadata { log_time: 12 }];
+        let ch2_meta = &[
+            Metadata {
+                log_time: 9,
+                publish_time: 7,
+                sequence: None,
+            },
+            Metadata {
+                log_time: 12,
+                publish_time: 10,
+                sequence: None,
+            },
+        ];
         let mut ch2_meta_iter = ch2_meta.iter();
 
         // Log two messages to each channel, interleaved
-        let writer = McapSink::new(&temp_file, WriteOptions::default(), None)
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
             .expect("failed to create writer");
         writer
             .log(&ch1, b"msg1", &ch1_meta[0])
@@ -309,7 +458,7 @@ mod tests {
                         ch1_msgs_iter.next().expect("unexpected message channel 1")
                     );
                     let metadata = ch1_meta_iter.next().expect("unexpected metadata channel 1");
-                    assert_eq!(msg.publish_time, metadata.log_time); // publish_time == log_time
+                    assert_eq!(msg.publish_time, metadata.publish_time);
                     assert_eq!(msg.log_time, metadata.log_time);
                     assert_eq!(msg.channel.topic, "foo");
                     assert_eq!(
@@ -323,7 +472,7 @@ mod tests {
                         ch2_msgs_iter.next().expect("unexpected message channel 2")
                     );
                     let metadata = ch2_meta_iter.next().expect("unexpected metadata channel 2");
-                    assert_eq!(msg.publish_time, metadata.log_time); // publish_time == log_time
+                    assert_eq!(msg.publish_time, metadata.publish_time);
                     assert_eq!(msg.log_time, metadata.log_time);
                     assert_eq!(msg.channel.topic, "bar");
                     assert_eq!(
@@ -350,7 +499,7 @@ mod tests {
         let temp_path = temp_file.path().to_owned();
 
         let metadata = Metadata::default();
-        let writer = McapSink::new(&temp_file, WriteOptions::default(), None)
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
             .expect("failed to create writer");
 
         writer
@@ -402,6 +551,46 @@ mod tests {
         assert_eq!(messages[5].sequence, 4);
     }
 
+    #[test]
+    fn test_provided_publish_time_and_sequence() {
+        let ctx = Context::new();
+        let ch = new_test_channel(&ctx, "foo".to_string(), "foo_schema".to_string());
+
+        let temp_file = NamedTempFile::new().expect("failed to create tempfile");
+        let temp_path = temp_file.path().to_owned();
+        let writer = crate::McapWriter::new()
+            .context(&ctx)
+            .create(temp_file)
+            .expect("failed to create writer");
+
+        ch.log_with_meta(
+            b"msg1",
+            PartialMetadata::with_log_time(10)
+                .publish_time(5)
+                .sequence(42),
+        );
+        // Generated sequence numbers continue from the provided one.
+        ch.log_with_meta(b"msg2", PartialMetadata::with_log_time(20));
+        let file = writer.close().expect("failed to close writer");
+
+        let contents = std::fs::read(&temp_path).expect("failed to read mcap");
+        let messages: Vec<mcap::Message> = mcap::MessageStream::new(&contents)
+            .expect("failed to create message stream")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("failed to collect messages");
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].log_time, 10);
+        assert_eq!(messages[0].publish_time, 5);
+        assert_eq!(messages[0].sequence, 42);
+        // Publish time defaults to the log time.
+        assert_eq!(messages[1].log_time, 20);
+        assert_eq!(messages[1].publish_time, 20);
+        assert_eq!(messages[1].sequence, 43);
+
+        drop(file);
+    }
+
     fn foreach_mcap_metadata<F>(path: &Path, mut f: F) -> Result<(), McapError>
     where
         F: FnMut(&mcap::records::Metadata),
@@ -458,7 +647,7 @@ mod tests {
         let temp_file = NamedTempFile::new().expect("create tempfile");
         let temp_path = temp_file.path().to_owned();
 
-        let writer = McapSink::new(&temp_file, WriteOptions::default(), None)
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
             .expect("failed to create writer");
 
         let mut metadata = BTreeMap::new();
@@ -483,7 +672,7 @@ mod tests {
         let temp_file = NamedTempFile::new().expect("create tempfile");
         let temp_path = temp_file.path().to_owned();
 
-        let writer = McapSink::new(&temp_file, WriteOptions::default(), None)
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
             .expect("failed to create writer");
 
         let empty_metadata = BTreeMap::new();
@@ -505,7 +694,7 @@ mod tests {
         let temp_file = NamedTempFile::new().expect("create tempfile");
         let temp_path = temp_file.path().to_owned();
 
-        let writer = McapSink::new(&temp_file, WriteOptions::default(), None)
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
             .expect("failed to create writer");
 
         let mut session = BTreeMap::new();
@@ -536,7 +725,7 @@ mod tests {
     fn test_write_metadata_after_close() {
         let temp_file = NamedTempFile::new().expect("create tempfile");
 
-        let writer = McapSink::new(&temp_file, WriteOptions::default(), None)
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
             .expect("failed to create writer");
 
         // Close the writer
@@ -602,6 +791,51 @@ mod tests {
         drop(file2);
     }
 
+    #[test]
+    fn test_message_filter() {
+        use crate::McapWriter;
+
+        let ctx = Context::new();
+
+        let temp_file = NamedTempFile::new().expect("failed to create tempfile");
+        let temp_path = temp_file.path().to_owned();
+        let writer = McapWriter::new()
+            .context(&ctx)
+            .message_filter_fn(|channel, msg, metadata| {
+                channel.topic() == "/1" && msg != b"skip" && metadata.log_time < 100
+            })
+            .create(temp_file)
+            .expect("failed to create writer");
+
+        let ch1 = ChannelBuilder::new("/1")
+            .context(&ctx)
+            .message_encoding("json")
+            .build_raw()
+            .unwrap();
+        let ch2 = ChannelBuilder::new("/2")
+            .context(&ctx)
+            .message_encoding("json")
+            .build_raw()
+            .unwrap();
+
+        ch1.log_with_meta(b"{}", PartialMetadata::with_log_time(1));
+        ch1.log_with_meta(b"skip", PartialMetadata::with_log_time(2));
+        ch1.log_with_meta(b"{}", PartialMetadata::with_log_time(100));
+        ch2.log_with_meta(b"{}", PartialMetadata::with_log_time(3));
+
+        let file = writer.close().expect("failed to close writer");
+
+        // Only one message passes the filter, and channels without messages are not recorded.
+        let summary = read_summary(&temp_path);
+        assert_eq!(summary.channels.len(), 1);
+        assert_eq!(summary.channels.get(&1).unwrap().topic, "/1");
+        let stats = summary.stats.unwrap();
+        assert_eq!(stats.message_count, 1);
+        assert_eq!(stats.message_start_time, 1);
+
+        drop(file);
+    }
+
     fn foreach_mcap_attachment<F>(path: &Path, mut f: F) -> Result<(), McapError>
     where
         F: FnMut(&mcap::records::AttachmentHeader, &[u8]),
@@ -621,7 +855,7 @@ mod tests {
         let temp_file = NamedTempFile::new().expect("create tempfile");
         let temp_path = temp_file.path().to_owned();
 
-        let writer = McapSink::new(&temp_file, WriteOptions::default(), None)
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
             .expect("failed to create writer");
 
         let attachment_data = b"hello, attachment!";
@@ -657,7 +891,7 @@ mod tests {
         let temp_file = NamedTempFile::new().expect("create tempfile");
         let temp_path = temp_file.path().to_owned();
 
-        let writer = McapSink::new(&temp_file, WriteOptions::default(), None)
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
             .expect("failed to create writer");
 
         writer
@@ -703,11 +937,87 @@ mod tests {
         assert_eq!(image.1, &[0x89, 0x50, 0x4E, 0x47]);
     }
 
+    #[test]
+    fn test_attach_from_reader() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        let header = mcap::records::AttachmentHeader {
+            log_time: 100,
+            create_time: 200,
+            name: "map.bin".to_string(),
+            media_type: "application/octet-stream".to_string(),
+        };
+
+        let streamed = NamedTempFile::new().expect("create tempfile");
+        let writer = McapSink::new(&streamed, WriteOptions::default(), None, None)
+            .expect("failed to create writer");
+        writer
+            .write_attachment(header.clone(), data.len() as u64, &mut &data[..])
+            .expect("failed to attach");
+        writer.finish().expect("failed to finish recording");
+
+        let buffered = NamedTempFile::new().expect("create tempfile");
+        let writer = McapSink::new(&buffered, WriteOptions::default(), None, None)
+            .expect("failed to create writer");
+        writer
+            .attach(&mcap::Attachment {
+                log_time: header.log_time,
+                create_time: header.create_time,
+                name: header.name.clone(),
+                media_type: header.media_type.clone(),
+                data: std::borrow::Cow::Borrowed(&data),
+            })
+            .expect("failed to attach");
+        writer.finish().expect("failed to finish recording");
+
+        // The streamed attachment is identical to one written from memory, including its CRC.
+        let read_attachment = |path: &Path| {
+            let contents = std::fs::read(path).unwrap();
+            mcap::read::LinearReader::new(&contents)
+                .unwrap()
+                .find_map(|record| match record.unwrap() {
+                    mcap::records::Record::Attachment { header, data, crc } => {
+                        Some((header, data.into_owned(), crc))
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+        let (found_header, found_data, crc) = read_attachment(streamed.path());
+        assert_eq!(found_header, header);
+        assert_eq!(found_data, data);
+        assert_ne!(crc, 0);
+        assert_eq!(crc, read_attachment(buffered.path()).2);
+    }
+
+    #[test]
+    fn test_attach_from_short_reader() {
+        let temp_file = NamedTempFile::new().expect("create tempfile");
+        let temp_path = temp_file.path().to_owned();
+
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
+            .expect("failed to create writer");
+        let header = mcap::records::AttachmentHeader {
+            log_time: 0,
+            create_time: 0,
+            name: "short.bin".to_string(),
+            media_type: "application/octet-stream".to_string(),
+        };
+        let result = writer.write_attachment(header, 8, &mut &b"abc"[..]);
+        assert!(matches!(result, Err(FoxgloveError::ValueError(_))));
+        writer.finish().expect("failed to finish recording");
+
+        // The attachment is padded, so the file remains valid.
+        let mut found_attachments = Vec::new();
+        foreach_mcap_attachment(&temp_path, |_, data| found_attachments.push(data.to_vec()))
+            .expect("failed to read MCAP attachments");
+        assert_eq!(found_attachments, vec![b"abc\0\0\0\0\0".to_vec()]);
+    }
+
     #[test]
     fn test_attach_after_close() {
         let temp_file = NamedTempFile::new().expect("create tempfile");
 
-        let writer = McapSink::new(&temp_file, WriteOptions::default(), None)
+        let writer = McapSink::new(&temp_file, WriteOptions::default(), None, None)
             .expect("failed to create writer");
 
         // Close the writer
@@ -0,0 +1,703 @@
+//! Runtime control of MCAP recordings.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "live_visualization")]
+use std::sync::Weak;
+
+use parking_lot::Mutex;
+
+use super::rotating::format_utc_timestamp;
+use super::{McapWriter, McapWriterHandle, RecordingSummary};
+use crate::{nanoseconds_since_epoch, FoxgloveError};
+#[cfg(feature = "live_visualization")]
+use crate::{
+    websocket::{
+        service::{Service, ServiceSchema},
+        Capability, Client, Parameter, Server, ServerListener,
+    },
+    Schema, WebSocketServer, WebSocketServerHandle,
+};
+
+/// The name of the metadata records written by [`RecordingController::add_marker`].
+const MARKER_METADATA_NAME: &str = "marker";
+
+/// The name of the parameter that indicates whether a recording is in progress.
+#[cfg(feature = "live_visualization")]
+const ACTIVE_PARAMETER: &str = "recording.active";
+/// The name of the parameter that holds the path of the current recording.
+#[cfg(feature = "live_visualization")]
+const PATH_PARAMETER: &str = "recording.path";
+/// The name of the parameter that holds the number of markers in the current recording.
+#[cfg(feature = "live_visualization")]
+const MARKER_COUNT_PARAMETER: &str = "recording.marker_count";
+
+/// A recording that was stopped or split by a [`RecordingController`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FinishedRecording {
+    /// The path to the recording file.
+    pub path: PathBuf,
+    /// A summary of the recording.
+    pub summary: RecordingSummary,
+}
+
+/// The recording currently being written.
+struct Recording {
+    handle: McapWriterHandle<BufWriter<File>>,
+    path: PathBuf,
+    markers: u64,
+}
+
+impl Recording {
+    fn finish(self) -> Result<FinishedRecording, FoxgloveError> {
+        let (_, summary) = self.handle.close()?;
+        Ok(FinishedRecording {
+            path: self.path,
+            summary,
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    current: Option<Recording>,
+    next_index: u64,
+}
+
+struct Inner {
+    writer: McapWriter,
+    dir: PathBuf,
+    state: Mutex<State>,
+    #[cfg(feature = "live_visualization")]
+    server: Mutex<Option<Weak<Server>>>,
+}
+
+impl Inner {
+    /// Opens a new recording file in the directory.
+    fn open(&self, state: &mut State) -> Result<PathBuf, FoxgloveError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let name = format!(
+            "{}_{:04}.mcap",
+            format_utc_timestamp(nanoseconds_since_epoch()),
+            state.next_index
+        );
+        let path = self.dir.join(name);
+        let handle = self.writer.clone().create_new_buffered_file(&path)?;
+        state.next_index += 1;
+        state.current = Some(Recording {
+            handle,
+            path: path.clone(),
+            markers: 0,
+        });
+        Ok(path)
+    }
+}
+
+/// Starts, stops, splits and annotates MCAP recordings at runtime.
+///
+/// The controller owns an [`McapWriter`] configuration, which it uses to create a new file in a
+/// directory each time a recording is started or split. Files are named
+/// `{timestamp}_{index}.mcap`, where `{timestamp}` is the UTC time at which the file was created,
+/// formatted as `YYYYMMDDTHHMMSSZ`, and `{index}` is the zero-based index of the file among those
+/// created by the controller, padded to four digits.
+///
+/// The controller is cheap to clone, and clones share the same recording. The current recording
+/// is finalized when it is stopped, or when the controller and all of its clones, services and
+/// listeners are dropped.
+///
+/// # WebSocket integration
+///
+/// With [`RecordingController::websocket_server`], the Foxglove app can control recordings with
+/// the following services, which take and return JSON-encoded requests and responses:
+///
+/// - `start_recording`: starts a recording, and responds with its `path`.
+/// - `stop_recording`: stops the recording, and responds with its `path` and `message_count`.
+/// - `add_marker`: adds a marker with the request's `label` to the recording.
+///
+/// The recording state is exposed as read-only parameters: `recording.active`, `recording.path`
+/// and `recording.marker_count`. Once the server has started, call
+/// [`RecordingController::attach`] to publish changes to the parameters to subscribed clients.
+///
+/// ```no_run
+/// use foxglove::{McapWriter, RecordingController, WebSocketServer};
+///
+/// let controller = RecordingController::new(McapWriter::new(), "recordings");
+/// let server = controller
+///     .websocket_server(WebSocketServer::new())
+///     .start_blocking()
+///     .expect("failed to start server");
+/// controller.attach(&server);
+/// ```
+#[derive(Clone)]
+pub struct RecordingController {
+    inner: Arc<Inner>,
+}
+
+impl Debug for RecordingController {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingController")
+            .field("writer", &self.inner.writer)
+            .field("dir", &self.inner.dir)
+            .field("current_path", &self.current_path())
+            .finish_non_exhaustive()
+    }
+}
+
+impl RecordingController {
+    /// Creates a controller that records with the given writer configuration, to files in `dir`.
+    ///
+    /// The directory is created when the first recording is started, if it does not exist.
+    pub fn new(writer: McapWriter, dir: impl AsRef<Path>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                writer,
+                dir: dir.as_ref().to_path_buf(),
+                state: Mutex::default(),
+                #[cfg(feature = "live_visualization")]
+                server: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Starts a new recording, and returns the path of the file.
+    ///
+    /// Returns an error if a recording is already in progress.
+    pub fn start(&self) -> Result<PathBuf, FoxgloveError> {
+        let path = {
+            let mut state = self.inner.state.lock();
+            if state.current.is_some() {
+                return Err(FoxgloveError::ValueError(
+                    "A recording is already in progress".to_string(),
+                ));
+            }
+            self.inner.open(&mut state)?
+        };
+        self.publish_state();
+        Ok(path)
+    }
+
+    /// Stops the current recording, and returns the finalized recording.
+    ///
+    /// Returns `None` if no recording is in progress.
+    pub fn stop(&self) -> Result<Option<FinishedRecording>, FoxgloveError> {
+        let recording = self.inner.state.lock().current.take();
+        let Some(recording) = recording else {
+            return Ok(None);
+        };
+        let result = recording.finish();
+        self.publish_state();
+        result.map(Some)
+    }
+
+    /// Finalizes the current recording, and continues recording to a new file.
+    ///
+    /// Returns the finalized recording. The path of the new file is available from
+    /// [`RecordingController::current_path`]. Returns an error if no recording is in progress, or
+    /// if the current recording could not be finalized.
+    ///
+    /// If the new file cannot be created, the finalized recording is still returned, and the
+    /// error is logged. In that case, recording stops, and [`RecordingController::is_recording`]
+    /// returns false.
+    ///
+    /// The new file is opened before the current recording is finalized, so that channels remain
+    /// subscribed throughout the split. A message logged while the split is in progress may be
+    /// written to both files.
+    pub fn split(&self) -> Result<FinishedRecording, FoxgloveError> {
+        let recording = {
+            let mut state = self.inner.state.lock();
+            let recording = state.current.take().ok_or_else(not_recording)?;
+            if let Err(e) = self.inner.open(&mut state) {
+                tracing::warn!("Failed to start a new recording after split: {e}");
+            }
+            recording
+        };
+        let result = recording.finish();
+        self.publish_state();
+        result
+    }
+
+    /// Adds a marker with the given label to the current recording.
+    ///
+    /// The marker is written as an MCAP metadata record named `marker`, with the `label`, and the
+    /// `log_time` from the writer's [`Context`][crate::Context] clock, in nanoseconds. Returns an
+    /// error if no recording is in progress.
+    pub fn add_marker(&self, label: &str) -> Result<(), FoxgloveError> {
+        {
+            let mut state = self.inner.state.lock();
+            let recording = state.current.as_mut().ok_or_else(not_recording)?;
+            let metadata = BTreeMap::from([
+                ("label".to_string(), label.to_string()),
+                (
+                    "log_time".to_string(),
+                    self.inner.writer.context.now().to_string(),
+                ),
+            ]);
+            recording
+                .handle
+                .write_metadata(MARKER_METADATA_NAME, metadata)?;
+            recording.markers += 1;
+        }
+        self.publish_state();
+        Ok(())
+    }
+
+    /// Returns true if a recording is in progress.
+    pub fn is_recording(&self) -> bool {
+        self.inner.state.lock().current.is_some()
+    }
+
+    /// Returns the path of the current recording, if a recording is in progress.
+    pub fn current_path(&self) -> Option<PathBuf> {
+        self.inner
+            .state
+            .lock()
+            .current
+            .as_ref()
+            .map(|r| r.path.clone())
+    }
+
+    /// Returns the recording state, as WebSocket parameters.
+    #[cfg(feature = "live_visualization")]
+    pub fn parameters(&self) -> Vec<Parameter> {
+        let state = self.inner.state.lock();
+        let current = state.current.as_ref();
+        vec![
+            Parameter::bool(ACTIVE_PARAMETER, current.is_some()),
+            Parameter::string(
+                PATH_PARAMETER,
+                current
+                    .map(|r| r.path.display().to_string())
+                    .unwrap_or_default(),
+            ),
+            Parameter::integer(
+                MARKER_COUNT_PARAMETER,
+                current.map_or(0, |r| r.markers as i64),
+            ),
+        ]
+    }
+
+    /// Returns the `start_recording`, `stop_recording` and `add_marker` services.
+    ///
+    /// Service handlers are invoked on a blocking thread, since they create and finalize files.
+    #[cfg(feature = "live_visualization")]
+    pub fn services(&self) -> Vec<Service> {
+        let start = {
+            let controller = self.clone();
+            Service::builder(
+                "start_recording",
+                service_schema("StartRecording", &["path"]),
+            )
+            .blocking_handler_fn(move |_| {
+                let path = controller.start()?;
+                json_response(serde_json::json!({ "path": path.to_string_lossy() }))
+            })
+        };
+        let stop = {
+            let controller = self.clone();
+            Service::builder(
+                "stop_recording",
+                service_schema("StopRecording", &["path", "message_count"]),
+            )
+            .blocking_handler_fn(move |_| {
+                let finished = controller.stop()?.ok_or_else(not_recording)?;
+                json_response(serde_json::json!({
+                    "path": finished.path.to_string_lossy(),
+                    "message_count": finished.summary.message_count,
+                }))
+            })
+        };
+        let marker = {
+            let controller = self.clone();
+            let schema = ServiceSchema::new("AddMarker")
+                .with_request(
+                    "json",
+                    Schema::new(
+                        "AddMarkerRequest",
+                        "jsonschema",
+                        br#"{"type":"object","properties":{"label":{"type":"string"}},"required":["label"]}"#,
+                    ),
+                )
+                .with_response("json", json_schema("AddMarkerResponse", &[]));
+            Service::builder("add_marker", schema).blocking_handler_fn(move |req| {
+                #[derive(serde::Deserialize)]
+                struct AddMarkerRequest {
+                    label: String,
+                }
+                let request: AddMarkerRequest = serde_json::from_slice(req.payload())
+                    .map_err(|e| FoxgloveError::ValueError(e.to_string()))?;
+                controller.add_marker(&request.label)?;
+                json_response(serde_json::json!({}))
+            })
+        };
+        vec![start, stop, marker]
+    }
+
+    /// Returns a [`ServerListener`] that exposes the recording state as read-only parameters.
+    ///
+    /// A server has a single listener. If you need to handle other events, implement your own
+    /// listener, and respond to parameter requests with [`RecordingController::parameters`].
+    #[cfg(feature = "live_visualization")]
+    pub fn listener(&self) -> Arc<dyn ServerListener> {
+        Arc::new(RecordingListener(self.clone()))
+    }
+
+    /// Configures a WebSocket server with the controller's services and listener.
+    ///
+    /// This adds the [`Services`][Capability::Services] and
+    /// [`Parameters`][Capability::Parameters] capabilities, and replaces the server's listener.
+    #[cfg(feature = "live_visualization")]
+    pub fn websocket_server(&self, server: WebSocketServer) -> WebSocketServer {
+        self.services()
+            .into_iter()
+            .fold(server, |server, service| server.add_service(service))
+            .add_capability(Capability::Parameters)
+            .listener(self.listener())
+    }
+
+    /// Publishes changes to the recording state to the server's clients, as parameter updates.
+    ///
+    /// Only clients that have subscribed to the recording parameters receive updates.
+    #[cfg(feature = "live_visualization")]
+    pub fn attach(&self, server: &WebSocketServerHandle) {
+        *self.inner.server.lock() = Some(server.weak_server());
+    }
+
+    /// Publishes the recording state to the attached server, if any.
+    fn publish_state(&self) {
+        #[cfg(feature = "live_visualization")]
+        {
+            let server = self.inner.server.lock().as_ref().and_then(Weak::upgrade);
+            if let Some(server) = server {
+                if server.has_capability(Capability::Parameters) {
+                    server.publish_parameter_values(self.parameters());
+                }
+            }
+        }
+    }
+}
+
+fn not_recording() -> FoxgloveError {
+    FoxgloveError::ValueError("No recording is in progress".to_string())
+}
+
+/// Creates a JSON schema for an object with the given string or integer properties.
+#[cfg(feature = "live_visualization")]
+fn json_schema(name: &str, properties: &[&str]) -> Schema {
+    let properties: serde_json::Map<_, _> = properties
+        .iter()
+        .map(|p| (p.to_string(), serde_json::json!({})))
+        .collect();
+    let schema = serde_json::json!({ "type": "object", "properties": properties });
+    Schema::new(name, "jsonschema", schema.to_string().into_bytes())
+}
+
+/// Creates a schema for a service with an empty request, and a response with the given
+/// properties.
+#[cfg(feature = "live_visualization")]
+fn service_schema(name: &str, response_properties: &[&str]) -> ServiceSchema {
+    ServiceSchema::new(name)
+        .with_request("json", json_schema(&format!("{name}Request"), &[]))
+        .with_response(
+            "json",
+            json_schema(&format!("{name}Response"), response_properties),
+        )
+}
+
+#[cfg(feature = "live_visualization")]
+fn json_response(value: serde_json::Value) -> Result<Vec<u8>, FoxgloveError> {
+    serde_json::to_vec(&value).map_err(|e| FoxgloveError::EncodeError(e.to_string()))
+}
+
+/// Exposes the recording state as read-only parameters.
+#[cfg(feature = "live_visualization")]
+struct RecordingListener(RecordingController);
+
+#[cfg(feature = "live_visualization")]
+impl ServerListener for RecordingListener {
+    fn on_get_parameters(
+        &self,
+        _client: Client,
+        param_names: Vec<String>,
+        _request_id: Option<&str>,
+    ) -> Vec<Parameter> {
+        self.0
+            .parameters()
+            .into_iter()
+            .filter(|p| param_names.is_empty() || param_names.contains(&p.name))
+            .collect()
+    }
+
+    fn on_set_parameters(
+        &self,
+        _client: Client,
+        parameters: Vec<Parameter>,
+        _request_id: Option<&str>,
+    ) -> Vec<Parameter> {
+        // Recording parameters are read-only, so respond with their current values. Unknown
+        // parameters are omitted from the response, rather than echoed as if they were set.
+        let current = self.0.parameters();
+        parameters
+            .into_iter()
+            .filter_map(|p| current.iter().find(|c| c.name == p.name).cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::read_summary;
+    use crate::{ChannelBuilder, Context};
+
+    #[test]
+    fn test_start_split_stop() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let controller = RecordingController::new(McapWriter::new().context(&ctx), dir.path());
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+
+        ch.log(b"dropped");
+        assert!(controller.add_marker("nope").is_err());
+        assert!(controller.split().is_err());
+        assert!(controller.stop().unwrap().is_none());
+
+        let first = controller.start().unwrap();
+        assert!(controller.start().is_err());
+        assert_eq!(controller.current_path(), Some(first.clone()));
+        ch.log(b"a");
+        controller.add_marker("interesting").unwrap();
+
+        let finished = controller.split().unwrap();
+        assert_eq!(finished.path, first);
+        assert_eq!(finished.summary.message_count, 1);
+        assert_eq!(finished.summary.metadata_count, 1);
+        let second = controller.current_path().unwrap();
+        assert_ne!(second, first);
+        ch.log(b"b");
+        ch.log(b"c");
+
+        let finished = controller.stop().unwrap().unwrap();
+        assert_eq!(finished.path, second);
+        assert_eq!(finished.summary.message_count, 2);
+        assert!(!controller.is_recording());
+        ch.log(b"dropped");
+
+        assert_eq!(read_summary(&first).stats.unwrap().message_count, 1);
+        assert_eq!(read_summary(&second).stats.unwrap().message_count, 2);
+    }
+
+    /// Records whether a channel still has sinks when a sink unsubscribes from it.
+    #[derive(Default)]
+    struct UnsubscribeObserver(parking_lot::Mutex<Vec<bool>>);
+
+    impl crate::ContextObserver for UnsubscribeObserver {
+        fn on_unsubscribe(&self, channel: &Arc<crate::RawChannel>, _sink_id: crate::SinkId) {
+            self.0.lock().push(channel.has_sinks());
+        }
+    }
+
+    #[test]
+    fn test_split_keeps_channel_subscribed() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let controller = RecordingController::new(McapWriter::new().context(&ctx), dir.path());
+        let _ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        let observer = Arc::new(UnsubscribeObserver::default());
+        ctx.add_observer(observer.clone());
+        controller.start().unwrap();
+
+        // The new recording is subscribed before the old one is removed, so there is no window in
+        // which messages have no sink.
+        controller.split().unwrap();
+        assert_eq!(*observer.0.lock(), vec![true]);
+
+        controller.stop().unwrap();
+        assert_eq!(*observer.0.lock(), vec![true, false]);
+    }
+
+    #[test]
+    fn test_split_open_failure() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("recordings");
+        let ctx = Context::new();
+        let controller = RecordingController::new(McapWriter::new().context(&ctx), &dir);
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        let first = controller.start().unwrap();
+        ch.log(b"a");
+
+        // Replace the directory with a file, so that the next recording cannot be created.
+        let moved = root.path().join("moved");
+        std::fs::rename(&dir, &moved).unwrap();
+        std::fs::write(&dir, b"").unwrap();
+
+        // The finished recording is returned, even though recording could not continue.
+        let finished = controller.split().unwrap();
+        assert_eq!(finished.path, first);
+        assert_eq!(finished.summary.message_count, 1);
+        assert!(!controller.is_recording());
+        let moved_file = moved.join(first.file_name().unwrap());
+        assert_eq!(read_summary(moved_file).stats.unwrap().message_count, 1);
+    }
+
+    #[cfg(feature = "live_visualization")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_websocket_services_and_parameters() {
+        use crate::websocket::ws_protocol::client::{
+            GetParameters, ServiceCallRequest, SetParameters, SubscribeParameterUpdates,
+        };
+        use crate::websocket::ws_protocol::server::ServerMessage;
+        use crate::websocket_client::WebSocketClient;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let controller = RecordingController::new(McapWriter::new().context(&ctx), dir.path());
+        let server = controller
+            .websocket_server(WebSocketServer::new().context(&ctx).bind("127.0.0.1", 0))
+            .start()
+            .await
+            .unwrap();
+        controller.attach(&server);
+
+        let mut client = WebSocketClient::connect(format!("127.0.0.1:{}", server.port()))
+            .await
+            .unwrap();
+        let ServerMessage::ServerInfo(_) = client.recv().await.unwrap() else {
+            panic!("expected server info");
+        };
+        let ServerMessage::AdvertiseServices(advertise) = client.recv().await.unwrap() else {
+            panic!("expected advertise services");
+        };
+        let service_id = |name: &str| {
+            advertise
+                .services
+                .iter()
+                .find(|s| s.name == name)
+                .map(|s| s.id)
+                .unwrap()
+        };
+        let (start_id, marker_id, stop_id) = (
+            service_id("start_recording"),
+            service_id("add_marker"),
+            service_id("stop_recording"),
+        );
+
+        client
+            .send(&SubscribeParameterUpdates::new([
+                ACTIVE_PARAMETER.to_string()
+            ]))
+            .await
+            .unwrap();
+        client
+            .send(&ServiceCallRequest {
+                service_id: start_id,
+                call_id: 1,
+                encoding: "json".into(),
+                payload: b"{}".into(),
+            })
+            .await
+            .unwrap();
+        let ServerMessage::ParameterValues(values) = client.recv().await.unwrap() else {
+            panic!("expected parameter values");
+        };
+        assert_eq!(
+            values.parameters,
+            vec![Parameter::bool(ACTIVE_PARAMETER, true)]
+        );
+        let ServerMessage::ServiceCallResponse(response) = client.recv().await.unwrap() else {
+            panic!("expected service call response");
+        };
+        let response: serde_json::Value = serde_json::from_slice(&response.payload).unwrap();
+        let path = controller.current_path().unwrap();
+        assert_eq!(response["path"], path.to_str().unwrap());
+
+        client
+            .send(&ServiceCallRequest {
+                service_id: marker_id,
+                call_id: 2,
+                encoding: "json".into(),
+                payload: br#"{"label": "here"}"#.into(),
+            })
+            .await
+            .unwrap();
+        // Every change to the recording state republishes the subscribed parameters.
+        let ServerMessage::ParameterValues(_) = client.recv().await.unwrap() else {
+            panic!("expected parameter values");
+        };
+        let ServerMessage::ServiceCallResponse(_) = client.recv().await.unwrap() else {
+            panic!("expected service call response");
+        };
+
+        client
+            .send(&GetParameters::new([MARKER_COUNT_PARAMETER.to_string()]))
+            .await
+            .unwrap();
+        let ServerMessage::ParameterValues(values) = client.recv().await.unwrap() else {
+            panic!("expected parameter values");
+        };
+        assert_eq!(
+            values.parameters,
+            vec![Parameter::integer(MARKER_COUNT_PARAMETER, 1)]
+        );
+
+        client
+            .send(&ServiceCallRequest {
+                service_id: stop_id,
+                call_id: 3,
+                encoding: "json".into(),
+                payload: b"{}".into(),
+            })
+            .await
+            .unwrap();
+        let ServerMessage::ParameterValues(values) = client.recv().await.unwrap() else {
+            panic!("expected parameter values");
+        };
+        assert_eq!(
+            values.parameters,
+            vec![Parameter::bool(ACTIVE_PARAMETER, false)]
+        );
+        let ServerMessage::ServiceCallResponse(_) = client.recv().await.unwrap() else {
+            panic!("expected service call response");
+        };
+        assert!(!controller.is_recording());
+        assert_eq!(read_summary(&path).stats.unwrap().metadata_count, 1);
+
+        // Recording parameters are read-only, and unknown parameters are not echoed.
+        client
+            .send(
+                &SetParameters::new([
+                    Parameter::bool(ACTIVE_PARAMETER, true),
+                    Parameter::bool("unknown", true),
+                ])
+                .with_id("set"),
+            )
+            .await
+            .unwrap();
+        let ServerMessage::ParameterValues(values) = client.recv().await.unwrap() else {
+            panic!("expected parameter values");
+        };
+        assert_eq!(values.id.as_deref(), Some("set"));
+        assert_eq!(
+            values.parameters,
+            vec![Parameter::bool(ACTIVE_PARAMETER, false)]
+        );
+
+        let _ = server.stop();
+    }
+}
@@ -0,0 +1,163 @@
+//! Summary of a finished MCAP recording.
+
+use std::collections::HashMap;
+
+use crate::Schema;
+
+/// A summary of a channel in a finished MCAP recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChannelSummary {
+    /// The channel topic.
+    pub topic: String,
+    /// The message encoding of the channel.
+    pub message_encoding: String,
+    /// The name of the channel's schema, if it has one.
+    pub schema_name: Option<String>,
+    /// The number of messages written on the channel.
+    pub message_count: u64,
+    /// The total size of the message payloads written on the channel, in bytes.
+    pub message_bytes: u64,
+}
+
+/// A summary of a finished MCAP recording, returned by
+/// [`McapWriterHandle::close`][crate::McapWriterHandle::close].
+///
+/// The summary is computed while writing, so it is available without re-reading the file.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RecordingSummary {
+    /// The channels in the recording, in the order in which they were added to the file.
+    ///
+    /// This includes channels on which no messages were written.
+    pub channels: Vec<ChannelSummary>,
+    /// The schemas in the recording, in the order in which they were added to the file.
+    pub schemas: Vec<Schema>,
+    /// The total number of messages.
+    pub message_count: u64,
+    /// The earliest message log time, or `None` if there are no messages.
+    pub start_time: Option<u64>,
+    /// The latest message log time, or `None` if there are no messages.
+    pub end_time: Option<u64>,
+    /// The number of chunks.
+    pub chunk_count: u32,
+    /// The ratio of uncompressed to compressed chunk data, or `None` if there are no chunks.
+    pub compression_ratio: Option<f64>,
+    /// The number of attachments.
+    pub attachment_count: u32,
+    /// The number of metadata records.
+    pub metadata_count: u32,
+}
+
+impl RecordingSummary {
+    /// Builds a summary from the writer's summary, and the number of message bytes written on each
+    /// MCAP channel.
+    pub(crate) fn new(summary: &mcap::Summary, channel_bytes: &HashMap<u16, u64>) -> Self {
+        let stats = summary.stats.clone().unwrap_or_default();
+
+        let mut channels: Vec<_> = summary.channels.values().collect();
+        channels.sort_by_key(|c| c.id);
+        let channels = channels
+            .into_iter()
+            .map(|c| ChannelSummary {
+                topic: c.topic.clone(),
+                message_encoding: c.message_encoding.clone(),
+                schema_name: c.schema.as_ref().map(|s| s.name.clone()),
+                message_count: stats
+                    .channel_message_counts
+                    .get(&c.id)
+                    .copied()
+                    .unwrap_or_default(),
+                message_bytes: channel_bytes.get(&c.id).copied().unwrap_or_default(),
+            })
+            .collect();
+
+        let mut schemas: Vec<_> = summary.schemas.values().collect();
+        schemas.sort_by_key(|s| s.id);
+        let schemas = schemas
+            .into_iter()
+            .map(|s| Schema::new(&s.name, &s.encoding, s.data.to_vec()))
+            .collect();
+
+        let (compressed, uncompressed) =
+            summary.chunk_indexes.iter().fold((0, 0), |(c, u), index| {
+                (c + index.compressed_size, u + index.uncompressed_size)
+            });
+        let compression_ratio = (compressed > 0).then(|| uncompressed as f64 / compressed as f64);
+
+        let has_messages = stats.message_count > 0;
+        Self {
+            channels,
+            schemas,
+            message_count: stats.message_count,
+            start_time: has_messages.then_some(stats.message_start_time),
+            end_time: has_messages.then_some(stats.message_end_time),
+            chunk_count: stats.chunk_count,
+            compression_ratio,
+            attachment_count: stats.attachment_count,
+            metadata_count: stats.metadata_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{ChannelBuilder, Context, McapWriter, PartialMetadata};
+
+    #[test]
+    fn test_recording_summary() {
+        let ctx = Context::new();
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create(Cursor::new(Vec::new()))
+            .unwrap();
+        let a = ChannelBuilder::new("/a")
+            .context(&ctx)
+            .message_encoding("json")
+            .schema(Schema::new("A", "jsonschema", br#"{}"#.to_vec()))
+            .build_raw()
+            .unwrap();
+        let empty = ChannelBuilder::new("/empty")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        empty.log_with_meta(b"", PartialMetadata::with_log_time(1));
+        a.log_with_meta(b"{\"x\":1}", PartialMetadata::with_log_time(20));
+        a.log_with_meta(b"{}", PartialMetadata::with_log_time(10));
+        handle
+            .write_metadata("info", [("k".to_string(), "v".to_string())].into())
+            .unwrap();
+
+        let (_, summary) = handle.close().unwrap();
+        assert_eq!(summary.message_count, 3);
+        assert_eq!(summary.start_time, Some(1));
+        assert_eq!(summary.end_time, Some(20));
+        assert_eq!(summary.chunk_count, 1);
+        assert!(summary.compression_ratio.is_some());
+        assert_eq!(summary.attachment_count, 0);
+        assert_eq!(summary.metadata_count, 1);
+        assert_eq!(summary.schemas.len(), 1);
+        assert_eq!(summary.schemas[0].name, "A");
+
+        let channels: Vec<_> = summary
+            .channels
+            .iter()
+            .map(|c| {
+                (
+                    c.topic.as_str(),
+                    c.schema_name.as_deref(),
+                    c.message_count,
+                    c.message_bytes,
+                )
+            })
+            .collect();
+        assert_eq!(
+            channels,
+            vec![("/empty", None, 1, 0), ("/a", Some("A"), 2, 9)]
+        );
+    }
+}
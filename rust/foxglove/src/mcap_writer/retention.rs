@@ -0,0 +1,252 @@
+//! Disk quota and retention for recording directories.
+
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::FoxgloveError;
+
+/// A callback invoked when a recording is deleted.
+type DeleteCallback = Arc<dyn Fn(&Path) + Send + Sync>;
+
+/// The action taken when a recording directory exceeds its disk quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RetentionMode {
+    /// Delete the oldest recordings until usage is within the quota, and delete recordings that
+    /// are older than the maximum age.
+    #[default]
+    DeleteOldest,
+    /// Never delete recordings. Instead, stop recording once usage exceeds the quota.
+    StopRecording,
+}
+
+/// A disk quota and retention policy for a directory of MCAP recordings.
+///
+/// The policy applies to files with an `.mcap` extension directly within the directory. Other
+/// files and subdirectories are ignored.
+///
+/// A policy can be attached to a rotating writer with [`RotationPolicy::retention`][retention],
+/// in which case it is enforced when the writer is created and after each segment is finalized.
+/// The policy is not enforced on a timer. It can also be enforced manually
+/// with [`RetentionPolicy::enforce`], though this should not be done on a directory that a
+/// rotating writer is actively writing to.
+///
+/// [retention]: crate::RotationPolicy::retention
+#[must_use]
+#[derive(Clone, Default)]
+pub struct RetentionPolicy {
+    max_total_bytes: Option<u64>,
+    max_age: Option<Duration>,
+    mode: RetentionMode,
+    on_delete: Option<DeleteCallback>,
+}
+
+impl Debug for RetentionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetentionPolicy")
+            .field("max_total_bytes", &self.max_total_bytes)
+            .field("max_age", &self.max_age)
+            .field("mode", &self.mode)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The outcome of enforcing a [`RetentionPolicy`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RetentionReport {
+    /// Recordings that were deleted, oldest first.
+    pub deleted: Vec<PathBuf>,
+    /// The total size of the remaining recordings, in bytes.
+    pub total_bytes: u64,
+    /// True if the total size still exceeds the quota.
+    pub over_quota: bool,
+}
+
+impl RetentionPolicy {
+    /// Creates a new retention policy, which has no limits until they are configured.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum total size of recordings in the directory, in bytes.
+    pub fn max_total_bytes(mut self, bytes: u64) -> Self {
+        self.max_total_bytes = Some(bytes);
+        self
+    }
+
+    /// Sets the maximum age of recordings, based on their modification time.
+    ///
+    /// The age is checked only when the policy is enforced. This has no effect in
+    /// [`RetentionMode::StopRecording`] mode.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// Sets the action taken when the directory exceeds its quota.
+    ///
+    /// The default is [`RetentionMode::DeleteOldest`].
+    pub fn mode(mut self, mode: RetentionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets a callback that is invoked after each recording is deleted.
+    pub fn on_delete(mut self, callback: impl Fn(&Path) + Send + Sync + 'static) -> Self {
+        self.on_delete = Some(Arc::new(callback));
+        self
+    }
+
+    /// Returns the retention mode.
+    pub(crate) fn retention_mode(&self) -> RetentionMode {
+        self.mode
+    }
+
+    /// Returns true if `bytes` exceeds the quota.
+    pub(crate) fn exceeds_quota(&self, bytes: u64) -> bool {
+        self.max_total_bytes.is_some_and(|max| bytes > max)
+    }
+
+    /// Enforces the policy on the recordings in a directory.
+    ///
+    /// Recordings are deleted only in [`RetentionMode::DeleteOldest`] mode.
+    pub fn enforce(&self, dir: impl AsRef<Path>) -> Result<RetentionReport, FoxgloveError> {
+        let report = self.enforce_deferred(dir.as_ref())?;
+        self.notify_deleted(&report.deleted);
+        Ok(report)
+    }
+
+    /// Enforces the policy without invoking the [`on_delete`][Self::on_delete] callback.
+    ///
+    /// The caller is responsible for passing the deleted paths to
+    /// [`notify_deleted`][Self::notify_deleted].
+    pub(crate) fn enforce_deferred(&self, dir: &Path) -> Result<RetentionReport, FoxgloveError> {
+        let mut recordings = list_recordings(dir)?;
+        let mut report = RetentionReport {
+            total_bytes: recordings.iter().map(|r| r.size).sum(),
+            ..RetentionReport::default()
+        };
+
+        if self.mode == RetentionMode::DeleteOldest {
+            let now = SystemTime::now();
+            let expired = |r: &Recording| {
+                self.max_age
+                    .is_some_and(|max| now.duration_since(r.modified).is_ok_and(|age| age > max))
+            };
+            while let Some(oldest) = recordings.first() {
+                if !expired(oldest) && !self.exceeds_quota(report.total_bytes) {
+                    break;
+                }
+                let oldest = recordings.remove(0);
+                match std::fs::remove_file(&oldest.path) {
+                    Ok(()) => {
+                        report.total_bytes -= oldest.size;
+                        report.deleted.push(oldest.path);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to delete {}: {e}", oldest.path.display());
+                    }
+                }
+            }
+        }
+
+        report.over_quota = self.exceeds_quota(report.total_bytes);
+        Ok(report)
+    }
+
+    /// Invokes the [`on_delete`][Self::on_delete] callback for each deleted recording.
+    pub(crate) fn notify_deleted(&self, deleted: &[PathBuf]) {
+        if let Some(callback) = &self.on_delete {
+            for path in deleted {
+                callback(path);
+            }
+        }
+    }
+}
+
+struct Recording {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Lists the recordings in a directory, oldest first.
+fn list_recordings(dir: &Path) -> Result<Vec<Recording>, FoxgloveError> {
+    let mut recordings = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "mcap") {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        recordings.push(Recording {
+            path,
+            size: metadata.len(),
+            modified: metadata.modified()?,
+        });
+    }
+    recordings.sort_by(|a, b| a.modified.cmp(&b.modified).then(a.path.cmp(&b.path)));
+    Ok(recordings)
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    fn write_file(dir: &Path, name: &str, len: usize, age: Duration) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, vec![0; len]).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_delete_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = write_file(dir.path(), "a.mcap", 10, Duration::from_secs(300));
+        let mid = write_file(dir.path(), "b.mcap", 10, Duration::from_secs(200));
+        let new = write_file(dir.path(), "c.mcap", 10, Duration::from_secs(100));
+        write_file(dir.path(), "notes.txt", 100, Duration::from_secs(400));
+
+        let deleted = Arc::new(Mutex::new(Vec::new()));
+        let policy = RetentionPolicy::new().max_total_bytes(25).on_delete({
+            let deleted = deleted.clone();
+            move |path| deleted.lock().push(path.to_path_buf())
+        });
+        let report = policy.enforce(dir.path()).unwrap();
+        assert_eq!(report.deleted, vec![old.clone()]);
+        assert_eq!(report.total_bytes, 20);
+        assert!(!report.over_quota);
+        assert_eq!(*deleted.lock(), vec![old]);
+
+        // Files past the maximum age are deleted, even when under quota.
+        let policy = RetentionPolicy::new().max_age(Duration::from_secs(150));
+        let report = policy.enforce(dir.path()).unwrap();
+        assert_eq!(report.deleted, vec![mid]);
+        assert!(new.exists());
+        assert!(dir.path().join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_stop_recording_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(dir.path(), "a.mcap", 10, Duration::from_secs(300));
+        let policy = RetentionPolicy::new()
+            .max_total_bytes(5)
+            .max_age(Duration::from_secs(1))
+            .mode(RetentionMode::StopRecording);
+        let report = policy.enforce(dir.path()).unwrap();
+        assert!(report.deleted.is_empty());
+        assert!(report.over_quota);
+        assert!(path.exists());
+    }
+}
@@ -0,0 +1,320 @@
+//! In-memory flight recorder.
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+
+use super::McapSink;
+use crate::library_version::get_library_version;
+use crate::{
+    ChannelDescriptor, FoxgloveError, McapWriteOptions, Metadata, RawChannel, Sink, SinkId,
+};
+
+/// The default maximum size of the messages retained by a [`RingBufferSink`].
+const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Options for a [`RingBufferSink`].
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct RingBufferOptions {
+    max_duration: Option<Duration>,
+    max_bytes: Option<usize>,
+    write_options: McapWriteOptions,
+}
+
+impl Default for RingBufferOptions {
+    fn default() -> Self {
+        Self {
+            max_duration: None,
+            max_bytes: Some(DEFAULT_MAX_BYTES),
+            write_options: McapWriteOptions::default(),
+        }
+    }
+}
+
+impl RingBufferOptions {
+    /// Creates a new set of ring buffer options with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum span of log time to retain.
+    ///
+    /// Messages are evicted once their log time is more than `duration` older than that of the
+    /// most recently logged message. By default, there is no limit on the duration.
+    pub fn max_duration(mut self, duration: impl Into<Option<Duration>>) -> Self {
+        self.max_duration = duration.into();
+        self
+    }
+
+    /// Sets the maximum total size of retained message payloads, in bytes.
+    ///
+    /// The oldest messages are evicted once the limit is exceeded. The default is 64 MiB. If set to
+    /// `None`, there is no limit on the size, and a [duration][Self::max_duration] should be
+    /// configured instead.
+    pub fn max_bytes(mut self, bytes: impl Into<Option<usize>>) -> Self {
+        self.max_bytes = bytes.into();
+        self
+    }
+
+    /// Sets the options used when writing an MCAP file with [`RingBufferSink::dump_to`].
+    ///
+    /// The library option is ignored.
+    pub fn write_options(mut self, options: McapWriteOptions) -> Self {
+        self.write_options = options;
+        self
+    }
+}
+
+/// A retained message.
+#[derive(Clone)]
+struct Entry {
+    channel: ChannelDescriptor,
+    msg: Bytes,
+    metadata: Metadata,
+}
+
+#[derive(Default)]
+struct Buffer {
+    entries: VecDeque<Entry>,
+    bytes: usize,
+}
+
+impl Buffer {
+    fn push(&mut self, entry: Entry, options: &RingBufferOptions) {
+        self.bytes += entry.msg.len();
+        let newest = entry.metadata.log_time;
+        self.entries.push_back(entry);
+        while let Some(oldest) = self.entries.front() {
+            let over_bytes = options.max_bytes.is_some_and(|max| self.bytes > max);
+            let over_duration = options.max_duration.is_some_and(|max| {
+                newest.saturating_sub(oldest.metadata.log_time) > max.as_nanos() as u64
+            });
+            if !over_bytes && !over_duration {
+                break;
+            }
+            self.bytes -= oldest.msg.len();
+            self.entries.pop_front();
+        }
+    }
+}
+
+/// A sink that retains the most recent messages in memory, and writes them to an MCAP file on
+/// demand.
+///
+/// This is useful as a "flight recorder", for capturing the events leading up to a failure
+/// without recording everything to disk. The sink subscribes to all channels in its context, and
+/// retains messages up to the limits configured with [`RingBufferOptions`], along with the
+/// channel and schema information needed to decode them. Call [`RingBufferSink::dump_to`] to
+/// write the retained window as a complete MCAP file.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+/// use foxglove::{Context, RingBufferOptions, RingBufferSink};
+///
+/// let ctx = Context::new();
+/// let recorder = RingBufferSink::new(
+///     RingBufferOptions::new().max_duration(Duration::from_secs(120)),
+/// );
+/// ctx.add_sink(recorder.clone());
+///
+/// // ... later, when something goes wrong:
+/// recorder.dump_to_file("incident.mcap").expect("dump failed");
+/// ```
+pub struct RingBufferSink {
+    id: SinkId,
+    options: RingBufferOptions,
+    buffer: Mutex<Buffer>,
+}
+
+impl Debug for RingBufferSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingBufferSink")
+            .field("id", &self.id)
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RingBufferSink {
+    /// Creates a new ring buffer sink.
+    ///
+    /// The sink must be registered with a context using [`Context::add_sink`][add_sink].
+    ///
+    /// [add_sink]: crate::Context::add_sink
+    pub fn new(options: RingBufferOptions) -> Arc<Self> {
+        Arc::new(Self {
+            id: SinkId::next(),
+            options,
+            buffer: Mutex::default(),
+        })
+    }
+
+    /// Returns the number of retained messages.
+    pub fn len(&self) -> usize {
+        self.buffer.lock().entries.len()
+    }
+
+    /// Returns true if no messages are retained.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.lock().entries.is_empty()
+    }
+
+    /// Returns the total size of retained message payloads, in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.buffer.lock().bytes
+    }
+
+    /// Discards all retained messages.
+    pub fn clear(&self) {
+        *self.buffer.lock() = Buffer::default();
+    }
+
+    /// Writes the retained messages to an MCAP file, and returns the writer.
+    ///
+    /// The retained messages are not discarded, and logging continues while the file is written.
+    pub fn dump_to<W: Write + Seek>(&self, writer: W) -> Result<W, FoxgloveError> {
+        let entries: Vec<_> = self.buffer.lock().entries.iter().cloned().collect();
+        let options = self
+            .options
+            .write_options
+            .clone()
+            .library(get_library_version());
+        let sink = McapSink::new(writer, options, None, None)?;
+        for entry in &entries {
+            sink.write_message(&entry.channel, &entry.msg, &entry.metadata)?;
+        }
+        let (writer, _) = sink.finish()?.expect("not finished");
+        Ok(writer)
+    }
+
+    /// Creates a new file, and writes the retained messages to it as MCAP.
+    ///
+    /// If the file already exists, this call will fail with
+    /// [`AlreadyExists`](`std::io::ErrorKind::AlreadyExists`).
+    pub fn dump_to_file(&self, path: impl AsRef<Path>) -> Result<(), FoxgloveError> {
+        let file = File::create_new(path)?;
+        let mut writer = self.dump_to(BufWriter::new(file))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Returns a WebSocket service that responds with the retained messages, encoded as MCAP.
+    ///
+    /// The request payload is ignored. The service is invoked on a blocking thread, so that
+    /// encoding a large buffer does not stall the server.
+    #[cfg(feature = "live_visualization")]
+    pub fn dump_service(
+        self: &Arc<Self>,
+        name: impl Into<String>,
+    ) -> crate::websocket::service::Service {
+        use crate::websocket::service::{Service, ServiceSchema};
+
+        let name = name.into();
+        let sink = self.clone();
+        Service::builder(&name, ServiceSchema::new(&name)).blocking_handler_fn(move |_| {
+            sink.dump_to(std::io::Cursor::new(Vec::new()))
+                .map(|cursor| cursor.into_inner())
+        })
+    }
+}
+
+impl Sink for RingBufferSink {
+    fn id(&self) -> SinkId {
+        self.id
+    }
+
+    fn log(
+        &self,
+        channel: &RawChannel,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        let entry = Entry {
+            channel: channel.descriptor().clone(),
+            msg: Bytes::copy_from_slice(msg),
+            metadata: *metadata,
+        };
+        self.buffer.lock().push(entry, &self.options);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{ChannelBuilder, Context, PartialMetadata};
+
+    fn new_channel(ctx: &Arc<Context>, topic: &str) -> Arc<RawChannel> {
+        ChannelBuilder::new(topic)
+            .context(ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_evict_by_bytes() {
+        let ctx = Context::new();
+        let sink = RingBufferSink::new(RingBufferOptions::new().max_bytes(10));
+        ctx.add_sink(sink.clone());
+        let ch = new_channel(&ctx, "/t");
+        for i in 0..5 {
+            ch.log_with_meta(b"1234", PartialMetadata::with_log_time(i));
+        }
+        assert_eq!(sink.len(), 2);
+        assert_eq!(sink.size_bytes(), 8);
+        sink.clear();
+        assert!(sink.is_empty());
+    }
+
+    #[test]
+    fn test_dump_to() {
+        let ctx = Context::new();
+        let sink = RingBufferSink::new(
+            RingBufferOptions::new()
+                .max_bytes(None)
+                .max_duration(Duration::from_nanos(10)),
+        );
+        ctx.add_sink(sink.clone());
+        let a = new_channel(&ctx, "/a");
+        let b = new_channel(&ctx, "/b");
+        a.log_with_meta(b"evicted", PartialMetadata::with_log_time(1));
+        b.log_with_meta(b"b1", PartialMetadata::with_log_time(15));
+        a.log_with_meta(b"a1", PartialMetadata::with_log_time(20));
+        b.log_with_meta(b"b2", PartialMetadata::with_log_time(25));
+        assert_eq!(sink.len(), 3);
+
+        let data = sink.dump_to(Cursor::new(Vec::new())).unwrap().into_inner();
+        let summary = mcap::Summary::read(&data).unwrap().unwrap();
+        assert_eq!(summary.channels.len(), 2);
+        let messages: Vec<_> = mcap::MessageStream::new(&data)
+            .unwrap()
+            .map(|m| {
+                let m = m.unwrap();
+                (m.channel.topic.clone(), m.data.into_owned(), m.log_time)
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("/b".to_string(), b"b1".to_vec(), 15),
+                ("/a".to_string(), b"a1".to_vec(), 20),
+                ("/b".to_string(), b"b2".to_vec(), 25),
+            ]
+        );
+
+        // Dumping does not discard the retained messages.
+        assert_eq!(sink.len(), 3);
+    }
+}
@@ -0,0 +1,893 @@
+//! MCAP file rotation.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use super::{create_file_sink, McapSink, McapSyncPolicy, RetentionMode, RetentionPolicy};
+use crate::{
+    nanoseconds_since_epoch, ChannelDescriptor, ChannelId, Context, FoxgloveError,
+    McapWriteOptions, Metadata, RawChannel, Sink, SinkChannelFilter, SinkId, SinkMessageFilter,
+};
+
+/// The default file name template for rotated segments.
+const DEFAULT_FILE_NAME_TEMPLATE: &str = "{timestamp}_{index}.mcap";
+
+/// A callback invoked when a segment is finalized.
+type SegmentCallback = Arc<dyn Fn(&McapSegment) + Send + Sync>;
+
+/// Information about a finalized segment of a rotating MCAP recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct McapSegment {
+    /// The path to the segment file.
+    pub path: PathBuf,
+    /// The zero-based index of the segment within the recording.
+    pub index: u64,
+    /// The number of messages written to the segment.
+    pub messages: u64,
+    /// The total size of the message payloads written to the segment, in bytes.
+    pub message_bytes: u64,
+}
+
+/// The policy that determines when a rotating MCAP writer rolls over to a new file.
+///
+/// See [`McapWriter::create_rotating`][crate::McapWriter::create_rotating].
+#[must_use]
+#[derive(Clone)]
+pub struct RotationPolicy {
+    max_bytes: Option<u64>,
+    max_messages: Option<u64>,
+    max_duration: Option<Duration>,
+    file_name_template: String,
+    on_segment_finalized: Option<SegmentCallback>,
+    retention: Option<RetentionPolicy>,
+}
+
+impl Debug for RotationPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RotationPolicy")
+            .field("max_bytes", &self.max_bytes)
+            .field("max_messages", &self.max_messages)
+            .field("max_duration", &self.max_duration)
+            .field("file_name_template", &self.file_name_template)
+            .field("retention", &self.retention)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: None,
+            max_messages: None,
+            max_duration: None,
+            file_name_template: DEFAULT_FILE_NAME_TEMPLATE.to_string(),
+            on_segment_finalized: None,
+            retention: None,
+        }
+    }
+}
+
+impl RotationPolicy {
+    /// Creates a new rotation policy, which never rolls over until limits are configured.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rolls over once the total size of message payloads in a segment would exceed `bytes`.
+    ///
+    /// The limit applies to uncompressed message data. The size of the file on disk also
+    /// includes MCAP framing, schemas, channels, and indexes, and is reduced by compression.
+    pub fn max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Rolls over once a segment contains `messages` messages.
+    pub fn max_messages(mut self, messages: u64) -> Self {
+        self.max_messages = Some(messages);
+        self
+    }
+
+    /// Rolls over once a segment has been open for `duration` of wall time.
+    ///
+    /// The limit is checked when a message is logged, so an idle recording does not roll over.
+    pub fn max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+
+    /// Sets the template for segment file names.
+    ///
+    /// The template may contain the following placeholders:
+    /// - `{timestamp}`: the UTC time at which the segment was opened, formatted as
+    ///   `YYYYMMDDTHHMMSSZ`.
+    /// - `{index}`: the zero-based index of the segment, padded to four digits.
+    ///
+    /// The default is `{timestamp}_{index}.mcap`.
+    pub fn file_name_template(mut self, template: impl Into<String>) -> Self {
+        self.file_name_template = template.into();
+        self
+    }
+
+    /// Sets a callback that is invoked after each segment is finalized and flushed.
+    ///
+    /// The callback is invoked synchronously, on the thread that caused the rollover, which may
+    /// be a logging thread. It should not block for long periods of time. The writer's lock is
+    /// released before the callback is invoked, so the callback may use the writer's handle.
+    pub fn on_segment_finalized(
+        mut self,
+        callback: impl Fn(&McapSegment) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_segment_finalized = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets a retention policy for the recording directory.
+    ///
+    /// The policy is enforced when the writer is created, and after each segment is finalized,
+    /// including when the writer is closed. An idle writer does not enforce the policy, so
+    /// recordings that pass their [maximum age][RetentionPolicy::max_age] are not deleted until
+    /// the current segment is finalized.
+    ///
+    /// In [`RetentionMode::StopRecording`] mode, the writer stops recording when the finalized
+    /// segments exceed the quota, or when the current segment would take the directory over the
+    /// quota. Messages logged after recording stops are discarded.
+    pub fn retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = Some(policy);
+        self
+    }
+
+    /// Formats the file name for a segment.
+    fn file_name(&self, index: u64, time: u64) -> String {
+        self.file_name_template
+            .replace("{timestamp}", &format_utc_timestamp(time))
+            .replace("{index}", &format!("{index:04}"))
+    }
+}
+
+/// Formats a time in nanoseconds since the epoch as `YYYYMMDDTHHMMSSZ`.
+pub(super) fn format_utc_timestamp(nanos: u64) -> String {
+    let secs = nanos / 1_000_000_000;
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (hour, min, sec) = (rem / 3600, rem % 3600 / 60, rem % 60);
+
+    // Convert days since the epoch to a civil date.
+    // See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!("{year:04}{month:02}{day:02}T{hour:02}{min:02}{sec:02}Z")
+}
+
+type SegmentWriter = BufWriter<File>;
+
+/// The segment currently being written.
+struct Segment {
+    sink: Arc<McapSink<SegmentWriter>>,
+    path: PathBuf,
+    index: u64,
+    opened: Instant,
+    messages: u64,
+    message_bytes: u64,
+}
+
+struct State {
+    current: Option<Segment>,
+    next_index: u64,
+    /// Channels that have been made available to the sink, which are written to each new segment.
+    channels: HashMap<ChannelId, ChannelDescriptor>,
+    /// Paths of all segments that have been opened, and not deleted by the retention policy.
+    paths: Vec<PathBuf>,
+    /// The size of the finalized recordings in the directory, as of the last time the retention
+    /// policy was enforced.
+    retained_bytes: u64,
+    /// True if recording was stopped by the retention policy.
+    stopped: bool,
+    /// True if the writer has been finished.
+    closed: bool,
+    /// Segments that have been finalized, whose callbacks have not yet been invoked.
+    finalized: Vec<McapSegment>,
+    /// Recordings deleted by the retention policy, whose callbacks have not yet been invoked.
+    deleted: Vec<PathBuf>,
+}
+
+/// A sink that writes to a sequence of MCAP files.
+pub(crate) struct RotatingMcapSink {
+    sink_id: SinkId,
+    dir: PathBuf,
+    options: McapWriteOptions,
+    sync_policy: McapSyncPolicy,
+    policy: RotationPolicy,
+    channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+    message_filter: Option<Arc<dyn SinkMessageFilter>>,
+    state: Mutex<State>,
+}
+
+impl RotatingMcapSink {
+    pub fn new(
+        dir: &Path,
+        options: McapWriteOptions,
+        sync_policy: McapSyncPolicy,
+        policy: RotationPolicy,
+        channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+        message_filter: Option<Arc<dyn SinkMessageFilter>>,
+    ) -> Result<Arc<Self>, FoxgloveError> {
+        std::fs::create_dir_all(dir)?;
+        let sink = Self {
+            sink_id: SinkId::next(),
+            dir: dir.to_path_buf(),
+            options,
+            sync_policy,
+            policy,
+            channel_filter,
+            message_filter,
+            state: Mutex::new(State {
+                current: None,
+                next_index: 0,
+                channels: HashMap::new(),
+                paths: Vec::new(),
+                retained_bytes: 0,
+                stopped: false,
+                closed: false,
+                finalized: Vec::new(),
+                deleted: Vec::new(),
+            }),
+        };
+        sink.update(|state| {
+            sink.enforce_retention(state)?;
+            sink.open_segment(state)
+        })?;
+        Ok(Arc::new(sink))
+    }
+
+    /// Opens the next segment, and writes the known channels to it.
+    ///
+    /// If the retention policy has stopped recording, no segment is opened.
+    fn open_segment(&self, state: &mut State) -> Result<(), FoxgloveError> {
+        if state.stopped {
+            return Ok(());
+        }
+        let index = state.next_index;
+        let path = self
+            .dir
+            .join(self.policy.file_name(index, nanoseconds_since_epoch()));
+        let sink = create_file_sink(&path, self.options.clone(), self.sync_policy, None, None)?;
+        for channel in state.channels.values() {
+            sink.add_channel(channel)?;
+        }
+        state.next_index += 1;
+        state.paths.push(path.clone());
+        state.current = Some(Segment {
+            sink,
+            path,
+            index,
+            opened: Instant::now(),
+            messages: 0,
+            message_bytes: 0,
+        });
+        Ok(())
+    }
+
+    /// Finalizes the current segment, if there is one, and then enforces the retention policy.
+    ///
+    /// The segment is queued for the [`on_segment_finalized`] callback, which is invoked by
+    /// [`update`][Self::update] once the lock has been released.
+    ///
+    /// [`on_segment_finalized`]: RotationPolicy::on_segment_finalized
+    fn finalize_segment(&self, state: &mut State) -> Result<(), FoxgloveError> {
+        let Some(segment) = state.current.take() else {
+            return Ok(());
+        };
+        if let Some((mut writer, _)) = segment.sink.finish()? {
+            writer.flush()?;
+        }
+        if self.policy.on_segment_finalized.is_some() {
+            state.finalized.push(McapSegment {
+                path: segment.path,
+                index: segment.index,
+                messages: segment.messages,
+                message_bytes: segment.message_bytes,
+            });
+        }
+        self.enforce_retention(state)
+    }
+
+    /// Applies a mutation to the state, and then invokes the [`on_segment_finalized`] and
+    /// [`on_delete`] callbacks for any finalized segments and deleted recordings once the lock
+    /// has been released.
+    ///
+    /// [`on_segment_finalized`]: RotationPolicy::on_segment_finalized
+    /// [`on_delete`]: RetentionPolicy::on_delete
+    fn update<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        let (result, finalized, deleted) = {
+            let mut state = self.state.lock();
+            let result = f(&mut state);
+            (
+                result,
+                std::mem::take(&mut state.finalized),
+                std::mem::take(&mut state.deleted),
+            )
+        };
+        if let Some(callback) = &self.policy.on_segment_finalized {
+            for segment in &finalized {
+                callback(segment);
+            }
+        }
+        if let Some(retention) = &self.policy.retention {
+            retention.notify_deleted(&deleted);
+        }
+        result
+    }
+
+    /// Enforces the retention policy, and stops recording if the quota has been reached.
+    ///
+    /// Deleted recordings are queued for the [`on_delete`] callback, which is invoked by
+    /// [`update`][Self::update] once the lock has been released.
+    ///
+    /// [`on_delete`]: RetentionPolicy::on_delete
+    fn enforce_retention(&self, state: &mut State) -> Result<(), FoxgloveError> {
+        let Some(retention) = &self.policy.retention else {
+            return Ok(());
+        };
+        let report = retention.enforce_deferred(&self.dir)?;
+        state.paths.retain(|p| !report.deleted.contains(p));
+        state.deleted.extend(report.deleted);
+        state.retained_bytes = report.total_bytes;
+        if report.over_quota && retention.retention_mode() == RetentionMode::StopRecording {
+            self.stop(state);
+        }
+        Ok(())
+    }
+
+    /// Stops recording, because the retention quota has been reached.
+    fn stop(&self, state: &mut State) {
+        if !state.stopped {
+            tracing::warn!(
+                "Disk quota for {} exceeded, stopping recording",
+                self.dir.display()
+            );
+            state.stopped = true;
+        }
+    }
+
+    /// Returns true if writing a message of the given size to the current segment would take the
+    /// directory over a quota that stops recording.
+    fn would_exceed_quota(&self, state: &State, segment: &Segment, len: usize) -> bool {
+        self.policy.retention.as_ref().is_some_and(|retention| {
+            retention.retention_mode() == RetentionMode::StopRecording
+                && retention
+                    .exceeds_quota(state.retained_bytes + segment.message_bytes + len as u64)
+        })
+    }
+
+    /// Returns true if writing a message of the given size would exceed the rotation policy.
+    fn should_rotate(&self, segment: &Segment, len: usize) -> bool {
+        if segment.messages == 0 {
+            return false;
+        }
+        let policy = &self.policy;
+        policy
+            .max_messages
+            .is_some_and(|max| segment.messages >= max)
+            || policy
+                .max_bytes
+                .is_some_and(|max| segment.message_bytes + len as u64 > max)
+            || policy
+                .max_duration
+                .is_some_and(|max| segment.opened.elapsed() >= max)
+    }
+
+    /// Finalizes the current segment and opens the next one.
+    ///
+    /// If a previous attempt to open a segment failed, this tries again.
+    pub fn rotate(&self) -> Result<(), FoxgloveError> {
+        self.update(|state| {
+            if state.closed {
+                return Err(FoxgloveError::SinkClosed);
+            }
+            if state.stopped {
+                return Ok(());
+            }
+            self.finalize_segment(state)?;
+            self.open_segment(state)
+        })
+    }
+
+    /// Finalizes the current segment, and returns the paths of all segments.
+    pub fn finish(&self) -> Result<Vec<PathBuf>, FoxgloveError> {
+        self.update(|state| {
+            state.closed = true;
+            self.finalize_segment(state)?;
+            Ok(state.paths.clone())
+        })
+    }
+
+    /// Writes a message to the current segment, rotating first if necessary.
+    ///
+    /// If there is no current segment because a previous attempt to open one failed, this tries
+    /// again, so that a transient failure does not end the recording.
+    fn write_message(
+        &self,
+        state: &mut State,
+        channel: &RawChannel,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        if state.stopped {
+            return Ok(());
+        }
+        if state.closed {
+            return Err(FoxgloveError::SinkClosed);
+        }
+        match &state.current {
+            None => self.open_segment(state)?,
+            Some(segment) if self.would_exceed_quota(state, segment, msg.len()) => {
+                self.stop(state);
+                self.finalize_segment(state)?;
+                return Ok(());
+            }
+            Some(segment) if self.should_rotate(segment, msg.len()) => {
+                self.finalize_segment(state)?;
+                self.open_segment(state)?;
+            }
+            Some(_) => (),
+        }
+        let Some(segment) = state.current.as_mut() else {
+            return Ok(());
+        };
+        segment
+            .sink
+            .write_message(channel.descriptor(), msg, metadata)?;
+        segment.messages += 1;
+        segment.message_bytes += msg.len() as u64;
+        Ok(())
+    }
+
+    /// Returns the path of the segment currently being written.
+    pub fn current_path(&self) -> Option<PathBuf> {
+        self.state.lock().current.as_ref().map(|s| s.path.clone())
+    }
+
+    /// Returns true if recording was stopped by the retention policy.
+    pub fn is_stopped(&self) -> bool {
+        self.state.lock().stopped
+    }
+}
+
+impl Sink for RotatingMcapSink {
+    fn id(&self) -> SinkId {
+        self.sink_id
+    }
+
+    fn log(
+        &self,
+        channel: &RawChannel,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        if let Some(filter) = &self.message_filter {
+            if !filter.should_log(channel, msg, metadata) {
+                return Ok(());
+            }
+        }
+        self.update(|state| self.write_message(state, channel, msg, metadata))
+    }
+
+    fn auto_subscribe(&self) -> bool {
+        self.channel_filter.is_none()
+    }
+
+    fn add_channels(&self, channels: &[&Arc<RawChannel>]) -> Option<Vec<ChannelId>> {
+        let channels: Vec<_> = channels
+            .iter()
+            .filter(|c| {
+                self.channel_filter
+                    .as_ref()
+                    .is_none_or(|f| f.should_subscribe(c.descriptor()))
+            })
+            .collect();
+        let mut state = self.state.lock();
+        for channel in &channels {
+            state
+                .channels
+                .insert(channel.id(), channel.descriptor().clone());
+            if let Some(segment) = &state.current {
+                if let Err(e) = segment.sink.add_channel(channel.descriptor()) {
+                    tracing::warn!("Failed to add channel to MCAP segment: {e}");
+                }
+            }
+        }
+        Some(channels.iter().map(|c| c.id()).collect())
+    }
+
+    fn remove_channel(&self, channel: &RawChannel) {
+        self.state.lock().channels.remove(&channel.id());
+    }
+}
+
+/// A handle to a rotating MCAP writer.
+///
+/// When this handle is dropped, the writer will unregister from the [`Context`], stop logging
+/// events, and finalize the current segment.
+#[must_use]
+pub struct RotatingMcapWriterHandle {
+    sink: Arc<RotatingMcapSink>,
+    context: Weak<Context>,
+}
+
+impl Debug for RotatingMcapWriterHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RotatingMcapWriterHandle")
+            .field("sink_id", &self.sink.sink_id)
+            .field("dir", &self.sink.dir)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RotatingMcapWriterHandle {
+    pub(crate) fn new(sink: Arc<RotatingMcapSink>, context: &Arc<Context>) -> Self {
+        Self {
+            sink,
+            context: Arc::downgrade(context),
+        }
+    }
+
+    /// Finalizes the current segment and begins writing to a new one, regardless of the
+    /// rotation policy.
+    pub fn rotate(&self) -> Result<(), FoxgloveError> {
+        self.sink.rotate()
+    }
+
+    /// Returns the path of the segment currently being written.
+    pub fn current_path(&self) -> Option<PathBuf> {
+        self.sink.current_path()
+    }
+
+    /// Returns true if recording was stopped because the directory reached the disk quota of its
+    /// [retention policy][RotationPolicy::retention].
+    pub fn is_stopped(&self) -> bool {
+        self.sink.is_stopped()
+    }
+
+    /// Stops logging events, finalizes the current segment, and returns the paths of all
+    /// segments that were written and have not been deleted by the retention policy.
+    pub fn close(self) -> Result<Vec<PathBuf>, FoxgloveError> {
+        self.finish()
+    }
+
+    fn finish(&self) -> Result<Vec<PathBuf>, FoxgloveError> {
+        if let Some(context) = self.context.upgrade() {
+            context.flush();
+            context.remove_sink(self.sink.sink_id);
+        }
+        self.sink.finish()
+    }
+}
+
+impl Drop for RotatingMcapWriterHandle {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            tracing::warn!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::read_summary;
+    use crate::{ChannelBuilder, McapWriter};
+
+    #[test]
+    fn test_format_utc_timestamp() {
+        assert_eq!(format_utc_timestamp(0), "19700101T000000Z");
+        assert_eq!(
+            format_utc_timestamp(1_709_251_199_000_000_000),
+            "20240229T235959Z"
+        );
+    }
+
+    #[test]
+    fn test_rotate_by_message_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let quiet = ChannelBuilder::new("/quiet")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        let finalized = Arc::new(Mutex::new(Vec::new()));
+        let policy = RotationPolicy::new()
+            .max_messages(2)
+            .file_name_template("rec_{index}.mcap")
+            .on_segment_finalized({
+                let finalized = finalized.clone();
+                move |segment| finalized.lock().push(segment.clone())
+            });
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_rotating(dir.path(), policy)
+            .unwrap();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        for i in 0..5u8 {
+            ch.log(&[i]);
+        }
+        handle.rotate().unwrap();
+        let paths = handle.close().unwrap();
+
+        let names: Vec<_> = paths
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "rec_0000.mcap",
+                "rec_0001.mcap",
+                "rec_0002.mcap",
+                "rec_0003.mcap"
+            ]
+        );
+        let counts: Vec<_> = finalized.lock().iter().map(|s| s.messages).collect();
+        assert_eq!(counts, vec![2, 2, 1, 0]);
+
+        // Each segment contains all known channels.
+        for path in &paths {
+            let summary = read_summary(path);
+            let mut topics: Vec<_> = summary.channels.values().map(|c| c.topic.clone()).collect();
+            topics.sort();
+            assert_eq!(topics, vec!["/quiet", "/t"]);
+        }
+        drop(quiet);
+    }
+
+    #[test]
+    fn test_rotate_by_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_rotating(dir.path(), RotationPolicy::new().max_bytes(10))
+            .unwrap();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        ch.log(&[0; 6]);
+        ch.log(&[0; 4]);
+        ch.log(&[0; 1]);
+        // Messages larger than the limit are written to their own segment.
+        ch.log(&[0; 20]);
+        let paths = handle.close().unwrap();
+        let counts: Vec<_> = paths
+            .iter()
+            .map(|p| read_summary(p).stats.unwrap().message_count)
+            .collect();
+        assert_eq!(counts, vec![2, 1, 1]);
+    }
+
+    #[test]
+    fn test_retention_deletes_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let deleted = Arc::new(Mutex::new(Vec::new()));
+        let retention = RetentionPolicy::new().max_total_bytes(1).on_delete({
+            let deleted = deleted.clone();
+            move |path| deleted.lock().push(path.to_path_buf())
+        });
+        let policy = RotationPolicy::new()
+            .max_messages(1)
+            .file_name_template("rec_{index}.mcap")
+            .retention(retention);
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_rotating(dir.path(), policy)
+            .unwrap();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        for i in 0..3u8 {
+            ch.log(&[i]);
+        }
+        assert_eq!(
+            *deleted.lock(),
+            vec![
+                dir.path().join("rec_0000.mcap"),
+                dir.path().join("rec_0001.mcap")
+            ]
+        );
+
+        // The policy is also enforced when the last segment is finalized.
+        let paths = handle.close().unwrap();
+        assert!(paths.is_empty());
+        assert_eq!(deleted.lock().len(), 3);
+    }
+
+    #[test]
+    fn test_retention_callback_can_access_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let slot: Arc<Mutex<Option<RotatingMcapWriterHandle>>> = Arc::default();
+        let deleted = Arc::new(Mutex::new(Vec::new()));
+        let retention = RetentionPolicy::new().max_total_bytes(1).on_delete({
+            let slot = slot.clone();
+            let deleted = deleted.clone();
+            move |path| {
+                // The callback is invoked without holding the writer's lock.
+                let current = slot.lock().as_ref().and_then(|h| h.current_path());
+                deleted.lock().push((path.to_path_buf(), current));
+            }
+        });
+        let policy = RotationPolicy::new()
+            .max_messages(1)
+            .file_name_template("rec_{index}.mcap")
+            .retention(retention);
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_rotating(dir.path(), policy)
+            .unwrap();
+        *slot.lock() = Some(handle);
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        ch.log(&[0]);
+        ch.log(&[1]);
+        assert_eq!(
+            *deleted.lock(),
+            vec![(
+                dir.path().join("rec_0000.mcap"),
+                Some(dir.path().join("rec_0001.mcap"))
+            )]
+        );
+
+        let handle = slot.lock().take().unwrap();
+        handle.close().unwrap();
+    }
+
+    #[test]
+    fn test_retention_max_age_enforced_at_finalize() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let policy = RotationPolicy::new()
+            .file_name_template("rec_{index}.mcap")
+            .retention(RetentionPolicy::new().max_age(Duration::from_secs(60)));
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_rotating(dir.path(), policy)
+            .unwrap();
+
+        // A recording expires while the writer is idle.
+        let old = dir.path().join("old.mcap");
+        std::fs::write(&old, b"").unwrap();
+        let file = File::options().write(true).open(&old).unwrap();
+        file.set_modified(std::time::SystemTime::now() - Duration::from_secs(120))
+            .unwrap();
+        drop(file);
+
+        let paths = handle.close().unwrap();
+        assert!(!old.exists());
+        assert_eq!(paths, vec![dir.path().join("rec_0000.mcap")]);
+    }
+
+    #[test]
+    fn test_retention_stops_recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let retention = RetentionPolicy::new()
+            .max_total_bytes(10)
+            .mode(RetentionMode::StopRecording);
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_rotating(dir.path(), RotationPolicy::new().retention(retention))
+            .unwrap();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        ch.log(&[0; 6]);
+        assert!(!handle.is_stopped());
+        ch.log(&[0; 6]);
+        assert!(handle.is_stopped());
+        assert!(handle.current_path().is_none());
+        ch.log(&[0; 1]);
+        handle.rotate().unwrap();
+
+        let paths = handle.close().unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(read_summary(&paths[0]).stats.unwrap().message_count, 1);
+    }
+
+    #[test]
+    fn test_retry_failed_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let policy = RotationPolicy::new()
+            .max_messages(1)
+            .file_name_template("rec_{index}.mcap");
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_rotating(dir.path(), policy)
+            .unwrap();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        ch.log(&[0]);
+
+        // Block the next segment's path, so that opening it fails.
+        let blocked = dir.path().join("rec_0001.mcap");
+        std::fs::create_dir(&blocked).unwrap();
+        ch.log(&[1]);
+        assert!(handle.current_path().is_none());
+
+        // The next message retries the open.
+        std::fs::remove_dir(&blocked).unwrap();
+        ch.log(&[2]);
+        assert_eq!(handle.current_path(), Some(blocked));
+
+        let paths = handle.close().unwrap();
+        let counts: Vec<_> = paths
+            .iter()
+            .map(|p| read_summary(p).stats.unwrap().message_count)
+            .collect();
+        assert_eq!(counts, vec![1, 1]);
+    }
+
+    #[test]
+    fn test_segment_callback_can_use_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::new();
+        let sink: Arc<Mutex<Option<Arc<RotatingMcapSink>>>> = Arc::default();
+        let current = Arc::new(Mutex::new(Vec::new()));
+        let policy = RotationPolicy::new()
+            .file_name_template("rec_{index}.mcap")
+            .on_segment_finalized({
+                let sink = sink.clone();
+                let current = current.clone();
+                move |_| {
+                    if let Some(sink) = sink.lock().clone() {
+                        current.lock().push(sink.current_path());
+                    }
+                }
+            });
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_rotating(dir.path(), policy)
+            .unwrap();
+        *sink.lock() = Some(handle.sink.clone());
+
+        // The callback is invoked without holding the writer's lock.
+        handle.rotate().unwrap();
+        assert_eq!(
+            *current.lock(),
+            vec![Some(dir.path().join("rec_0001.mcap"))]
+        );
+        sink.lock().take();
+        handle.close().unwrap();
+    }
+}
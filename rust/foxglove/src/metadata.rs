@@ -39,8 +39,15 @@ impl ToUnixNanos for chrono::DateTime<chrono::Utc> {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct PartialMetadata {
     /// The log time is the time, as nanoseconds from the unix epoch, that the message was recorded.
-    /// Usually this is the time log() is called. If omitted, the current time is used.
+    /// Usually this is the time log() is called. If omitted, the current time is read from the
+    /// context's [`Clock`][crate::Clock].
     pub log_time: Option<u64>,
+    /// The publish time is the time, as nanoseconds from the unix epoch, that the message was
+    /// originally published, such as a sensor timestamp. If omitted, the log time is used.
+    pub publish_time: Option<u64>,
+    /// The sequence number of the message within its channel. If omitted, sinks that record
+    /// sequence numbers will assign their own.
+    pub sequence: Option<u32>,
 }
 
 impl PartialMetadata {
@@ -51,8 +58,25 @@ impl PartialMetadata {
     pub fn with_log_time(log_time: impl ToUnixNanos) -> Self {
         Self {
             log_time: Some(log_time.to_unix_nanos()),
+            ..Self::default()
         }
     }
+
+    /// Sets the publish time.
+    ///
+    /// `publish_time` accepts the same types as [`with_log_time`][Self::with_log_time].
+    #[must_use]
+    pub fn publish_time(mut self, publish_time: impl ToUnixNanos) -> Self {
+        self.publish_time = Some(publish_time.to_unix_nanos());
+        self
+    }
+
+    /// Sets the sequence number.
+    #[must_use]
+    pub fn sequence(mut self, sequence: u32) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
 }
 
 /// Metadata is the metadata associated with a log message.
@@ -61,4 +85,9 @@ pub struct Metadata {
     /// The log time is the time, as nanoseconds from the unix epoch, that the message was recorded.
     /// Usually this is the time log() is called. If omitted, the current time is used.
     pub log_time: u64,
+    /// The publish time is the time, as nanoseconds from the unix epoch, that the message was
+    /// originally published. If not provided by the caller, this is equal to the log time.
+    pub publish_time: u64,
+    /// The sequence number of the message within its channel, if provided by the caller.
+    pub sequence: Option<u32>,
 }
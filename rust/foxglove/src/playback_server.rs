@@ -0,0 +1,545 @@
+//! A WebSocket server for seekable playback of MCAP files.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use mcap::records::MessageHeader;
+use mcap::sans_io::indexed_reader::{IndexedReadEvent, IndexedReader, IndexedReaderOptions};
+use mcap::sans_io::summary_reader::{SummaryReadEvent, SummaryReader};
+use parking_lot::{Condvar, Mutex};
+
+use crate::websocket::{
+    Capability, PlaybackCommand, PlaybackControlRequest, PlaybackState, PlaybackStatus, Server,
+    ServerListener, ShutdownHandle,
+};
+use crate::{
+    ChannelBuilder, Context, FoxgloveError, ManualClock, PartialMetadata, RawChannel, Schema,
+    WebSocketServer, WebSocketServerHandle,
+};
+
+/// The minimum interval between time broadcasts during playback.
+const TIME_BROADCAST_INTERVAL: Duration = Duration::from_millis(1000 / 60);
+
+/// A WebSocket server that plays back an indexed MCAP file, under the control of the Foxglove
+/// app.
+///
+/// The server advertises the [`RangedPlayback`][crate::websocket::Capability::RangedPlayback]
+/// capability, with a time range read from the file's summary. Clients can play, pause, change
+/// the playback speed and seek. Seeks use the file's chunk indexes, so that playback resumes
+/// without reading the data that precedes the seek time. On each seek, the server clears the
+/// session, so that clients discard previously received data.
+///
+/// Messages are logged to a dedicated [`Context`], in log-time order. The server broadcasts the
+/// playback time to clients, and sends a [`PlaybackState`] when playback reaches the end of the
+/// file.
+///
+/// The file must be chunked, and contain a summary section with chunk indexes. This is the
+/// default for files written by [`McapWriter`][crate::McapWriter].
+///
+/// # Example
+/// ```no_run
+/// use foxglove::{PlaybackServer, WebSocketServer};
+///
+/// let handle = PlaybackServer::open("recording.mcap")
+///     .expect("failed to open recording")
+///     .websocket_server(WebSocketServer::new().bind("127.0.0.1", 8765))
+///     .start_blocking()
+///     .expect("failed to start server");
+/// ```
+#[must_use]
+pub struct PlaybackServer {
+    path: PathBuf,
+    summary: mcap::Summary,
+    time_range: (u64, u64),
+    server: WebSocketServer,
+}
+
+impl Debug for PlaybackServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlaybackServer")
+            .field("path", &self.path)
+            .field("time_range", &self.time_range)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PlaybackServer {
+    /// Reads the summary of the MCAP file at `path`, and creates a playback server for it.
+    ///
+    /// Returns an error if the file does not have a summary with chunk indexes.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, FoxgloveError> {
+        let path = path.as_ref();
+        let summary = read_summary(&mut File::open(path)?)?.ok_or_else(|| {
+            FoxgloveError::ValueError(format!("{} has no summary section", path.display()))
+        })?;
+        let message_count = summary.stats.as_ref().map(|s| s.message_count);
+        if summary.chunk_indexes.is_empty() && message_count != Some(0) {
+            return Err(FoxgloveError::ValueError(format!(
+                "{} is not indexed",
+                path.display()
+            )));
+        }
+        let time_range = match &summary.stats {
+            Some(stats) => (stats.message_start_time, stats.message_end_time),
+            None => (
+                summary
+                    .chunk_indexes
+                    .iter()
+                    .map(|c| c.message_start_time)
+                    .min()
+                    .unwrap_or_default(),
+                summary
+                    .chunk_indexes
+                    .iter()
+                    .map(|c| c.message_end_time)
+                    .max()
+                    .unwrap_or_default(),
+            ),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            summary,
+            time_range,
+            server: WebSocketServer::new(),
+        })
+    }
+
+    /// Returns the log time range of the messages in the file.
+    pub fn time_range(&self) -> (u64, u64) {
+        self.time_range
+    }
+
+    /// Sets the WebSocket server configuration.
+    ///
+    /// The server's context, listener, and playback time range are replaced by the playback
+    /// server.
+    pub fn websocket_server(mut self, server: WebSocketServer) -> Self {
+        self.server = server;
+        self
+    }
+
+    /// Starts the server, and begins playback in the paused state.
+    pub async fn start(self) -> Result<PlaybackServerHandle, FoxgloveError> {
+        let (server, playback) = self.prepare()?;
+        let server = server.start().await?;
+        Ok(playback.spawn(server))
+    }
+
+    /// Starts the server, and begins playback in the paused state.
+    ///
+    /// This method will panic if invoked from an asynchronous execution context. Use
+    /// [`PlaybackServer::start`] instead.
+    pub fn start_blocking(self) -> Result<PlaybackServerHandle, FoxgloveError> {
+        let (server, playback) = self.prepare()?;
+        let server = server.start_blocking()?;
+        Ok(playback.spawn(server))
+    }
+
+    /// Creates the playback context and channels, and configures the WebSocket server.
+    fn prepare(self) -> Result<(WebSocketServer, Playback), FoxgloveError> {
+        let context = Context::new();
+        let clock = Arc::new(ManualClock::new(self.time_range.0));
+        context.set_clock(clock.clone());
+
+        let mut channels = HashMap::new();
+        for (&id, channel) in &self.summary.channels {
+            let schema = channel
+                .schema
+                .as_ref()
+                .map(|s| Schema::new(&s.name, &s.encoding, s.data.to_vec()));
+            let raw = ChannelBuilder::new(&channel.topic)
+                .context(&context)
+                .message_encoding(&channel.message_encoding)
+                .schema(schema)
+                .metadata(channel.metadata.clone())
+                .build_raw()?;
+            channels.insert(id, raw);
+        }
+
+        let control = Arc::new(Control {
+            state: Mutex::new(ControlState {
+                status: PlaybackStatus::Paused,
+                speed: 1.0,
+                current_time: self.time_range.0,
+                seek: None,
+                generation: 0,
+                stopped: false,
+            }),
+            changed: Condvar::new(),
+        });
+        let listener = Arc::new(PlaybackListener {
+            control: control.clone(),
+            time_range: self.time_range,
+        });
+        let server = self
+            .server
+            .context(&context)
+            .listener(listener)
+            .add_capability(Capability::Time)
+            .playback_time_range(self.time_range.0, self.time_range.1);
+        let playback = Playback {
+            file: File::open(&self.path)?,
+            summary: self.summary,
+            time_range: self.time_range,
+            control,
+            channels,
+            clock,
+        };
+        Ok((server, playback))
+    }
+}
+
+/// A handle to a running [`PlaybackServer`].
+///
+/// This handle can safely be dropped and the server will continue playback.
+#[derive(Debug)]
+pub struct PlaybackServerHandle {
+    server: WebSocketServerHandle,
+    control: Arc<Control>,
+    thread: JoinHandle<()>,
+}
+
+impl PlaybackServerHandle {
+    /// Returns the underlying WebSocket server handle.
+    pub fn server(&self) -> &WebSocketServerHandle {
+        &self.server
+    }
+
+    /// Returns the current playback state.
+    pub fn state(&self) -> PlaybackState {
+        self.control.state.lock().playback_state(false)
+    }
+
+    /// Stops playback and gracefully shuts down the server.
+    pub fn stop(self) -> ShutdownHandle {
+        self.control.state.lock().stopped = true;
+        self.control.changed.notify_all();
+        let _ = self.thread.join();
+        self.server.stop()
+    }
+}
+
+/// Playback state shared between the server listener and the playback thread.
+#[derive(Debug)]
+struct Control {
+    state: Mutex<ControlState>,
+    changed: Condvar,
+}
+
+#[derive(Debug)]
+struct ControlState {
+    status: PlaybackStatus,
+    speed: f32,
+    current_time: u64,
+    /// A pending seek, to be performed by the playback thread.
+    seek: Option<u64>,
+    /// Incremented on every change, so that the playback thread can re-anchor its timing.
+    generation: u64,
+    stopped: bool,
+}
+
+impl ControlState {
+    fn playback_state(&self, did_seek: bool) -> PlaybackState {
+        PlaybackState {
+            status: self.status,
+            current_time: self.current_time,
+            playback_speed: self.speed,
+            did_seek,
+            request_id: None,
+        }
+    }
+}
+
+struct PlaybackListener {
+    control: Arc<Control>,
+    time_range: (u64, u64),
+}
+
+impl ServerListener for PlaybackListener {
+    fn on_playback_control_request(
+        &self,
+        request: PlaybackControlRequest,
+    ) -> Option<PlaybackState> {
+        let mut state = self.control.state.lock();
+        if request.playback_speed.is_finite() && request.playback_speed > 0.0 {
+            state.speed = request.playback_speed;
+        }
+        let mut seek = request
+            .seek_time
+            .map(|t| t.clamp(self.time_range.0, self.time_range.1));
+        state.status = match request.playback_command {
+            PlaybackCommand::Play => {
+                // Playing from the end restarts from the beginning.
+                if seek.is_none() && state.status == PlaybackStatus::Ended {
+                    seek = Some(self.time_range.0);
+                }
+                PlaybackStatus::Playing
+            }
+            PlaybackCommand::Pause => PlaybackStatus::Paused,
+        };
+        if let Some(time) = seek {
+            state.seek = Some(time);
+            state.current_time = time;
+        }
+        state.generation += 1;
+        self.control.changed.notify_all();
+        Some(state.playback_state(seek.is_some()))
+    }
+}
+
+/// The playback thread's resources.
+struct Playback {
+    file: File,
+    summary: mcap::Summary,
+    time_range: (u64, u64),
+    control: Arc<Control>,
+    channels: HashMap<u16, Arc<RawChannel>>,
+    clock: Arc<ManualClock>,
+}
+
+impl Playback {
+    fn spawn(self, server: WebSocketServerHandle) -> PlaybackServerHandle {
+        let control = self.control.clone();
+        let weak_server = server.weak_server();
+        let thread = std::thread::Builder::new()
+            .name("foxglove-playback".to_string())
+            .spawn(move || {
+                if let Some(server) = weak_server.upgrade() {
+                    self.run(&server);
+                }
+            })
+            .expect("failed to spawn playback thread");
+        PlaybackServerHandle {
+            server,
+            control,
+            thread,
+        }
+    }
+
+    fn run(mut self, server: &Server) {
+        let mut reader = match self.new_reader(self.time_range.0) {
+            Ok(reader) => reader,
+            Err(e) => {
+                tracing::error!("Failed to read MCAP file: {e}");
+                return;
+            }
+        };
+        let mut pending: Option<(MessageHeader, Vec<u8>)> = None;
+        // The wall time and log time from which playback is currently paced.
+        let mut anchor: Option<(Instant, u64)> = None;
+        let mut generation = 0;
+        let mut last_broadcast: Option<Instant> = None;
+        let mut buf = Vec::new();
+
+        let mut state = self.control.state.lock();
+        loop {
+            if state.stopped {
+                return;
+            }
+            if let Some(time) = state.seek.take() {
+                drop(state);
+                server.clear_session(None);
+                match self.new_reader(time) {
+                    Ok(r) => reader = r,
+                    Err(e) => tracing::error!("Failed to seek: {e}"),
+                }
+                pending = None;
+                self.clock.set(time);
+                server.broadcast_time(time);
+                state = self.control.state.lock();
+                continue;
+            }
+            if state.generation != generation {
+                generation = state.generation;
+                anchor = None;
+            }
+            if state.status != PlaybackStatus::Playing {
+                self.control.changed.wait(&mut state);
+                continue;
+            }
+
+            if pending.is_none() {
+                drop(state);
+                let next = self.next_message(&mut reader, &mut buf);
+                state = self.control.state.lock();
+                match next {
+                    Ok(Some(message)) => pending = Some(message),
+                    result => {
+                        if let Err(e) = result {
+                            tracing::error!("Failed to read MCAP file: {e}");
+                        }
+                        state.status = PlaybackStatus::Ended;
+                        state.current_time = self.time_range.1;
+                        server.broadcast_playback_state(state.playback_state(false));
+                    }
+                }
+                continue;
+            }
+
+            let Some((header, data)) = &pending else {
+                continue;
+            };
+            let (wall, start) = *anchor.get_or_insert((Instant::now(), state.current_time));
+            let offset = header.log_time.saturating_sub(start) as f64 / f64::from(state.speed);
+            let deadline = wall + Duration::from_nanos(offset as u64);
+            if Instant::now() < deadline {
+                // Wake early if the playback state changes.
+                self.control.changed.wait_until(&mut state, deadline);
+                continue;
+            }
+
+            state.current_time = header.log_time;
+            drop(state);
+            self.clock.set(header.log_time);
+            if let Some(channel) = self.channels.get(&header.channel_id) {
+                channel.log_with_meta(
+                    data,
+                    PartialMetadata::with_log_time(header.log_time)
+                        .publish_time(header.publish_time)
+                        .sequence(header.sequence),
+                );
+            }
+            if last_broadcast.is_none_or(|t| t.elapsed() >= TIME_BROADCAST_INTERVAL) {
+                server.broadcast_time(header.log_time);
+                last_broadcast = Some(Instant::now());
+            }
+            pending = None;
+            state = self.control.state.lock();
+        }
+    }
+
+    /// Creates an indexed reader that yields messages in log-time order, starting from `time`.
+    fn new_reader(&self, time: u64) -> Result<IndexedReader, FoxgloveError> {
+        let options = IndexedReaderOptions::new().log_time_on_or_after(time);
+        Ok(IndexedReader::new_with_options(&self.summary, options)?)
+    }
+
+    /// Reads the next message, loading chunks from the file as needed.
+    fn next_message(
+        &mut self,
+        reader: &mut IndexedReader,
+        buf: &mut Vec<u8>,
+    ) -> Result<Option<(MessageHeader, Vec<u8>)>, FoxgloveError> {
+        while let Some(event) = reader.next_event() {
+            match event? {
+                IndexedReadEvent::ReadChunkRequest { offset, length } => {
+                    self.file.seek(SeekFrom::Start(offset))?;
+                    buf.resize(length, 0);
+                    self.file.read_exact(buf)?;
+                    reader.insert_chunk_record_data(offset, buf)?;
+                }
+                IndexedReadEvent::Message { header, data } => {
+                    return Ok(Some((header, data.to_vec())));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Reads the summary section of an MCAP file.
+fn read_summary(file: &mut File) -> Result<Option<mcap::Summary>, FoxgloveError> {
+    let mut reader = SummaryReader::new();
+    while let Some(event) = reader.next_event() {
+        match event? {
+            SummaryReadEvent::ReadRequest(len) => {
+                let read = file.read(reader.insert(len))?;
+                reader.notify_read(read);
+            }
+            SummaryReadEvent::SeekRequest(to) => {
+                reader.notify_seeked(file.seek(to)?);
+            }
+        }
+    }
+    Ok(reader.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::websocket::ws_protocol::server::ServerMessage;
+    use crate::websocket_client::WebSocketClient;
+    use crate::McapWriter;
+
+    fn write_recording(path: &Path) {
+        let ctx = Context::new();
+        let handle = McapWriter::new()
+            .context(&ctx)
+            .create_new_buffered_file(path)
+            .unwrap();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        for i in 0..10u64 {
+            ch.log_with_meta(&[i as u8], PartialMetadata::with_log_time(1000 + i));
+        }
+        handle.close().unwrap();
+    }
+
+    #[test]
+    fn test_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+        write_recording(&path);
+        let server = PlaybackServer::open(&path).unwrap();
+        assert_eq!(server.time_range(), (1000, 1009));
+    }
+
+    #[tokio::test]
+    async fn test_seek_and_play() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mcap");
+        write_recording(&path);
+        let handle = PlaybackServer::open(&path)
+            .unwrap()
+            .websocket_server(WebSocketServer::new().bind("127.0.0.1", 0))
+            .start()
+            .await
+            .unwrap();
+        assert_eq!(handle.state().status, PlaybackStatus::Paused);
+
+        let mut client = WebSocketClient::connect(format!("127.0.0.1:{}", handle.server().port()))
+            .await
+            .unwrap();
+        let ServerMessage::ServerInfo(info) = client.recv().await.unwrap() else {
+            panic!("expected server info");
+        };
+        assert_eq!(info.data_start_time.map(|t| t.nsec), Some(1000));
+        assert_eq!(info.data_end_time.map(|t| t.nsec), Some(1009));
+
+        client
+            .send(&PlaybackControlRequest {
+                playback_command: PlaybackCommand::Play,
+                playback_speed: 1.0,
+                seek_time: Some(1005),
+                request_id: "seek".to_string(),
+            })
+            .await
+            .unwrap();
+
+        // Playback runs to the end, starting from the seek time.
+        let mut times = Vec::new();
+        let ended = loop {
+            match client.recv().await.unwrap() {
+                ServerMessage::PlaybackState(state) if state.status == PlaybackStatus::Ended => {
+                    break state;
+                }
+                ServerMessage::Time(time) => times.push(time.timestamp),
+                _ => (),
+            }
+        };
+        assert_eq!(ended.current_time, 1009);
+        assert_eq!(ended.request_id, None);
+        assert_eq!(times.first(), Some(&1005));
+        assert_eq!(handle.state().status, PlaybackStatus::Ended);
+        let _ = handle.stop();
+    }
+}
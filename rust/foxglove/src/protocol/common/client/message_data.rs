@@ -60,6 +60,7 @@ mod tests {
     use crate::protocol::v1::{client::ClientMessage, BinaryMessage};
 
     use super::*;
+    use crate::protocol::v2;
 
     fn message() -> MessageData<'static> {
         MessageData::new(30, br#"{"key": "value"}"#)
@@ -77,4 +78,17 @@ mod tests {
         let msg = ClientMessage::parse_binary(&buf).unwrap();
         assert_eq!(msg, ClientMessage::MessageData(orig));
     }
+
+    #[test]
+    fn test_encode_v2() {
+        insta::assert_snapshot!(format!("{:#04x?}", v2::BinaryMessage::to_bytes(&message())));
+    }
+
+    #[test]
+    fn test_roundtrip_v2() {
+        let orig = message();
+        let buf = v2::BinaryMessage::to_bytes(&orig);
+        let msg = v2::client::ClientMessage::parse_binary(&buf).unwrap();
+        assert_eq!(msg, v2::client::ClientMessage::MessageData(orig));
+    }
 }
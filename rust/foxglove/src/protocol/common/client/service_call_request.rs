@@ -72,6 +72,7 @@ mod tests {
     use crate::protocol::v1::{client::ClientMessage, BinaryMessage};
 
     use super::*;
+    use crate::protocol::v2;
 
     fn message() -> ServiceCallRequest<'static> {
         ServiceCallRequest {
@@ -114,4 +115,17 @@ mod tests {
         let msg = ClientMessage::parse_binary(&buf).unwrap();
         assert_eq!(msg, ClientMessage::ServiceCallRequest(orig));
     }
+
+    #[test]
+    fn test_encode_v2() {
+        insta::assert_snapshot!(format!("{:#04x?}", v2::BinaryMessage::to_bytes(&message())));
+    }
+
+    #[test]
+    fn test_roundtrip_v2() {
+        let orig = message();
+        let buf = v2::BinaryMessage::to_bytes(&orig);
+        let msg = v2::client::ClientMessage::parse_binary(&buf).unwrap();
+        assert_eq!(msg, v2::client::ClientMessage::ServiceCallRequest(orig));
+    }
 }
@@ -135,6 +135,7 @@ mod tests {
     use crate::protocol::v1::{server::ServerMessage, BinaryMessage};
 
     use super::*;
+    use crate::protocol::v2;
 
     fn asset_data() -> FetchAssetResponse<'static> {
         FetchAssetResponse::asset_data(10, b"data")
@@ -199,4 +200,20 @@ mod tests {
         let msg = ServerMessage::parse_binary(&buf).unwrap();
         assert_eq!(msg, ServerMessage::FetchAssetResponse(orig));
     }
+
+    #[test]
+    fn test_encode_v2() {
+        insta::assert_snapshot!(format!(
+            "{:#04x?}",
+            v2::BinaryMessage::to_bytes(&asset_data())
+        ));
+    }
+
+    #[test]
+    fn test_roundtrip_v2() {
+        let orig = asset_data();
+        let buf = v2::BinaryMessage::to_bytes(&orig);
+        let msg = v2::server::ServerMessage::parse_binary(&buf).unwrap();
+        assert_eq!(msg, v2::server::ServerMessage::FetchAssetResponse(orig));
+    }
 }
@@ -41,6 +41,7 @@ mod tests {
     use crate::protocol::v1::{server::ServerMessage, BinaryMessage};
 
     use super::*;
+    use crate::protocol::v2;
 
     fn message() -> Time {
         Time::new(1234567890)
@@ -58,4 +59,17 @@ mod tests {
         let msg = ServerMessage::parse_binary(&buf).unwrap();
         assert_eq!(msg, ServerMessage::Time(orig));
     }
+
+    #[test]
+    fn test_encode_v2() {
+        insta::assert_snapshot!(format!("{:#04x?}", v2::BinaryMessage::to_bytes(&message())));
+    }
+
+    #[test]
+    fn test_roundtrip_v2() {
+        let orig = message();
+        let buf = v2::BinaryMessage::to_bytes(&orig);
+        let msg = v2::server::ServerMessage::parse_binary(&buf).unwrap();
+        assert_eq!(msg, v2::server::ServerMessage::Time(orig));
+    }
 }
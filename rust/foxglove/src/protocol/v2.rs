@@ -3,3 +3,6 @@
 pub mod client;
 mod message;
 pub mod server;
+pub mod tungstenite;
+
+pub use message::BinaryMessage;
@@ -0,0 +1,51 @@
+//! Tungstenite support.
+//!
+//! Messages which are shared with protocol v1 use the same encoding, and are converted by the
+//! implementations in [`crate::protocol::v1::tungstenite`].
+
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::protocol::v2::{client, server, BinaryMessage};
+use crate::protocol::ParseError;
+
+impl<'a> TryFrom<&'a Message> for client::ClientMessage<'a> {
+    type Error = ParseError;
+
+    fn try_from(msg: &'a Message) -> Result<Self, Self::Error> {
+        match msg {
+            Message::Text(utf8) => Self::parse_json(utf8),
+            Message::Binary(bytes) => Self::parse_binary(bytes),
+            _ => Err(ParseError::UnhandledMessageType),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Message> for server::ServerMessage<'a> {
+    type Error = ParseError;
+
+    fn try_from(msg: &'a Message) -> Result<Self, Self::Error> {
+        match msg {
+            Message::Text(utf8) => Self::parse_json(utf8),
+            Message::Binary(bytes) => Self::parse_binary(bytes),
+            _ => Err(ParseError::UnhandledMessageType),
+        }
+    }
+}
+
+impl From<&client::Subscribe> for Message {
+    fn from(value: &client::Subscribe) -> Self {
+        Message::Binary(value.to_bytes().into())
+    }
+}
+
+impl From<&client::Unsubscribe> for Message {
+    fn from(value: &client::Unsubscribe) -> Self {
+        Message::Binary(value.to_bytes().into())
+    }
+}
+
+impl From<&server::MessageData<'_>> for Message {
+    fn from(value: &server::MessageData<'_>) -> Self {
+        Message::Binary(value.to_bytes().into())
+    }
+}
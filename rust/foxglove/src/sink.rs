@@ -7,6 +7,16 @@ use smallvec::SmallVec;
 use crate::metadata::Metadata;
 use crate::{ChannelId, FoxgloveError, RawChannel};
 
+mod filtered_sink;
+mod fn_sink;
+mod metered_sink;
+mod tee_sink;
+
+pub use filtered_sink::FilteredSink;
+pub use fn_sink::FnSink;
+pub(crate) use metered_sink::MeteredSink;
+pub use tee_sink::TeeSink;
+
 /// Uniquely identifies a [`Sink`] in the context of this program.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct SinkId(NonZeroU64);
@@ -47,7 +57,18 @@ impl From<SinkId> for NonZeroU64 {
 ///
 /// Sinks are thread-safe and can be shared between threads. Usually you'd use our implementations
 /// like [`McapWriter`](crate::McapWriter) or [`WebSocketServer`](crate::WebSocketServer).
-#[doc(hidden)]
+///
+/// To log to a custom destination, implement this trait and register the sink with
+/// [`Context::add_sink`][crate::Context::add_sink]. For simple cases, the [`FnSink`],
+/// [`FilteredSink`], and [`TeeSink`] adapters can be composed without implementing the trait
+/// directly.
+///
+/// By default, the [`log`](Sink::log) method is invoked synchronously on the thread that logs the
+/// message. For a context created with [`Context::with_dispatcher`][dispatch], it is instead
+/// invoked on one of the context's worker threads. In either case, implementations should avoid
+/// blocking for long periods of time, since that stalls logging or fills the dispatch queue.
+///
+/// [dispatch]: crate::Context::with_dispatcher
 pub trait Sink: Send + Sync {
     /// Returns the sink's unique ID.
     fn id(&self) -> SinkId;
@@ -121,6 +142,15 @@ pub trait Sink: Send + Sync {
     fn auto_subscribe(&self) -> bool {
         true
     }
+
+    /// Returns the number of messages that this sink accepted but subsequently dropped.
+    ///
+    /// A sink that buffers messages may drop them under backpressure, without reporting an error
+    /// from [`Sink::log`]. Such sinks can override this method to surface the count in
+    /// [`SinkStats`][crate::SinkStats]. The default implementation returns zero.
+    fn dropped_messages(&self) -> u64 {
+        0
+    }
 }
 
 /// A small group of sinks.
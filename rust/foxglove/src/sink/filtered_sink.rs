@@ -0,0 +1,199 @@
+//! A sink wrapper that applies a channel filter.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use crate::sink_channel_filter::SinkChannelFilterFn;
+use crate::{
+    ChannelDescriptor, ChannelId, FoxgloveError, Metadata, RawChannel, Sink, SinkChannelFilter,
+    SinkId,
+};
+
+/// A [`Sink`] that forwards messages to an inner sink, but only for channels that pass a
+/// [`SinkChannelFilter`].
+///
+/// Channels that are rejected by the filter are hidden from the inner sink entirely: it is not
+/// notified when they are added or removed, and it never receives messages logged on them.
+///
+/// The filtered sink shares its [`SinkId`] with the inner sink, so that messages logged with
+/// [`RawChannel::log_to_sink`] are delivered as expected. As a consequence, the inner sink must not
+/// also be registered with the context directly.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use foxglove::{Context, FilteredSink, FnSink};
+///
+/// let ctx = Context::new();
+/// let inner = Arc::new(FnSink::new(|_, _, _| Ok(())));
+/// let sink = FilteredSink::from_fn(inner, |channel| channel.topic().starts_with("/camera"));
+/// ctx.add_sink(Arc::new(sink));
+/// ```
+pub struct FilteredSink {
+    inner: Arc<dyn Sink>,
+    filter: Arc<dyn SinkChannelFilter>,
+    /// Channels that have been admitted by the filter.
+    admitted: RwLock<HashSet<ChannelId>>,
+}
+
+impl FilteredSink {
+    /// Wraps `inner` with the provided channel filter.
+    pub fn new(inner: Arc<dyn Sink>, filter: Arc<dyn SinkChannelFilter>) -> Self {
+        Self {
+            inner,
+            filter,
+            admitted: RwLock::default(),
+        }
+    }
+
+    /// Wraps `inner` with a channel filter function. See [`SinkChannelFilter`] for more
+    /// information.
+    pub fn from_fn(
+        inner: Arc<dyn Sink>,
+        filter: impl Fn(&ChannelDescriptor) -> bool + Sync + Send + 'static,
+    ) -> Self {
+        Self::new(inner, Arc::new(SinkChannelFilterFn(filter)))
+    }
+
+    /// Returns the inner sink.
+    pub fn inner(&self) -> &Arc<dyn Sink> {
+        &self.inner
+    }
+}
+
+impl Debug for FilteredSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FilteredSink")
+            .field("id", &self.inner.id())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Sink for FilteredSink {
+    fn id(&self) -> SinkId {
+        self.inner.id()
+    }
+
+    fn log(
+        &self,
+        channel: &RawChannel,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        // The filter is also enforced here, in case we're subscribed to a channel by some other
+        // means, such as by a parent `TeeSink`.
+        if !self.admitted.read().contains(&channel.id()) {
+            return Ok(());
+        }
+        self.inner.log(channel, msg, metadata)
+    }
+
+    fn add_channels(&self, channels: &[&Arc<RawChannel>]) -> Option<Vec<ChannelId>> {
+        let channels: Vec<_> = channels
+            .iter()
+            .copied()
+            .filter(|c| self.filter.should_subscribe(c.descriptor()))
+            .collect();
+        if channels.is_empty() {
+            return None;
+        }
+        self.admitted
+            .write()
+            .extend(channels.iter().map(|c| c.id()));
+
+        let ids = self.inner.add_channels(&channels);
+        if self.inner.auto_subscribe() {
+            Some(channels.iter().map(|c| c.id()).collect())
+        } else {
+            ids
+        }
+    }
+
+    fn remove_channel(&self, channel: &RawChannel) {
+        if self.admitted.write().remove(&channel.id()) {
+            self.inner.remove_channel(channel);
+        }
+    }
+
+    fn auto_subscribe(&self) -> bool {
+        false
+    }
+
+    fn dropped_messages(&self) -> u64 {
+        self.inner.dropped_messages()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::RecordingSink;
+    use crate::{ChannelBuilder, Context};
+
+    fn new_channel(ctx: &Arc<Context>, topic: &str) -> Arc<RawChannel> {
+        ChannelBuilder::new(topic)
+            .context(ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_filtered_sink() {
+        let ctx = Context::new();
+        let c1 = new_channel(&ctx, "/1");
+
+        let inner = Arc::new(RecordingSink::new());
+        let sink = Arc::new(FilteredSink::from_fn(inner.clone(), |c| c.topic() != "/2"));
+        assert_eq!(sink.id(), inner.id());
+        assert!(ctx.add_sink(sink.clone()));
+
+        let c2 = new_channel(&ctx, "/2");
+        let c3 = new_channel(&ctx, "/3");
+        assert!(c1.has_sinks());
+        assert!(!c2.has_sinks());
+        assert!(c3.has_sinks());
+
+        c1.log(b"1");
+        c2.log(b"2");
+        c3.log(b"3");
+        let msgs = inner.take_messages();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0].channel_id, c1.id());
+        assert_eq!(msgs[1].channel_id, c3.id());
+
+        // Messages on rejected channels are dropped, even if delivered directly.
+        sink.log(&c2, b"2", &Metadata::default()).unwrap();
+        assert!(inner.take_messages().is_empty());
+    }
+
+    #[test]
+    fn test_filtered_sink_respects_inner_subscriptions() {
+        let ctx = Context::new();
+        let inner = Arc::new(
+            RecordingSink::new()
+                .auto_subscribe(false)
+                .add_channels(|channels| {
+                    Some(
+                        channels
+                            .iter()
+                            .filter(|c| c.topic() != "/3")
+                            .map(|c| c.id())
+                            .collect(),
+                    )
+                }),
+        );
+        let sink = Arc::new(FilteredSink::from_fn(inner, |c| c.topic() != "/2"));
+        assert!(ctx.add_sink(sink));
+
+        let c1 = new_channel(&ctx, "/1");
+        let c2 = new_channel(&ctx, "/2");
+        let c3 = new_channel(&ctx, "/3");
+        assert!(c1.has_sinks());
+        assert!(!c2.has_sinks());
+        assert!(!c3.has_sinks());
+    }
+}
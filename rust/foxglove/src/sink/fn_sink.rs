@@ -0,0 +1,101 @@
+//! A sink backed by a closure.
+
+use std::fmt::Debug;
+
+use crate::{FoxgloveError, Metadata, RawChannel, Sink, SinkId};
+
+/// A [`Sink`] that invokes a closure for each logged message.
+///
+/// This is a convenient way to implement a simple sink without defining a new type. The sink
+/// automatically subscribes to all channels in its context. To restrict the set of channels, wrap
+/// it in a [`FilteredSink`](crate::FilteredSink).
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use foxglove::{Context, FnSink};
+///
+/// let ctx = Context::new();
+/// let sink = Arc::new(FnSink::new(|channel, msg, metadata| {
+///     println!("{}: {} bytes at {}", channel.topic(), msg.len(), metadata.log_time);
+///     Ok(())
+/// }));
+/// ctx.add_sink(sink);
+/// ```
+pub struct FnSink<F> {
+    id: SinkId,
+    func: F,
+}
+
+impl<F> FnSink<F>
+where
+    F: Fn(&RawChannel, &[u8], &Metadata) -> Result<(), FoxgloveError> + Send + Sync,
+{
+    /// Creates a new sink that invokes `func` for each logged message.
+    pub fn new(func: F) -> Self {
+        Self {
+            id: SinkId::next(),
+            func,
+        }
+    }
+}
+
+impl<F> Debug for FnSink<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FnSink")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> Sink for FnSink<F>
+where
+    F: Fn(&RawChannel, &[u8], &Metadata) -> Result<(), FoxgloveError> + Send + Sync,
+{
+    fn id(&self) -> SinkId {
+        self.id
+    }
+
+    fn log(
+        &self,
+        channel: &RawChannel,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        (self.func)(channel, msg, metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{ChannelBuilder, Context};
+
+    #[test]
+    fn test_fn_sink() {
+        let ctx = Context::new();
+        let count = Arc::new(AtomicUsize::new(0));
+        let sink = Arc::new(FnSink::new({
+            let count = count.clone();
+            move |channel, msg, metadata| {
+                assert_eq!(channel.topic(), "/t");
+                assert_eq!(msg, b"hello");
+                assert_eq!(metadata.log_time, 42);
+                count.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+        }));
+        assert!(ctx.add_sink(sink));
+
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        ch.log_with_meta(b"hello", crate::PartialMetadata::with_log_time(42));
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+}
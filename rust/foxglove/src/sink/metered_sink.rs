@@ -0,0 +1,71 @@
+//! A sink wrapper that records throughput statistics.
+
+use std::sync::Arc;
+
+use crate::stats::StatsCounter;
+use crate::{ChannelId, FoxgloveError, Metadata, RawChannel, Sink, SinkId, SinkStats};
+
+/// Wraps a sink registered with a [`Context`][crate::Context], to record throughput statistics
+/// on its behalf.
+///
+/// Sinks are only wrapped if enabled with [`Context::enable_sink_stats`][enable].
+///
+/// [enable]: crate::Context::enable_sink_stats
+pub(crate) struct MeteredSink {
+    inner: Arc<dyn Sink>,
+    stats: StatsCounter,
+}
+
+impl MeteredSink {
+    pub fn new(inner: Arc<dyn Sink>) -> Self {
+        Self {
+            inner,
+            stats: StatsCounter::default(),
+        }
+    }
+
+    /// Returns a snapshot of the sink's statistics.
+    pub fn stats(&self) -> SinkStats {
+        self.stats.sink_stats(self.inner.dropped_messages())
+    }
+}
+
+impl Sink for MeteredSink {
+    fn id(&self) -> SinkId {
+        self.inner.id()
+    }
+
+    fn log(
+        &self,
+        channel: &RawChannel,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        self.stats.record(msg.len(), metadata.log_time);
+        let result = self.inner.log(channel, msg, metadata);
+        if result.is_err() {
+            self.stats.record_error();
+        }
+        result
+    }
+
+    fn add_channels(&self, channels: &[&Arc<RawChannel>]) -> Option<Vec<ChannelId>> {
+        self.inner.add_channels(channels)
+    }
+
+    fn add_channel(&self, channel: &Arc<RawChannel>) -> bool {
+        self.inner.add_channel(channel)
+    }
+
+    fn remove_channel(&self, channel: &RawChannel) {
+        self.inner.remove_channel(channel);
+    }
+
+    fn auto_subscribe(&self) -> bool {
+        self.inner.auto_subscribe()
+    }
+
+    fn dropped_messages(&self) -> u64 {
+        self.inner.dropped_messages()
+    }
+}
@@ -0,0 +1,135 @@
+//! A sink that fans out to multiple child sinks.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::log_sink_set::LogSinkSet;
+use crate::{ChannelId, FoxgloveError, Metadata, RawChannel, Sink, SinkId};
+
+/// A [`Sink`] that forwards every message to each of its child sinks.
+///
+/// The tee sink automatically subscribes to all channels in its context, and forwards channel
+/// advertisements and removals to its children. Child subscription preferences are not consulted;
+/// to restrict the set of channels that a particular child receives, wrap that child in a
+/// [`FilteredSink`](crate::FilteredSink).
+///
+/// Errors returned by a child are logged, and do not prevent delivery to the remaining children.
+///
+/// The child sinks must not also be registered with the context directly.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use foxglove::{Context, FilteredSink, FnSink, Sink, TeeSink};
+///
+/// let ctx = Context::new();
+/// let all: Arc<dyn Sink> = Arc::new(FnSink::new(|_, _, _| Ok(())));
+/// let some: Arc<dyn Sink> = Arc::new(FilteredSink::from_fn(
+///     Arc::new(FnSink::new(|_, _, _| Ok(()))),
+///     |channel| channel.topic() == "/log",
+/// ));
+/// ctx.add_sink(Arc::new(TeeSink::new([all, some])));
+/// ```
+pub struct TeeSink {
+    id: SinkId,
+    children: LogSinkSet,
+}
+
+impl TeeSink {
+    /// Creates a new tee sink with the provided children.
+    pub fn new(children: impl IntoIterator<Item = Arc<dyn Sink>>) -> Self {
+        let sinks = LogSinkSet::new();
+        sinks.store(children.into_iter().collect());
+        Self {
+            id: SinkId::next(),
+            children: sinks,
+        }
+    }
+}
+
+impl Debug for TeeSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TeeSink")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Sink for TeeSink {
+    fn id(&self) -> SinkId {
+        self.id
+    }
+
+    fn log(
+        &self,
+        channel: &RawChannel,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        self.children
+            .for_each(|sink| sink.log(channel, msg, metadata));
+        Ok(())
+    }
+
+    fn add_channels(&self, channels: &[&Arc<RawChannel>]) -> Option<Vec<ChannelId>> {
+        self.children.for_each(|sink| {
+            sink.add_channels(channels);
+            Ok(())
+        });
+        None
+    }
+
+    fn remove_channel(&self, channel: &RawChannel) {
+        self.children.for_each(|sink| {
+            sink.remove_channel(channel);
+            Ok(())
+        });
+    }
+
+    fn dropped_messages(&self) -> u64 {
+        self.children
+            .load()
+            .iter()
+            .map(|s| s.dropped_messages())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{ErrorSink, RecordingSink};
+    use crate::{ChannelBuilder, Context, FilteredSink};
+
+    #[test]
+    fn test_tee_sink() {
+        let ctx = Context::new();
+        let s1 = Arc::new(RecordingSink::new());
+        let s2 = Arc::new(RecordingSink::new());
+        let tee = Arc::new(TeeSink::new([
+            Arc::new(ErrorSink::default()) as Arc<dyn Sink>,
+            s1.clone(),
+            Arc::new(FilteredSink::from_fn(s2.clone(), |c| c.topic() == "/2")),
+        ]));
+        assert!(ctx.add_sink(tee));
+
+        let c1 = ChannelBuilder::new("/1")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        let c2 = ChannelBuilder::new("/2")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        c1.log(b"1");
+        c2.log(b"2");
+
+        let msgs = s1.take_messages();
+        assert_eq!(msgs.len(), 2);
+        let msgs = s2.take_messages();
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].channel_id, c2.id());
+    }
+}
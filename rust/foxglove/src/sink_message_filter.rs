@@ -0,0 +1,29 @@
+use crate::{Metadata, RawChannel};
+
+/// A filter for individual messages, which is consulted each time a message is logged to a sink.
+///
+/// Unlike a [`SinkChannelFilter`][crate::SinkChannelFilter], which decides once per channel when
+/// the sink subscribes, this filter can inspect the message payload and metadata. This can be used
+/// to drop messages by content, by time window, or according to a sampling policy.
+///
+/// The filter is invoked synchronously on the thread that delivers the message to the sink. This
+/// is the thread that logs the message, or a dispatcher worker thread if the context was created
+/// with [`Context::with_dispatcher`][crate::Context::with_dispatcher]. Either way, implementations
+/// should be fast and must not block.
+pub trait SinkMessageFilter: Sync + Send {
+    /// Returns true if the message should be logged to the sink.
+    fn should_log(&self, channel: &RawChannel, msg: &[u8], metadata: &Metadata) -> bool;
+}
+
+pub(crate) struct SinkMessageFilterFn<F>(pub F)
+where
+    F: Fn(&RawChannel, &[u8], &Metadata) -> bool + Sync + Send;
+
+impl<F> SinkMessageFilter for SinkMessageFilterFn<F>
+where
+    F: Fn(&RawChannel, &[u8], &Metadata) -> bool + Sync + Send,
+{
+    fn should_log(&self, channel: &RawChannel, msg: &[u8], metadata: &Metadata) -> bool {
+        self.0(channel, msg, metadata)
+    }
+}
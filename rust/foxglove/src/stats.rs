@@ -0,0 +1,351 @@
+//! Throughput statistics for channels and sinks.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, LazyLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::schemas::KeyValuePair;
+use crate::{ChannelId, Context, SinkId};
+
+/// The default topic for the diagnostics channel.
+pub const DIAGNOSTICS_TOPIC: &str = "/foxglove/diagnostics";
+
+/// Reference point for rate measurements.
+static EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Returns the number of whole seconds since [`EPOCH`].
+fn epoch_secs() -> u64 {
+    EPOCH.elapsed().as_secs()
+}
+
+/// Measures an event rate over the most recent complete one-second window.
+#[derive(Default)]
+struct RateMeter {
+    /// The current window, in seconds since [`EPOCH`].
+    window: AtomicU64,
+    /// The count of events in the current window.
+    current: AtomicU64,
+    /// The count of events in the previous window.
+    previous: AtomicU64,
+}
+
+impl RateMeter {
+    fn record(&self) {
+        self.record_at(epoch_secs());
+    }
+
+    /// Returns the number of events per second.
+    fn rate(&self) -> f64 {
+        self.rate_at(epoch_secs())
+    }
+
+    fn record_at(&self, now: u64) {
+        let window = self.window.load(Ordering::Relaxed);
+        if now != window
+            && self
+                .window
+                .compare_exchange(window, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            let count = self.current.swap(0, Ordering::Relaxed);
+            let previous = if now == window + 1 { count } else { 0 };
+            self.previous.store(previous, Ordering::Relaxed);
+        }
+        self.current.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn rate_at(&self, now: u64) -> f64 {
+        let window = self.window.load(Ordering::Relaxed);
+        let count = if now == window {
+            self.previous.load(Ordering::Relaxed)
+        } else if now == window + 1 {
+            self.current.load(Ordering::Relaxed)
+        } else {
+            0
+        };
+        count as f64
+    }
+}
+
+/// Lock-free throughput counters.
+#[derive(Default)]
+pub(crate) struct StatsCounter {
+    messages: AtomicU64,
+    bytes: AtomicU64,
+    /// Zero if no messages have been recorded.
+    last_log_time: AtomicU64,
+    errors: AtomicU64,
+    rate: RateMeter,
+}
+
+impl StatsCounter {
+    /// Records a message.
+    pub fn record(&self, bytes: usize, log_time: u64) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.last_log_time.store(log_time, Ordering::Relaxed);
+        self.rate.record();
+    }
+
+    /// Records an error.
+    pub fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn channel_stats(&self, topic: &str) -> ChannelStats {
+        ChannelStats {
+            topic: topic.to_string(),
+            messages: self.messages.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            last_log_time: self.last_log_time(),
+            rate: self.rate.rate(),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn sink_stats(&self, dropped: u64) -> SinkStats {
+        SinkStats {
+            messages: self.messages.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            last_log_time: self.last_log_time(),
+            rate: self.rate.rate(),
+            errors: self.errors.load(Ordering::Relaxed),
+            dropped,
+        }
+    }
+
+    fn last_log_time(&self) -> Option<u64> {
+        Some(self.last_log_time.load(Ordering::Relaxed)).filter(|&t| t != 0)
+    }
+}
+
+/// Throughput statistics for a channel.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct ChannelStats {
+    /// The channel topic.
+    pub topic: String,
+    /// The number of messages logged on this channel, whether or not any sinks are subscribed.
+    pub messages: u64,
+    /// The total size of the messages, in bytes.
+    ///
+    /// Typed channels do not encode messages while no sinks are subscribed, so those messages are
+    /// not included in this total.
+    pub bytes: u64,
+    /// The log time of the most recent message, if any.
+    pub last_log_time: Option<u64>,
+    /// The number of messages logged during the last full second.
+    pub rate: f64,
+    /// The number of times a sink failed to log a message on this channel.
+    pub errors: u64,
+}
+
+/// Throughput statistics for a sink.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct SinkStats {
+    /// The number of messages delivered to the sink.
+    pub messages: u64,
+    /// The total size of the messages, in bytes.
+    pub bytes: u64,
+    /// The log time of the most recent message, if any.
+    pub last_log_time: Option<u64>,
+    /// The number of messages delivered during the last full second.
+    pub rate: f64,
+    /// The number of messages that the sink failed to log.
+    pub errors: u64,
+    /// The number of messages that the sink accepted but subsequently dropped, as reported by
+    /// [`Sink::dropped_messages`][crate::Sink::dropped_messages].
+    pub dropped: u64,
+}
+
+/// A snapshot of throughput statistics for a [`Context`].
+///
+/// See [`Context::stats`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct ContextStats {
+    /// Statistics for each channel in the context.
+    pub channels: HashMap<ChannelId, ChannelStats>,
+    /// Statistics for each sink in the context.
+    pub sinks: HashMap<SinkId, SinkStats>,
+}
+
+impl ContextStats {
+    /// Formats the statistics as key-value pairs, with one entry per channel and sink.
+    pub fn to_key_value_pairs(&self) -> Vec<KeyValuePair> {
+        let mut pairs: Vec<_> = self
+            .channels
+            .values()
+            .map(|s| KeyValuePair {
+                key: format!("channel {}", s.topic),
+                value: format!(
+                    "messages={} bytes={} rate={:.1} errors={}",
+                    s.messages, s.bytes, s.rate, s.errors
+                ),
+            })
+            .collect();
+        pairs.sort_by(|a, b| a.key.cmp(&b.key));
+        let mut sinks: Vec<_> = self.sinks.iter().collect();
+        sinks.sort_by_key(|(id, _)| **id);
+        pairs.extend(sinks.into_iter().map(|(id, s)| KeyValuePair {
+            key: format!("sink {id}"),
+            value: format!(
+                "messages={} bytes={} rate={:.1} errors={} dropped={}",
+                s.messages, s.bytes, s.rate, s.errors, s.dropped
+            ),
+        }));
+        pairs
+    }
+}
+
+/// A handle to a diagnostics publisher started with [`Context::start_diagnostics`].
+///
+/// Publishing stops when the handle is dropped.
+#[must_use]
+#[derive(Debug)]
+pub struct DiagnosticsHandle {
+    stop_tx: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DiagnosticsHandle {
+    pub(crate) fn start(context: &Arc<Context>, topic: &str, interval: Duration) -> Self {
+        let channel = context.channel_builder(topic).build::<KeyValuePair>();
+        let context = Arc::downgrade(context);
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("foxglove-diagnostics".into())
+            .spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                    let Some(context) = context.upgrade() else {
+                        break;
+                    };
+                    let mut stats = context.stats();
+                    stats.channels.remove(&channel.id());
+                    for pair in stats.to_key_value_pairs() {
+                        channel.log(&pair);
+                    }
+                }
+            })
+            .expect("failed to spawn diagnostics thread");
+        Self {
+            stop_tx: Some(stop_tx),
+            thread: Some(thread),
+        }
+    }
+
+    /// Stops publishing diagnostics.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for DiagnosticsHandle {
+    fn drop(&mut self) {
+        drop(self.stop_tx.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{ErrorSink, RecordingSink};
+    use crate::{ChannelBuilder, PartialMetadata, Sink};
+
+    #[test]
+    fn test_context_stats() {
+        let ctx = Context::new();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+
+        // Messages logged without sinks are counted by the channel.
+        ch.log_with_meta(b"f", PartialMetadata::with_log_time(5));
+
+        // Sinks are not metered unless sink statistics are enabled.
+        let unmetered = Arc::new(RecordingSink::new());
+        ctx.add_sink(unmetered.clone());
+        ctx.enable_sink_stats();
+
+        let ok = Arc::new(RecordingSink::new());
+        let err = Arc::new(ErrorSink::default());
+        ctx.add_sink(ok.clone());
+        ctx.add_sink(err.clone());
+        ch.log_with_meta(b"abc", PartialMetadata::with_log_time(10));
+        ch.log_with_meta(b"de", PartialMetadata::with_log_time(20));
+
+        let stats = ctx.stats();
+        let channel = &stats.channels[&ch.id()];
+        assert_eq!(channel.topic, "/t");
+        assert_eq!(channel.messages, 3);
+        assert_eq!(channel.bytes, 6);
+        assert_eq!(channel.last_log_time, Some(20));
+        assert_eq!(channel.errors, 2);
+        assert!(!stats.sinks.contains_key(&unmetered.id()));
+        assert!(channel.rate <= 3.0);
+
+        let sink = &stats.sinks[&ok.id()];
+        assert_eq!(sink.messages, 2);
+        assert_eq!(sink.bytes, 5);
+        assert_eq!(sink.last_log_time, Some(20));
+        assert_eq!(sink.errors, 0);
+
+        let sink = &stats.sinks[&err.id()];
+        assert_eq!(sink.messages, 2);
+        assert_eq!(sink.errors, 2);
+
+        assert_eq!(ch.stats(), *channel);
+    }
+
+    #[test]
+    fn test_rate_meter() {
+        let meter = RateMeter::default();
+        meter.record_at(10);
+        meter.record_at(10);
+        assert_eq!(meter.rate_at(10), 0.0);
+        assert_eq!(meter.rate_at(11), 2.0);
+
+        meter.record_at(11);
+        assert_eq!(meter.rate_at(11), 2.0);
+        assert_eq!(meter.rate_at(12), 1.0);
+
+        // An idle window decays to zero.
+        assert_eq!(meter.rate_at(13), 0.0);
+        meter.record_at(20);
+        assert_eq!(meter.rate_at(20), 0.0);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let ctx = Context::new();
+        let ch = ChannelBuilder::new("/t")
+            .context(&ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+        let sink = Arc::new(RecordingSink::new());
+        ctx.add_sink(sink.clone());
+        ch.log(b"x");
+
+        let handle = ctx.start_diagnostics(Duration::from_millis(10));
+        let diagnostics = ctx.get_channel_by_topic(DIAGNOSTICS_TOPIC).unwrap();
+        let start = Instant::now();
+        while !sink
+            .take_messages()
+            .iter()
+            .any(|m| m.channel_id == diagnostics.id())
+        {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        handle.stop();
+    }
+}
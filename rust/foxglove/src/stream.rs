@@ -5,28 +5,58 @@
 
 use std::{
     fmt::Debug,
-    io::{self, Seek, SeekFrom, Write},
+    future::Future,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
     pin::Pin,
-    sync::Arc,
-    task::Poll,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Poll, Waker},
+    time::{Duration, Instant},
 };
 
-use tokio::sync::mpsc::{Receiver as TokioReceiver, Sender as TokioSender};
-
 use bytes::{Bytes, BytesMut};
-use futures::{ready, Stream};
-use parking_lot::Mutex;
+use futures::Stream;
+use parking_lot::{Condvar, Mutex};
+use tokio::sync::Notify;
+
+use crate::mcap_writer::McapSink;
+use crate::sink_channel_filter::SinkChannelFilterFn;
+use crate::{
+    ChannelBuilder, ChannelDescriptor, ChannelId, Context, FoxgloveError, McapAttachment,
+    McapAttachmentHeader, McapWriteOptions, McapWriterHandle, Metadata, OverflowPolicy, RawChannel,
+    Sink, SinkChannelFilter, SinkId,
+};
 
-use crate::{ChannelBuilder, Context, FoxgloveError, McapWriteOptions, McapWriterHandle};
+/// The default buffer capacity for a stream attached with [`attach_mcap_stream`].
+const DEFAULT_BUFFER_CAPACITY: usize = 4 * 1024 * 1024;
 
 #[derive(Default)]
 struct Inner {
     buffer: BytesMut,
     position: u64,
+    /// Set when the writer begins to finish. No further messages are admitted.
+    closing: bool,
+    /// Set when the writer has finished. The stream ends once the buffer is drained.
+    finished: bool,
+    /// Set when the [`McapStream`] is dropped. Further writes are discarded.
+    stream_closed: bool,
+    waker: Option<Waker>,
+}
+
+#[derive(Default)]
+struct Shared {
+    inner: Mutex<Inner>,
+    /// Notifies blocked logging threads when the stream consumes the buffer.
+    consumed: Condvar,
+    /// Notifies async tasks when the stream consumes the buffer.
+    consumed_notify: Notify,
 }
 
 #[derive(Default, Clone)]
-struct SharedBuffer(Arc<Mutex<Inner>>);
+struct SharedBuffer(Arc<Shared>);
 
 impl Debug for SharedBuffer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -34,11 +64,118 @@ impl Debug for SharedBuffer {
     }
 }
 
+impl SharedBuffer {
+    /// Waits until there is room in the buffer for another message, if the policy blocks, and
+    /// returns whether the message should be written.
+    fn admit(
+        &self,
+        capacity: usize,
+        policy: OverflowPolicy,
+        deadline: Option<Instant>,
+    ) -> Admission {
+        let mut inner = self.0.inner.lock();
+        loop {
+            if inner.closing || inner.stream_closed || deadline.is_some_and(|d| d <= Instant::now())
+            {
+                return Admission::Closed;
+            }
+            if inner.buffer.len() < capacity {
+                return Admission::Accepted;
+            }
+            match (policy, deadline) {
+                (OverflowPolicy::Block, Some(deadline)) => {
+                    self.0.consumed.wait_until(&mut inner, deadline);
+                }
+                (OverflowPolicy::Block, None) => self.0.consumed.wait(&mut inner),
+                _ => return Admission::Full,
+            }
+        }
+    }
+
+    /// Blocks until the buffer is below `capacity`, or the stream is closing.
+    fn wait_for_capacity(&self, capacity: usize) {
+        let mut inner = self.0.inner.lock();
+        while !inner.closing && !inner.stream_closed && inner.buffer.len() >= capacity {
+            self.0.consumed.wait(&mut inner);
+        }
+    }
+
+    /// Waits until the stream has consumed the buffer.
+    async fn drained(&self) -> Result<(), FoxgloveError> {
+        loop {
+            let notified = self.0.consumed_notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let inner = self.0.inner.lock();
+                if inner.buffer.is_empty() {
+                    return Ok(());
+                }
+                if inner.stream_closed {
+                    return Err(std::io::Error::other("McapStream was closed").into());
+                }
+            }
+            notified.await;
+        }
+    }
+
+    fn set_closing(&self) {
+        self.0.inner.lock().closing = true;
+        self.0.consumed.notify_all();
+    }
+
+    fn set_finished(&self) {
+        let waker = {
+            let mut inner = self.0.inner.lock();
+            inner.finished = true;
+            inner.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn set_stream_closed(&self) {
+        {
+            let mut inner = self.0.inner.lock();
+            inner.stream_closed = true;
+            inner.buffer.clear();
+        }
+        self.0.consumed.notify_all();
+        self.0.consumed_notify.notify_waiters();
+    }
+
+    fn poll_read(&self, cx: &mut std::task::Context<'_>) -> Poll<Option<Bytes>> {
+        let mut inner = self.0.inner.lock();
+        if !inner.buffer.is_empty() {
+            let bytes = inner.buffer.split().freeze();
+            drop(inner);
+            self.0.consumed.notify_all();
+            self.0.consumed_notify.notify_waiters();
+            return Poll::Ready(Some(bytes));
+        }
+        if inner.finished {
+            return Poll::Ready(None);
+        }
+        inner.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
 impl Write for SharedBuffer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let mut inner = self.0.lock();
-        inner.buffer.extend_from_slice(buf);
-        inner.position += buf.len() as u64;
+        let waker = {
+            let mut inner = self.0.inner.lock();
+            inner.position += buf.len() as u64;
+            if inner.stream_closed {
+                return Ok(buf.len());
+            }
+            inner.buffer.extend_from_slice(buf);
+            inner.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
         Ok(buf.len())
     }
 
@@ -49,7 +186,7 @@ impl Write for SharedBuffer {
 
 impl Seek for SharedBuffer {
     fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
-        let inner = self.0.lock();
+        let inner = self.0.inner.lock();
         match pos {
             SeekFrom::Start(n) if inner.position == n => Ok(n),
             SeekFrom::Current(0) => Ok(inner.position),
@@ -58,93 +195,419 @@ impl Seek for SharedBuffer {
     }
 }
 
+/// The outcome of [`SharedBuffer::admit`].
+enum Admission {
+    /// The message should be written.
+    Accepted,
+    /// The buffer is full, and the overflow policy discards the message.
+    Full,
+    /// The stream has ended, or is ending, so the message is discarded.
+    Closed,
+}
+
+/// A sink that applies the stream's overflow policy before passing messages to the MCAP sink.
+struct StreamSink {
+    inner: Arc<McapSink<SharedBuffer>>,
+    buffer: SharedBuffer,
+    capacity: usize,
+    policy: OverflowPolicy,
+    deadline: Option<Instant>,
+    /// The number of messages discarded because the buffer was full.
+    dropped: AtomicU64,
+}
+
+impl Sink for StreamSink {
+    fn id(&self) -> SinkId {
+        self.inner.id()
+    }
+
+    fn log(
+        &self,
+        channel: &RawChannel,
+        msg: &[u8],
+        metadata: &Metadata,
+    ) -> Result<(), FoxgloveError> {
+        match self.buffer.admit(self.capacity, self.policy, self.deadline) {
+            Admission::Accepted => self.inner.log(channel, msg, metadata),
+            Admission::Full => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Admission::Closed => Ok(()),
+        }
+    }
+
+    fn add_channels(&self, channels: &[&Arc<RawChannel>]) -> Option<Vec<ChannelId>> {
+        self.inner.add_channels(channels)
+    }
+
+    fn remove_channel(&self, channel: &RawChannel) {
+        self.inner.remove_channel(channel);
+    }
+
+    fn auto_subscribe(&self) -> bool {
+        self.inner.auto_subscribe()
+    }
+
+    fn dropped_messages(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// A reader that waits for the stream to consume the buffer before each read, so that
+/// attachment data is not read faster than the stream consumes it.
+struct ThrottledReader<'a, R> {
+    reader: R,
+    buffer: &'a SharedBuffer,
+    capacity: usize,
+}
+
+impl<R: Read> Read for ThrottledReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.buffer.wait_for_capacity(self.capacity);
+        self.reader.read(buf)
+    }
+}
+
+/// The MCAP writer, shared by the handle and the stream.
+struct StreamWriter {
+    handle: Mutex<Option<McapWriterHandle<SharedBuffer>>>,
+    buffer: SharedBuffer,
+    /// The buffer capacity, if the stream applies backpressure.
+    capacity: Option<usize>,
+}
+
+impl StreamWriter {
+    /// Writes an attachment, streaming its data from `reader`.
+    fn attach_from_reader(
+        &self,
+        header: McapAttachmentHeader,
+        length: u64,
+        reader: impl Read,
+    ) -> Result<(), FoxgloveError> {
+        let handle = self.handle.lock();
+        let handle = handle.as_ref().ok_or(FoxgloveError::SinkClosed)?;
+        match self.capacity {
+            Some(capacity) => handle.attach_from_reader(
+                header,
+                length,
+                ThrottledReader {
+                    reader,
+                    buffer: &self.buffer,
+                    capacity,
+                },
+            ),
+            None => handle.attach_from_reader(header, length, reader),
+        }
+    }
+
+    /// Stops logging events, and writes the end of the MCAP file to the buffer.
+    ///
+    /// This blocks until the writer has been closed. Concurrent calls wait for the first one to
+    /// complete, so that the stream does not end before the file has been completed.
+    fn finish(&self) -> Result<(), FoxgloveError> {
+        // Release logging threads blocked on the buffer, before removing the sink.
+        self.buffer.set_closing();
+        let result = {
+            let mut handle = self.handle.lock();
+            handle.take().map_or(Ok(()), |h| h.close().map(|_| ()))
+        };
+        self.buffer.set_finished();
+        result
+    }
+}
+
+/// Options for an [`McapStream`] attached to an existing [`Context`].
+///
+/// See [`attach_mcap_stream`].
+#[must_use]
+#[derive(Clone)]
+pub struct McapStreamOptions {
+    write_options: McapWriteOptions,
+    channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+    time_limit: Option<Duration>,
+    buffer_capacity: usize,
+    overflow_policy: OverflowPolicy,
+}
+
+impl Debug for McapStreamOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McapStreamOptions")
+            .field("write_options", &self.write_options)
+            .field("time_limit", &self.time_limit)
+            .field("buffer_capacity", &self.buffer_capacity)
+            .field("overflow_policy", &self.overflow_policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for McapStreamOptions {
+    fn default() -> Self {
+        Self {
+            write_options: McapWriteOptions::new(),
+            channel_filter: None,
+            time_limit: None,
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            overflow_policy: OverflowPolicy::DropNewest,
+        }
+    }
+}
+
+impl McapStreamOptions {
+    /// Creates new options, with a 4 MiB buffer, and no filter or time limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the MCAP write options.
+    ///
+    /// Seeking is always disabled, since the stream cannot seek.
+    pub fn write_options(mut self, options: McapWriteOptions) -> Self {
+        self.write_options = options;
+        self
+    }
+
+    /// Sets a [`SinkChannelFilter`] for the stream.
+    pub fn channel_filter(mut self, filter: Arc<dyn SinkChannelFilter>) -> Self {
+        self.channel_filter = Some(filter);
+        self
+    }
+
+    /// Sets a channel filter for the stream. See [`SinkChannelFilter`] for more information.
+    pub fn channel_filter_fn(
+        mut self,
+        filter: impl Fn(&ChannelDescriptor) -> bool + Sync + Send + 'static,
+    ) -> Self {
+        self.channel_filter = Some(Arc::new(SinkChannelFilterFn(filter)));
+        self
+    }
+
+    /// Ends the stream once `limit` has elapsed since it was attached.
+    ///
+    /// Messages logged after the limit are discarded. The next time the [`McapStream`] is polled,
+    /// the MCAP file is completed on a Tokio blocking thread, so the stream must be polled from a
+    /// Tokio runtime.
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Sets the approximate number of bytes that may be buffered before the [`McapStream`]
+    /// consumes them.
+    ///
+    /// Messages are encoded into chunks, which are written to the buffer as they fill up, so the
+    /// buffer may exceed the capacity by up to one chunk.
+    pub fn buffer_capacity(mut self, bytes: usize) -> Self {
+        self.buffer_capacity = bytes;
+        self
+    }
+
+    /// Sets the policy applied when a message is logged while the buffer is full.
+    ///
+    /// With [`OverflowPolicy::DropNewest`] (the default), the message is discarded, and counted
+    /// in the stream's [`SinkStats::dropped`][crate::SinkStats::dropped]. With
+    /// [`OverflowPolicy::Block`], the logging thread waits until the [`McapStream`] consumes the
+    /// buffer, which applies backpressure to every thread logging to the context, so a slow
+    /// consumer slows down logging.
+    ///
+    /// Data that has already been written to the buffer cannot be discarded, so
+    /// [`OverflowPolicy::DropOldest`] is not supported, and [`attach_mcap_stream`] returns an
+    /// error if it is set.
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+}
+
 /// Creates an [`McapStream`] and [`McapStreamHandle`] pair that can be used to encode logged
 /// messages as a [`futures::Stream`] of MCAP bytes.
 ///
 /// The returned [`McapStreamHandle`] can be used to create channels which will log messages to the
-/// [`McapStream`]. The handle must routinely called [`McapStreamHandle::flush`] to push bytes from
-/// the writer to the [`McapStream`]. When the recording is finished [`McapStreamHandle::close`]
-/// must be called to ensure that all bytes have been flushed to the [`McapStream`].
+/// [`McapStream`]. The handle should routinely call [`McapStreamHandle::flush`] to wait for the
+/// [`McapStream`] to consume the buffered bytes. When the recording is finished
+/// [`McapStreamHandle::close`] must be called to ensure that all bytes have been flushed to the
+/// [`McapStream`].
+///
+/// To stream data that is logged to an existing [`Context`], use [`attach_mcap_stream`].
 pub fn create_mcap_stream() -> (McapStreamHandle, McapStream) {
-    let buffer = SharedBuffer::default();
     let context = Context::new();
-
+    let buffer = SharedBuffer::default();
     let writer = context
         .mcap_writer_with_options(McapWriteOptions::new().disable_seeking(true))
         .create(buffer.clone())
         .expect("writer has valid configuration");
+    new_stream(context, buffer, writer, None, None)
+}
 
-    let (sender, receiver) = tokio::sync::mpsc::channel(1);
+/// Attaches an [`McapStream`] to an existing [`Context`], to stream the data that is logged to
+/// it as MCAP bytes.
+///
+/// The stream receives messages from every channel in the context, or from the channels selected
+/// by the [channel filter][McapStreamOptions::channel_filter]. Encoded data is buffered until the
+/// [`McapStream`] consumes it. When the buffer reaches its
+/// [capacity][McapStreamOptions::buffer_capacity], the
+/// [overflow policy][McapStreamOptions::overflow_policy] applies, so no manual flushing is
+/// required.
+///
+/// Returns an error if the overflow policy is [`OverflowPolicy::DropOldest`], which is not
+/// supported for streams.
+///
+/// The stream ends when [`McapStreamHandle::close`] is called, when the handle is dropped, or
+/// when the [time limit][McapStreamOptions::time_limit] elapses.
+///
+/// ```no_run
+/// use foxglove::stream::{attach_mcap_stream, McapStreamOptions};
+/// use foxglove::Context;
+/// use std::time::Duration;
+///
+/// let (handle, stream) = attach_mcap_stream(
+///     &Context::get_default(),
+///     McapStreamOptions::new()
+///         .channel_filter_fn(|channel| channel.topic().starts_with("/camera"))
+///         .time_limit(Duration::from_secs(30)),
+/// )
+/// .expect("failed to attach stream");
+/// // Return `stream` as the body of an HTTP response.
+/// ```
+pub fn attach_mcap_stream(
+    context: &Arc<Context>,
+    options: McapStreamOptions,
+) -> Result<(McapStreamHandle, McapStream), FoxgloveError> {
+    if options.overflow_policy == OverflowPolicy::DropOldest {
+        return Err(FoxgloveError::ValueError(
+            "OverflowPolicy::DropOldest is not supported for MCAP streams".to_string(),
+        ));
+    }
+    let buffer = SharedBuffer::default();
+    let deadline = options.time_limit.map(|limit| Instant::now() + limit);
+    let mut writer = context.mcap_writer_with_options(options.write_options.disable_seeking(true));
+    if let Some(filter) = options.channel_filter {
+        writer = writer.channel_filter(filter);
+    }
+    let writer = writer.create_wrapped(buffer.clone(), |inner| {
+        Arc::new(StreamSink {
+            inner,
+            buffer: buffer.clone(),
+            capacity: options.buffer_capacity,
+            policy: options.overflow_policy,
+            deadline,
+            dropped: AtomicU64::new(0),
+        })
+    })?;
+    Ok(new_stream(
+        context.clone(),
+        buffer,
+        writer,
+        Some(options.buffer_capacity),
+        deadline,
+    ))
+}
 
-    let handle = McapStreamHandle {
+fn new_stream(
+    context: Arc<Context>,
+    buffer: SharedBuffer,
+    handle: McapWriterHandle<SharedBuffer>,
+    capacity: Option<usize>,
+    deadline: Option<Instant>,
+) -> (McapStreamHandle, McapStream) {
+    let writer = Arc::new(StreamWriter {
+        handle: Mutex::new(Some(handle)),
         buffer,
-        writer: Some(writer),
-        sender,
+        capacity,
+    });
+    let handle = McapStreamHandle {
+        writer: writer.clone(),
         context,
     };
-
-    (handle, McapStream { receiver })
+    let stream = McapStream {
+        writer,
+        deadline,
+        sleep: None,
+    };
+    (handle, stream)
 }
 
 /// A handle to an MCAP stream writer.
 ///
-/// When this handle is dropped, the writer will unregister from the [`Context`] and stop logging
-/// events. It will attempt to flush any buffered data but may fail if the [`McapStream`] is
-/// currently full.
+/// When this handle is dropped, the writer will unregister from the [`Context`], stop logging
+/// events, and write the end of the MCAP file to the [`McapStream`].
 ///
-/// To ensure no data is lost, call the [`McapStreamHandle::close`] method instead of dropping.
+/// To wait until the [`McapStream`] has consumed all data, call the [`McapStreamHandle::close`]
+/// method instead of dropping.
 #[must_use]
-#[derive(Debug)]
 pub struct McapStreamHandle {
-    writer: Option<McapWriterHandle<SharedBuffer>>,
-    buffer: SharedBuffer,
-    sender: TokioSender<BytesMut>,
+    writer: Arc<StreamWriter>,
     context: Arc<Context>,
 }
 
+impl Debug for McapStreamHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McapStreamHandle")
+            .field("context", &self.context)
+            .finish_non_exhaustive()
+    }
+}
+
 impl McapStreamHandle {
-    /// Returns a channel builder for a channel in the stream writer.
+    /// Returns a channel builder for a channel in the stream writer's context.
     ///
     /// You should choose a unique topic name per channel for compatibility with the Foxglove app.
     pub fn channel_builder(&self, topic: impl Into<String>) -> ChannelBuilder {
         self.context.channel_builder(topic)
     }
 
-    /// Stop logging events and flush any buffered data.
+    /// Writes an attachment to the stream.
     ///
-    /// This method will return an error if the MCAP writer fails to finish or if the
-    /// [`McapStream`] has already been closed.
-    pub async fn close(mut self) -> Result<(), FoxgloveError> {
-        if let Some(writer) = self.writer.take() {
-            if let Err(e) = writer.close() {
-                // If an error occurred still flush the buffer. We'll likely get a truncated MCAP
-                // but anything that was successfully written will be there.
-                let _ = Self::flush_shared_buffer(&mut self.sender, &mut self.buffer).await;
-                return Err(e);
-            }
-        }
-
-        Ok(Self::flush_shared_buffer(&mut self.sender, &mut self.buffer).await?)
+    /// See [`McapWriterHandle::attach`].
+    pub fn attach(&self, attachment: &McapAttachment<'_>) -> Result<(), FoxgloveError> {
+        let handle = self.writer.handle.lock();
+        handle
+            .as_ref()
+            .ok_or(FoxgloveError::SinkClosed)?
+            .attach(attachment)
     }
 
-    async fn flush_shared_buffer(
-        sender: &mut TokioSender<BytesMut>,
-        buffer: &mut SharedBuffer,
-    ) -> io::Result<()> {
-        let bytes = {
-            let mut inner = buffer.0.lock();
-            inner.buffer.split()
-        };
-
-        if bytes.is_empty() {
-            return Ok(());
-        }
+    /// Writes an attachment to the stream, streaming `length` bytes of data from `reader`.
+    ///
+    /// See [`McapWriterHandle::attach_from_reader`]. For a stream created with
+    /// [`attach_mcap_stream`], the data is written no faster than the [`McapStream`]
+    /// consumes it, so this method blocks, and must not be called from the task that polls the
+    /// stream.
+    pub fn attach_from_reader(
+        &self,
+        header: McapAttachmentHeader,
+        length: u64,
+        reader: impl Read,
+    ) -> Result<(), FoxgloveError> {
+        self.writer.attach_from_reader(header, length, reader)
+    }
 
-        if sender.send(bytes).await.is_err() {
-            return Err(std::io::Error::other("McapStream channel was closed"));
-        }
+    /// Writes the file at `path` to the stream as an attachment, streaming its contents.
+    ///
+    /// See [`McapWriterHandle::attach_file`] and [`McapStreamHandle::attach_from_reader`].
+    pub fn attach_file(
+        &self,
+        path: impl AsRef<Path>,
+        media_type: &str,
+    ) -> Result<(), FoxgloveError> {
+        let (header, length, file) = crate::mcap_writer::open_attachment_file(
+            path.as_ref(),
+            media_type,
+            self.context.now(),
+        )?;
+        self.writer.attach_from_reader(header, length, file)
+    }
 
-        Ok(())
+    /// Stop logging events, and wait until the [`McapStream`] has consumed all buffered data.
+    ///
+    /// This method will return an error if the MCAP writer fails to finish or if the
+    /// [`McapStream`] was closed before it consumed all data.
+    pub async fn close(self) -> Result<(), FoxgloveError> {
+        let result = self.writer.finish();
+        // If an error occurred still drain the buffer. We'll likely get a truncated MCAP but
+        // anything that was successfully written will be there.
+        let drained = self.writer.buffer.drained().await;
+        result.and(drained)
     }
 
     /// Get the current size of the buffer.
@@ -152,43 +615,32 @@ impl McapStreamHandle {
     /// This can be used in conjunction with [`McapStreamHandle::flush`] to ensure the buffer does
     /// not grow unbounded.
     pub fn buffer_size(&mut self) -> usize {
-        self.buffer.0.lock().buffer.len()
+        self.writer.buffer.0.inner.lock().buffer.len()
     }
 
-    /// Flush the buffer from the MCAP writer to the [`McapStream`].
+    /// Wait until the [`McapStream`] has consumed the buffered data.
     ///
-    /// This method returns a future that will wait until the [`McapStream`] has capacity for the
-    /// flushed buffer.
+    /// This method returns an error if the [`McapStream`] was closed before it consumed the
+    /// buffered data.
     pub async fn flush(&mut self) -> Result<(), FoxgloveError> {
-        Self::flush_shared_buffer(&mut self.sender, &mut self.buffer).await?;
-        Ok(())
+        self.writer.buffer.drained().await
     }
 }
 
 impl Drop for McapStreamHandle {
     fn drop(&mut self) {
-        if let Some(writer) = self.writer.take() {
-            if let Err(e) = writer.close() {
-                tracing::warn!("{e}");
-            }
-        }
-
-        let mut inner = self.buffer.0.lock();
-        let buffer = inner.buffer.split();
-
-        if !buffer.is_empty() {
-            // When the handle is dropped try and send the final buffer. If the channel is full or
-            // closed log as a warning.
-            if let Err(e) = self.sender.try_send(buffer) {
-                tracing::warn!("{e}");
-            }
+        if let Err(e) = self.writer.finish() {
+            tracing::warn!("{e}");
         }
     }
 }
 
 /// A stream of MCAP bytes from a writer.
 pub struct McapStream {
-    receiver: TokioReceiver<BytesMut>,
+    writer: Arc<StreamWriter>,
+    deadline: Option<Instant>,
+    /// The timer for the deadline, which is created when the stream is first polled.
+    sleep: Option<Pin<Box<tokio::time::Sleep>>>,
 }
 
 impl Stream for McapStream {
@@ -198,11 +650,30 @@ impl Stream for McapStream {
         mut self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        let Some(bytes) = ready!(self.receiver.poll_recv(cx)) else {
-            return Poll::Ready(None);
-        };
+        if let Some(deadline) = self.deadline {
+            let sleep = self
+                .sleep
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline.into())));
+            if sleep.as_mut().poll(cx).is_ready() {
+                self.deadline = None;
+                self.sleep = None;
+                // Finishing the writer blocks, so do it off the executor. The stream ends once the
+                // end of the file has been written to the buffer.
+                let writer = self.writer.clone();
+                tokio::task::spawn_blocking(move || {
+                    if let Err(e) = writer.finish() {
+                        tracing::warn!("Failed to finish MCAP stream: {e}");
+                    }
+                });
+            }
+        }
+        self.writer.buffer.poll_read(cx)
+    }
+}
 
-        Poll::Ready(Some(bytes.freeze()))
+impl Drop for McapStream {
+    fn drop(&mut self) {
+        self.writer.buffer.set_stream_closed();
     }
 }
 
@@ -269,4 +740,133 @@ mod tests {
         assert_eq!(stats.message_count, 100);
         assert_eq!(stats.channel_count, 1);
     }
+
+    async fn read_stream(mut stream: McapStream) -> mcap::Summary {
+        let mut mcap_bytes = vec![];
+        while let Some(bytes) = stream.next().await {
+            mcap_bytes.extend_from_slice(&bytes[..]);
+        }
+        mcap::Summary::read(&mcap_bytes[..]).unwrap().unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_attach_with_filter_and_backpressure() {
+        let ctx = Context::new();
+        let a = ctx.channel_builder("/a").build::<Message>();
+        let b = ctx.channel_builder("/b").build::<Message>();
+
+        let (handle, stream) = attach_mcap_stream(
+            &ctx,
+            McapStreamOptions::new()
+                .write_options(McapWriteOptions::new().chunk_size(Some(64)))
+                .channel_filter_fn(|channel| channel.topic() == "/a")
+                .buffer_capacity(1)
+                .overflow_policy(OverflowPolicy::Block),
+        )
+        .unwrap();
+
+        // Logging blocks until the stream consumes the buffer, so log from a blocking thread.
+        let logger = tokio::task::spawn_blocking(move || {
+            for i in 0..100 {
+                a.log(&Message { data: i as f64 });
+                b.log(&Message { data: i as f64 });
+            }
+        });
+        let reader = tokio::spawn(read_stream(stream));
+        logger.await.unwrap();
+        handle.close().await.unwrap();
+
+        let summary = reader.await.unwrap();
+        let stats = summary.stats.unwrap();
+        assert_eq!(stats.message_count, 100);
+        assert_eq!(stats.channel_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_attach_drop_when_full() {
+        let ctx = Context::new();
+        ctx.enable_sink_stats();
+        let channel = ctx.channel_builder("/a").build::<Message>();
+
+        let (handle, stream) = attach_mcap_stream(
+            &ctx,
+            McapStreamOptions::new()
+                .write_options(McapWriteOptions::new().chunk_size(Some(64)))
+                .buffer_capacity(256),
+        )
+        .unwrap();
+
+        // Nothing consumes the stream, so messages are dropped once the buffer is full, which is
+        // the default policy.
+        for i in 0..100 {
+            channel.log(&Message { data: i as f64 });
+        }
+        let sinks = ctx.stats().sinks;
+        assert_eq!(sinks.len(), 1);
+        let dropped = sinks.values().next().unwrap().dropped;
+        let reader = tokio::spawn(read_stream(stream));
+        handle.close().await.unwrap();
+
+        let stats = reader.await.unwrap().stats.unwrap();
+        assert!(stats.message_count > 0 && stats.message_count < 100);
+        assert_eq!(stats.message_count + dropped, 100);
+    }
+
+    #[test]
+    fn test_attach_drop_oldest_unsupported() {
+        let ctx = Context::new();
+        let result = attach_mcap_stream(
+            &ctx,
+            McapStreamOptions::new().overflow_policy(OverflowPolicy::DropOldest),
+        );
+        assert!(matches!(result, Err(FoxgloveError::ValueError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_attach_time_limit() {
+        let ctx = Context::new();
+        let channel = ctx.channel_builder("/a").build::<Message>();
+
+        let (handle, stream) = attach_mcap_stream(
+            &ctx,
+            McapStreamOptions::new().time_limit(Duration::from_millis(50)),
+        )
+        .unwrap();
+        channel.log(&Message { data: 1.0 });
+
+        // The stream ends by itself once the time limit elapses.
+        let summary = read_stream(stream).await;
+        channel.log(&Message { data: 2.0 });
+        assert_eq!(summary.stats.unwrap().message_count, 1);
+        handle.close().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_attach_file_to_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calibration.bin");
+        std::fs::write(&path, vec![7u8; 300_000]).unwrap();
+
+        let ctx = Context::new();
+        let (handle, stream) =
+            attach_mcap_stream(&ctx, McapStreamOptions::new().buffer_capacity(1024)).unwrap();
+        let reader = tokio::spawn(read_stream(stream));
+
+        // The attachment is read no faster than the stream consumes it.
+        let handle = tokio::task::spawn_blocking(move || {
+            handle
+                .attach_file(&path, "application/octet-stream")
+                .unwrap();
+            handle
+        })
+        .await
+        .unwrap();
+        handle.close().await.unwrap();
+
+        let summary = reader.await.unwrap();
+        assert_eq!(summary.stats.unwrap().attachment_count, 1);
+        let index = &summary.attachment_indexes[0];
+        assert_eq!(index.name, "calibration.bin");
+        assert_eq!(index.data_size, 300_000);
+    }
 }
@@ -92,7 +92,7 @@ async fn test_logging_to_file_and_live_sinks() {
 
         channel.log(&msg);
 
-        let writer = handle.close().expect("Failed to flush log");
+        let (writer, _) = handle.close().expect("Failed to flush log");
         file = writer
             .into_inner()
             .expect("Failed to get tempfile from bufwriter");
@@ -75,7 +75,7 @@ async fn test_sink_channel_filtering_on_mcap_and_ws() {
     assert_eq!(msg.subscription_id, subscription_id);
 
     // MCAP received a message on /1
-    let writer = mcap.close().expect("Failed to close writer");
+    let (writer, _) = mcap.close().expect("Failed to close writer");
     let file = writer.into_inner().expect("Failed to get tempfile");
     let summary = read_summary(file.path());
     assert_eq!(summary.channels.len(), 1);
@@ -84,3 +84,70 @@ async fn test_sink_channel_filtering_on_mcap_and_ws() {
         "/1"
     );
 }
+
+#[cfg(feature = "live_visualization")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_latched_replay_to_mcap_and_ws() {
+    use crate::{PartialMetadata, WebSocketClient};
+
+    let ctx = Context::new();
+    let ch = ChannelBuilder::new("/latched")
+        .context(&ctx)
+        .message_encoding("raw")
+        .latched(2)
+        .build_raw()
+        .unwrap();
+    ch.log_with_meta(b"1", PartialMetadata::with_log_time(1));
+    ch.log_with_meta(b"2", PartialMetadata::with_log_time(2));
+    ch.log_with_meta(b"3", PartialMetadata::with_log_time(3));
+
+    // A writer added mid-run receives the retained messages.
+    let file = NamedTempFile::new().unwrap();
+    let mcap = McapWriter::new()
+        .context(&ctx)
+        .create(BufWriter::new(file))
+        .unwrap();
+
+    // So does a WebSocket client that subscribes later.
+    let server = WebSocketServer::new()
+        .bind("127.0.0.1", 0)
+        .context(&ctx)
+        .start()
+        .await
+        .expect("Failed to start server");
+    let mut client = WebSocketClient::connect(format!("127.0.0.1:{}", server.port()))
+        .await
+        .expect("failed to connect");
+    expect_recv!(client, ServerMessage::ServerInfo);
+    expect_recv!(client, ServerMessage::Advertise);
+    let subscribe_msg = Subscribe::new([Subscription {
+        id: 1,
+        channel_id: ch.id().into(),
+    }]);
+    client.send(&subscribe_msg).await.expect("Failed to send");
+    for (data, log_time) in [(b"2", 2), (b"3", 3)] {
+        let msg = expect_recv!(client, ServerMessage::MessageData);
+        assert_eq!(msg.subscription_id, 1);
+        assert_eq!(msg.log_time, log_time);
+        assert_eq!(msg.data.as_ref(), data);
+    }
+
+    ch.log_with_meta(b"4", PartialMetadata::with_log_time(4));
+    let msg = expect_recv!(client, ServerMessage::MessageData);
+    assert_eq!(msg.data.as_ref(), b"4");
+
+    let (writer, _) = mcap.close().expect("Failed to close writer");
+    let file = writer.into_inner().expect("Failed to get tempfile");
+    let contents = std::fs::read(file.path()).unwrap();
+    let messages: Vec<_> = mcap::MessageStream::new(&contents)
+        .unwrap()
+        .map(|m| m.unwrap())
+        .map(|m| (m.data.to_vec(), m.log_time))
+        .collect();
+    assert_eq!(
+        messages,
+        vec![(b"2".to_vec(), 2), (b"3".to_vec(), 3), (b"4".to_vec(), 4)]
+    );
+
+    server.stop().wait().await;
+}
@@ -10,4 +10,4 @@ pub use global_context::GlobalContextTest;
 pub(crate) use mcap::read_summary;
 pub use sink::{ErrorSink, MockSink, RecordingSink};
 #[cfg(feature = "live_visualization")]
-pub(crate) use websocket::{assert_eventually, RecordingServerListener};
+pub(crate) use websocket::{assert_eventually, assert_eventually_within, RecordingServerListener};
@@ -258,7 +258,14 @@ impl ServerListener for RecordingServerListener {
 /// assert_eventually(|| dbg!(x.len()) == 2);
 /// ```
 pub async fn assert_eventually(cond: impl Fn() -> bool) {
-    let timeout = Duration::from_millis(50);
+    assert_eventually_within(Duration::from_millis(50), cond).await;
+}
+
+/// Asserts that `cond` returns true within `timeout`, polling every 1ms.
+///
+/// This is for conditions that are expected to take longer than [`assert_eventually`] allows,
+/// such as reconnecting after a delay.
+pub async fn assert_eventually_within(timeout: Duration, cond: impl Fn() -> bool) {
     let poll_interval = Duration::from_millis(1);
     let result = tokio::time::timeout(timeout, async {
         while !cond() {
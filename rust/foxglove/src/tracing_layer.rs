@@ -0,0 +1,269 @@
+//! A [`tracing_subscriber::Layer`] that logs events to a Foxglove channel.
+
+use std::cell::Cell;
+use std::fmt::{Debug, Write};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context as LayerContext;
+use tracing_subscriber::Layer;
+
+use crate::clock::ClockSource;
+use crate::convert::SaturatingFrom;
+use crate::schemas::{log::Level as LogLevel, Log, Timestamp};
+use crate::{Channel, Context, PartialMetadata};
+
+/// The default topic for the log channel.
+const DEFAULT_TOPIC: &str = "/log";
+
+thread_local! {
+    /// Set while the layer is logging an event, to avoid recursing on events emitted by sinks.
+    static IN_LAYER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Clears [`IN_LAYER`] when dropped, even if logging the event panics.
+struct InLayerGuard;
+
+impl InLayerGuard {
+    fn enter() -> Self {
+        IN_LAYER.set(true);
+        Self
+    }
+}
+
+impl Drop for InLayerGuard {
+    fn drop(&mut self) {
+        IN_LAYER.set(false);
+    }
+}
+
+/// A builder for a [`LogLayer`].
+#[must_use]
+pub struct LogLayerBuilder {
+    context: Arc<Context>,
+    topic: String,
+    #[cfg(feature = "live_visualization")]
+    status_server: Option<std::sync::Weak<crate::websocket::Server>>,
+}
+
+impl Debug for LogLayerBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogLayerBuilder")
+            .field("context", &self.context)
+            .field("topic", &self.topic)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LogLayerBuilder {
+    /// Sets the context for the log channel.
+    pub fn context(mut self, ctx: &Arc<Context>) -> Self {
+        self.context = ctx.clone();
+        self
+    }
+
+    /// Sets the topic for the log channel.
+    ///
+    /// The default is `/log`.
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = topic.into();
+        self
+    }
+
+    /// Also forwards WARN and ERROR events to clients of a WebSocket server, with
+    /// [`WebSocketServerHandle::publish_status`][crate::WebSocketServerHandle::publish_status].
+    ///
+    /// The layer does not keep the server alive.
+    #[cfg(feature = "live_visualization")]
+    pub fn publish_status(mut self, server: &crate::WebSocketServerHandle) -> Self {
+        self.status_server = Some(server.weak_server());
+        self
+    }
+
+    /// Creates the log channel and returns the layer.
+    pub fn build(self) -> LogLayer {
+        LogLayer {
+            channel: self.context.channel_builder(self.topic).build(),
+            clock: self.context.clock_source().clone(),
+            #[cfg(feature = "live_visualization")]
+            status_server: self.status_server,
+        }
+    }
+}
+
+/// A [`tracing_subscriber::Layer`] that logs [`tracing`] events as [`Log`] messages.
+///
+/// Each event is converted to a [`Log`] message, with the event's target as the `name`, its
+/// source location as the `file` and `line`, and its fields formatted into the `message`. TRACE
+/// and DEBUG events are logged at [`Debug`][LogLevel::Debug] level. Events are timestamped with the
+/// context's [clock][Context::clock].
+///
+/// Events emitted while the layer is logging, such as warnings from a sink, are ignored.
+///
+/// This type is only available with the `tracing-subscriber` feature.
+///
+/// # Example
+/// ```
+/// use tracing_subscriber::prelude::*;
+///
+/// let ctx = foxglove::Context::new();
+/// tracing_subscriber::registry()
+///     .with(foxglove::LogLayer::builder().context(&ctx).build())
+///     .init();
+///
+/// tracing::info!(answer = 42, "hello");
+/// ```
+pub struct LogLayer {
+    channel: Channel<Log>,
+    clock: Arc<ClockSource>,
+    #[cfg(feature = "live_visualization")]
+    status_server: Option<std::sync::Weak<crate::websocket::Server>>,
+}
+
+impl Debug for LogLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogLayer")
+            .field("channel", &self.channel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LogLayer {
+    /// Returns a builder for a log layer.
+    pub fn builder() -> LogLayerBuilder {
+        LogLayerBuilder {
+            context: Context::get_default(),
+            topic: DEFAULT_TOPIC.to_string(),
+            #[cfg(feature = "live_visualization")]
+            status_server: None,
+        }
+    }
+
+    /// Returns the channel on which events are logged.
+    pub fn channel(&self) -> &Channel<Log> {
+        &self.channel
+    }
+
+    #[cfg(feature = "live_visualization")]
+    fn publish_status(&self, level: tracing::Level, message: &str) {
+        use crate::websocket::Status;
+
+        let Some(server) = self.status_server.as_ref().and_then(|s| s.upgrade()) else {
+            return;
+        };
+        let status = match level {
+            tracing::Level::ERROR => Status::error(message),
+            tracing::Level::WARN => Status::warning(message),
+            _ => return,
+        };
+        server.publish_status(status);
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        if IN_LAYER.get() {
+            return;
+        }
+        #[cfg(feature = "live_visualization")]
+        let has_status = self.status_server.is_some();
+        #[cfg(not(feature = "live_visualization"))]
+        let has_status = false;
+        if !has_status && !self.channel.has_sinks() {
+            return;
+        }
+
+        let _guard = InLayerGuard::enter();
+        let now = self.clock.now();
+        let metadata = event.metadata();
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let log = Log {
+            timestamp: Some(Timestamp::saturating_from(
+                UNIX_EPOCH + Duration::from_nanos(now),
+            )),
+            level: log_level(*metadata.level()) as i32,
+            message: visitor.message,
+            name: metadata.target().to_string(),
+            file: metadata.file().unwrap_or_default().to_string(),
+            line: metadata.line().unwrap_or_default(),
+        };
+        #[cfg(feature = "live_visualization")]
+        self.publish_status(*metadata.level(), &log.message);
+        self.channel
+            .log_with_meta(&log, PartialMetadata::with_log_time(now));
+    }
+}
+
+/// Maps a tracing level to a log level.
+fn log_level(level: tracing::Level) -> LogLevel {
+    match level {
+        tracing::Level::ERROR => LogLevel::Error,
+        tracing::Level::WARN => LogLevel::Warning,
+        tracing::Level::INFO => LogLevel::Info,
+        tracing::Level::DEBUG | tracing::Level::TRACE => LogLevel::Debug,
+    }
+}
+
+/// Formats an event's fields as `message key=value ...`.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.record_debug(field, &format_args!("{value}"));
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if !self.message.is_empty() {
+            self.message.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.message, "{}={value:?}", field.name());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_log_layer() {
+        let ctx = Context::new();
+        ctx.set_clock(Arc::new(crate::ManualClock::new(1_500_000_000u64)));
+        let receiver = ctx.subscribe::<Log>("/tracing");
+        let layer = LogLayer::builder().context(&ctx).topic("/tracing").build();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "my_target", count = 3, name = "x", "something {}", "happened");
+            tracing::trace!("detail");
+        });
+
+        let (log, metadata) = receiver.try_recv().unwrap();
+        assert_eq!(log.timestamp, Some(Timestamp::new(1, 500_000_000)));
+        assert_eq!(metadata.log_time, 1_500_000_000);
+        assert_eq!(log.level, LogLevel::Warning as i32);
+        assert_eq!(log.name, "my_target");
+        assert_eq!(log.message, r#"something happened count=3 name="x""#);
+        assert!(log.file.ends_with("tracing_layer.rs"));
+        assert_ne!(log.line, 0);
+
+        let (log, _) = receiver.try_recv().unwrap();
+        assert_eq!(log.level, LogLevel::Debug as i32);
+        assert_eq!(log.message, "detail");
+        assert!(receiver.try_recv().is_none());
+    }
+}
@@ -1,6 +1,7 @@
 //! Websocket functionality
 
 mod advertise;
+mod authorization;
 mod capability;
 mod channel_view;
 mod client;
@@ -21,6 +22,10 @@ mod tests;
 #[doc(hidden)]
 pub mod ws_protocol;
 
+pub use authorization::{
+    AuthorizationError, ClientPermissions, ConnectionAuthorizer, ConnectionRequest,
+};
+pub(crate) use authorization::{ConnectionAuthorizerFn, SUBSCRIBE_DENIED, WRITE_PARAMETERS_DENIED};
 pub use capability::Capability;
 pub use channel_view::ChannelView;
 pub use client::{Client, ClientId};
@@ -31,7 +36,7 @@ pub(crate) use fetch_asset::{AsyncAssetHandlerFn, BlockingAssetHandlerFn};
 pub use server::ShutdownHandle;
 pub(crate) use server::{create_server, Server, ServerOptions};
 pub use server_listener::ServerListener;
-pub use streams::TlsIdentity;
+pub use streams::{TlsClientAuth, TlsIdentity};
 #[doc(hidden)]
 pub use ws_protocol::client::{PlaybackCommand, PlaybackControlRequest};
 pub use ws_protocol::parameter::{
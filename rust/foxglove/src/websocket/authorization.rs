@@ -0,0 +1,343 @@
+//! Per-connection authorization for the websocket server.
+
+use std::net::SocketAddr;
+
+use tokio_tungstenite::tungstenite::handshake::server;
+
+/// The HTTP upgrade request of a client connecting to the websocket server.
+///
+/// This is provided to [`ConnectionAuthorizer::authorize`].
+#[derive(Debug)]
+pub struct ConnectionRequest<'a> {
+    request: &'a server::Request,
+    peer_addr: SocketAddr,
+    peer_subject: Option<&'a str>,
+}
+
+impl<'a> ConnectionRequest<'a> {
+    pub(crate) fn new(
+        request: &'a server::Request,
+        peer_addr: SocketAddr,
+        peer_subject: Option<&'a str>,
+    ) -> Self {
+        Self {
+            request,
+            peer_addr,
+            peer_subject,
+        }
+    }
+
+    /// Returns the address of the connecting client.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Returns the subject of the client's verified TLS certificate, if it presented one.
+    ///
+    /// This is only available if the server was configured to verify client certificates.
+    pub fn peer_certificate_subject(&self) -> Option<&str> {
+        self.peer_subject
+    }
+
+    /// Returns the path of the request URI.
+    pub fn path(&self) -> &str {
+        self.request.uri().path()
+    }
+
+    /// Returns the raw query string of the request URI, if there is one.
+    pub fn query(&self) -> Option<&str> {
+        self.request.uri().query()
+    }
+
+    /// Returns the percent-decoded value of the first query parameter with the given name.
+    ///
+    /// For example, a client connecting to `ws://host:8765/?token=abc` would have a `token` query
+    /// parameter with value `abc`.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query()?.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (urlencoding::decode(key).ok()? == name)
+                .then(|| urlencoding::decode(value).ok().map(|v| v.into_owned()))
+                .flatten()
+        })
+    }
+
+    /// Returns the value of the first header with the given name.
+    ///
+    /// Header names are case-insensitive. Returns `None` if the header is not present, or if its
+    /// value is not valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.request.headers().get(name)?.to_str().ok()
+    }
+
+    /// Returns the values of all headers with the given name.
+    ///
+    /// Values which are not valid UTF-8 are omitted.
+    pub fn header_values(&self, name: &str) -> Vec<&str> {
+        self.request
+            .headers()
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .collect()
+    }
+
+    /// Returns the bearer token from the `Authorization` header, if there is one.
+    pub fn bearer_token(&self) -> Option<&str> {
+        let value = self.header("authorization")?;
+        let (scheme, token) = value.split_once(' ')?;
+        scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+    }
+}
+
+/// An error returned by a [`ConnectionAuthorizer`] to reject a connection.
+///
+/// The client's upgrade request is answered with the HTTP status code and message.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message} ({status})")]
+pub struct AuthorizationError {
+    status: u16,
+    message: String,
+}
+
+impl AuthorizationError {
+    /// Rejects the connection with a `401 Unauthorized` response.
+    ///
+    /// Use this when the client did not provide valid credentials.
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self {
+            status: 401,
+            message: message.into(),
+        }
+    }
+
+    /// Rejects the connection with a `403 Forbidden` response.
+    ///
+    /// Use this when the client's credentials are valid, but do not permit access to the server.
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: 403,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the HTTP status code of the response.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Returns the message included in the response body.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// A set of names that a client may access.
+///
+/// Each pattern is either an exact name, or a prefix followed by `*`.
+#[derive(Debug, Clone)]
+enum Allowlist {
+    All,
+    Only(Vec<String>),
+}
+
+impl Allowlist {
+    fn only(patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self::Only(patterns.into_iter().map(Into::into).collect())
+    }
+
+    fn allows(&self, name: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(patterns) => patterns.iter().any(|p| match p.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => p == name,
+            }),
+        }
+    }
+}
+
+/// Prefix of the error status sent when a client may not subscribe to a topic.
+pub(crate) const SUBSCRIBE_DENIED: &str = "Not permitted to subscribe to topic: ";
+
+/// The error status sent when a client may not write parameters.
+pub(crate) const WRITE_PARAMETERS_DENIED: &str = "Not permitted to set parameters";
+
+/// Permissions granted to a connected client by a [`ConnectionAuthorizer`].
+///
+/// By default, a client is permitted to do everything that the server supports. Restrictions on
+/// topics and services are expressed as lists of patterns, where each pattern is either an exact
+/// name, or a prefix followed by `*`. For example, `/camera/*` matches `/camera/front` and
+/// `/camera/rear/image`.
+///
+/// Requests which are not permitted are answered with an error [`Status`][super::Status].
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct ClientPermissions {
+    subscribe: Allowlist,
+    publish: Allowlist,
+    services: Allowlist,
+    write_parameters: bool,
+}
+
+impl ClientPermissions {
+    /// Returns permissions which allow everything that the server supports.
+    pub fn all() -> Self {
+        Self {
+            subscribe: Allowlist::All,
+            publish: Allowlist::All,
+            services: Allowlist::All,
+            write_parameters: true,
+        }
+    }
+
+    /// Returns permissions which only allow the client to observe the server.
+    ///
+    /// The client will still receive channel and service advertisements and may read parameters,
+    /// but cannot subscribe, publish, call services, or write parameters.
+    pub fn read_only() -> Self {
+        Self {
+            subscribe: Allowlist::Only(vec![]),
+            publish: Allowlist::Only(vec![]),
+            services: Allowlist::Only(vec![]),
+            write_parameters: false,
+        }
+    }
+
+    /// Restricts the topics that the client may subscribe to.
+    pub fn subscribe_topics(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.subscribe = Allowlist::only(patterns);
+        self
+    }
+
+    /// Restricts the topics that the client may publish to.
+    ///
+    /// This requires the [`ClientPublish`][super::Capability::ClientPublish] capability. Client
+    /// channels on other topics are rejected when they are advertised.
+    pub fn publish_topics(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.publish = Allowlist::only(patterns);
+        self
+    }
+
+    /// Restricts the services that the client may call.
+    pub fn services(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.services = Allowlist::only(patterns);
+        self
+    }
+
+    /// Sets whether the client may write parameters.
+    pub fn write_parameters(mut self, allow: bool) -> Self {
+        self.write_parameters = allow;
+        self
+    }
+
+    /// Returns true if the client may subscribe to the topic.
+    pub fn can_subscribe(&self, topic: &str) -> bool {
+        self.subscribe.allows(topic)
+    }
+
+    /// Returns true if the client may publish to the topic.
+    pub fn can_publish(&self, topic: &str) -> bool {
+        self.publish.allows(topic)
+    }
+
+    /// Returns true if the client may call the service.
+    pub fn can_call_service(&self, name: &str) -> bool {
+        self.services.allows(name)
+    }
+
+    /// Returns true if the client may write parameters.
+    pub fn can_write_parameters(&self) -> bool {
+        self.write_parameters
+    }
+}
+
+impl Default for ClientPermissions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Decides whether a client may connect to the websocket server, and what it may do.
+///
+/// The authorizer is consulted during the websocket handshake, before the client is registered
+/// with the server. It is invoked from the server's connection task and must not block.
+pub trait ConnectionAuthorizer: Send + Sync {
+    /// Returns the permissions for the client, or an error to reject the connection.
+    fn authorize(
+        &self,
+        request: &ConnectionRequest<'_>,
+    ) -> Result<ClientPermissions, AuthorizationError>;
+}
+
+pub(crate) struct ConnectionAuthorizerFn<F>(pub F)
+where
+    F: Fn(&ConnectionRequest<'_>) -> Result<ClientPermissions, AuthorizationError> + Send + Sync;
+
+impl<F> ConnectionAuthorizer for ConnectionAuthorizerFn<F>
+where
+    F: Fn(&ConnectionRequest<'_>) -> Result<ClientPermissions, AuthorizationError> + Send + Sync,
+{
+    fn authorize(
+        &self,
+        request: &ConnectionRequest<'_>,
+    ) -> Result<ClientPermissions, AuthorizationError> {
+        self.0(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_request() {
+        let request = server::Request::builder()
+            .uri("/ws?token=a%20b&empty&x=1")
+            .header("Authorization", "Bearer secret")
+            .header("X-Role", "viewer")
+            .header("X-Role", "admin")
+            .body(())
+            .unwrap();
+        let addr = "127.0.0.1:1234".parse().unwrap();
+        let req = ConnectionRequest::new(&request, addr, Some("CN=client"));
+        assert_eq!(req.peer_addr(), addr);
+        assert_eq!(req.peer_certificate_subject(), Some("CN=client"));
+        assert_eq!(req.path(), "/ws");
+        assert_eq!(req.query_param("token").as_deref(), Some("a b"));
+        assert_eq!(req.query_param("empty").as_deref(), Some(""));
+        assert_eq!(req.query_param("missing"), None);
+        assert_eq!(req.bearer_token(), Some("secret"));
+        assert_eq!(req.header("x-role"), Some("viewer"));
+        assert_eq!(req.header_values("x-role"), vec!["viewer", "admin"]);
+    }
+
+    #[test]
+    fn test_client_permissions() {
+        let all = ClientPermissions::all();
+        assert!(all.can_subscribe("/a"));
+        assert!(all.can_publish("/a"));
+        assert!(all.can_call_service("svc"));
+        assert!(all.can_write_parameters());
+
+        let read_only = ClientPermissions::read_only();
+        assert!(!read_only.can_subscribe("/a"));
+        assert!(!read_only.can_publish("/a"));
+        assert!(!read_only.can_call_service("svc"));
+        assert!(!read_only.can_write_parameters());
+
+        let perms = ClientPermissions::all()
+            .subscribe_topics(["/camera/*", "/tf"])
+            .services(["get_*"]);
+        assert!(perms.can_subscribe("/camera/front"));
+        assert!(perms.can_subscribe("/tf"));
+        assert!(!perms.can_subscribe("/tf_static"));
+        assert!(!perms.can_subscribe("/lidar"));
+        assert!(perms.can_publish("/anything"));
+        assert!(perms.can_call_service("get_map"));
+        assert!(!perms.can_call_service("set_map"));
+    }
+}
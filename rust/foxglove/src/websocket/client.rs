@@ -56,6 +56,16 @@ impl Client {
         self.client.upgrade().map(|client| client.sink_id())
     }
 
+    /// Returns the subject of the client's verified TLS certificate, if it presented one.
+    ///
+    /// This is only available if the server was configured to verify client certificates. Returns
+    /// `None` if the client is disconnected.
+    pub fn peer_certificate_subject(&self) -> Option<String> {
+        self.client
+            .upgrade()
+            .and_then(|client| client.peer_certificate_subject().map(String::from))
+    }
+
     /// Send a status message to this client. Does nothing if client is disconnected.
     pub fn send_status(&self, status: Status) {
         if let Some(client) = self.client.upgrade() {
@@ -1,5 +1,6 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Weak;
 use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 
@@ -11,7 +12,9 @@ use tokio::sync::oneshot;
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::WebSocketStream;
 
+use crate::protocol::v2;
 use crate::sink_channel_filter::SinkChannelFilter;
+use crate::sink_message_filter::SinkMessageFilter;
 use crate::websocket::streams::ServerStream;
 use crate::websocket::PlaybackControlRequest;
 use crate::{ChannelId, Context, FoxgloveError, Metadata, RawChannel, Sink, SinkId};
@@ -20,6 +23,8 @@ use self::ws_protocol::server::{
     FetchAssetResponse, ParameterValues, ServiceCallFailure, Unadvertise,
 };
 
+use super::authorization::ClientPermissions;
+use super::handshake::ProtocolVersion;
 use super::semaphore::Semaphore;
 use super::server::Server;
 use super::service::{self, CallId, ServiceId};
@@ -29,7 +34,7 @@ use super::ws_protocol::server::MessageData;
 use super::ws_protocol::{self, ParseError};
 use super::{
     advertise, AssetResponder, Capability, Client, ClientChannel, ClientChannelId, ClientId,
-    Parameter, Status, StatusLevel,
+    Parameter, Status, StatusLevel, SUBSCRIBE_DENIED, WRITE_PARAMETERS_DENIED,
 };
 
 mod poller;
@@ -60,6 +65,13 @@ pub(super) struct ConnectedClient {
     weak_self: Weak<Self>,
     sink_id: SinkId,
     channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+    message_filter: Option<Arc<dyn SinkMessageFilter>>,
+    /// Protocol version negotiated during the handshake.
+    protocol: ProtocolVersion,
+    /// Permissions granted to the client during the handshake.
+    permissions: ClientPermissions,
+    /// Subject of the client's verified TLS certificate, if it presented one.
+    peer_subject: Option<String>,
     context: Weak<Context>,
     poller: parking_lot::Mutex<Option<Poller>>,
     /// A cache of channels for `on_subscribe` and `on_unsubscribe` callbacks.
@@ -68,6 +80,8 @@ pub(super) struct ConnectedClient {
     data_plane_rx: flume::Receiver<Message>,
     control_plane_tx: flume::Sender<Message>,
     service_call_sem: Semaphore,
+    /// Count of data plane messages dropped due to backpressure.
+    dropped: AtomicU64,
     fetch_asset_sem: Semaphore,
     /// Subscriptions from this client
     subscriptions: parking_lot::Mutex<BiHashMap<ChannelId, SubscriptionId>>,
@@ -101,9 +115,37 @@ impl Sink for ConnectedClient {
         let Some(subscription_id) = subscriptions.get_by_left(&channel.id()).copied() else {
             return Ok(());
         };
+        drop(subscriptions);
 
-        let message = MessageData::new(subscription_id.into(), metadata.log_time, msg);
-        self.send_data_lossy(&message, MAX_SEND_RETRIES);
+        if let Some(filter) = &self.message_filter {
+            if !filter.should_log(channel, msg, metadata) {
+                return Ok(());
+            }
+        }
+
+        let message = match self.protocol {
+            ProtocolVersion::V1 => Message::from(&MessageData::new(
+                subscription_id.into(),
+                metadata.log_time,
+                msg,
+            )),
+            ProtocolVersion::V2 => Message::from(&v2::server::MessageData::new(
+                subscription_id.into(),
+                metadata.log_time,
+                msg,
+            )),
+        };
+        match self.send_data_lossy(message, MAX_SEND_RETRIES) {
+            SendLossyResult::Sent => (),
+            SendLossyResult::SentLossy(dropped) => {
+                self.dropped.fetch_add(dropped as u64, Ordering::Relaxed);
+            }
+            // The oldest queued messages were dropped, and so was this one.
+            SendLossyResult::ExhaustedRetries => {
+                self.dropped
+                    .fetch_add(MAX_SEND_RETRIES as u64 + 1, Ordering::Relaxed);
+            }
+        }
         Ok(())
     }
 
@@ -134,9 +176,14 @@ impl Sink for ConnectedClient {
         // Clients maintain subscriptions dynamically.
         false
     }
+
+    fn dropped_messages(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
 }
 
 impl ConnectedClient {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         context: &Weak<Context>,
         server: &Weak<Server>,
@@ -144,6 +191,10 @@ impl ConnectedClient {
         addr: SocketAddr,
         message_backlog_size: usize,
         channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+        message_filter: Option<Arc<dyn SinkMessageFilter>>,
+        protocol: ProtocolVersion,
+        permissions: ClientPermissions,
+        peer_subject: Option<String>,
     ) -> Arc<Self> {
         let (data_plane_tx, data_plane_rx) = flume::bounded(message_backlog_size);
         let (control_plane_tx, control_plane_rx) = flume::bounded(message_backlog_size);
@@ -155,6 +206,10 @@ impl ConnectedClient {
             sink_id: SinkId::next(),
             context: context.clone(),
             channel_filter,
+            message_filter,
+            protocol,
+            permissions,
+            peer_subject,
             poller: parking_lot::Mutex::new(Some(Poller::new(
                 websocket,
                 data_plane_rx.clone(),
@@ -166,6 +221,7 @@ impl ConnectedClient {
             data_plane_rx,
             control_plane_tx,
             service_call_sem: Semaphore::new(DEFAULT_SERVICE_CALLS_PER_CLIENT),
+            dropped: AtomicU64::default(),
             fetch_asset_sem: Semaphore::new(DEFAULT_FETCH_ASSET_CALLS_PER_CLIENT),
             subscriptions: parking_lot::Mutex::default(),
             advertised_channels: parking_lot::Mutex::default(),
@@ -196,6 +252,10 @@ impl ConnectedClient {
         self.addr
     }
 
+    pub fn peer_certificate_subject(&self) -> Option<&str> {
+        self.peer_subject.as_deref()
+    }
+
     /// Runs the client's poll loop to completion.
     ///
     /// The poll loop may exit either due to the client closing the connection, or due to an
@@ -218,7 +278,7 @@ impl ConnectedClient {
     ///
     /// Standard protocol messages (such as Close) should be handled upstream.
     fn handle_message(&self, message: Message) {
-        let msg = match ClientMessage::try_from(&message) {
+        let msg = match self.decode_message(&message) {
             Ok(m) => m,
             Err(ParseError::EmptyBinaryMessage) => {
                 tracing::debug!("Received empty binary message from {}", self.addr);
@@ -270,6 +330,53 @@ impl ConnectedClient {
         }
     }
 
+    /// Decodes a client message with the negotiated protocol version.
+    ///
+    /// Protocol v2 clients subscribe by channel ID, so the channel ID doubles as the subscription
+    /// ID. This lets v2 subscriptions share the v1 bookkeeping.
+    fn decode_message<'a>(&self, message: &'a Message) -> Result<ClientMessage<'a>, ParseError> {
+        let msg = match self.protocol {
+            ProtocolVersion::V1 => return ClientMessage::try_from(message),
+            ProtocolVersion::V2 => v2::client::ClientMessage::try_from(message)?,
+        };
+        Ok(match msg {
+            v2::client::ClientMessage::Subscribe(m) => {
+                ClientMessage::Subscribe(ws_protocol::client::Subscribe::new(
+                    m.channel_ids
+                        .into_iter()
+                        .map(|id| ws_protocol::client::Subscription::new(id, id.into())),
+                ))
+            }
+            v2::client::ClientMessage::Unsubscribe(m) => {
+                ClientMessage::Unsubscribe(ws_protocol::client::Unsubscribe::new(m.channel_ids))
+            }
+            v2::client::ClientMessage::Advertise(m) => ClientMessage::Advertise(m),
+            v2::client::ClientMessage::Unadvertise(m) => ClientMessage::Unadvertise(m),
+            v2::client::ClientMessage::MessageData(m) => ClientMessage::MessageData(m),
+            v2::client::ClientMessage::GetParameters(m) => ClientMessage::GetParameters(m),
+            v2::client::ClientMessage::SetParameters(m) => ClientMessage::SetParameters(m),
+            v2::client::ClientMessage::SubscribeParameterUpdates(m) => {
+                ClientMessage::SubscribeParameterUpdates(m)
+            }
+            v2::client::ClientMessage::UnsubscribeParameterUpdates(m) => {
+                ClientMessage::UnsubscribeParameterUpdates(m)
+            }
+            v2::client::ClientMessage::ServiceCallRequest(m) => {
+                ClientMessage::ServiceCallRequest(m)
+            }
+            v2::client::ClientMessage::SubscribeConnectionGraph => {
+                ClientMessage::SubscribeConnectionGraph
+            }
+            v2::client::ClientMessage::UnsubscribeConnectionGraph => {
+                ClientMessage::UnsubscribeConnectionGraph
+            }
+            v2::client::ClientMessage::FetchAsset(m) => ClientMessage::FetchAsset(m),
+            v2::client::ClientMessage::PlaybackControlRequest(m) => {
+                ClientMessage::PlaybackControlRequest(m)
+            }
+        })
+    }
+
     /// Send the message on the data plane, dropping up to retries older messages to make room, if necessary.
     fn send_data_lossy(&self, message: impl Into<Message>, retries: usize) -> SendLossyResult {
         send_lossy::send_lossy(
@@ -366,6 +473,14 @@ impl ConnectedClient {
             .collect();
 
         for channel in channels {
+            if !self.permissions.can_publish(&channel.topic) {
+                self.send_error(format!(
+                    "Not permitted to publish to topic: {}; ignoring advertisement",
+                    channel.topic
+                ));
+                continue;
+            }
+
             // Using a limited scope here to avoid holding the lock on advertised_channels while calling on_client_advertise
             let client_channel = {
                 match self.advertised_channels.lock().entry(channel.id) {
@@ -438,6 +553,16 @@ impl ConnectedClient {
                     subscriptions.swap_remove(i);
                     continue;
                 };
+                if !self.permissions.can_subscribe(channel.topic()) {
+                    tracing::info!(
+                        "Client {} is not permitted to subscribe to topic: {}",
+                        self.addr,
+                        channel.topic()
+                    );
+                    self.send_error(format!("{SUBSCRIBE_DENIED}{}", channel.topic()));
+                    subscriptions.swap_remove(i);
+                    continue;
+                }
                 subscribed_channels.push(channel.clone());
                 i += 1
             }
@@ -517,6 +642,11 @@ impl ConnectedClient {
             return;
         }
 
+        if !self.permissions.can_write_parameters() {
+            self.send_error(WRITE_PARAMETERS_DENIED.to_string());
+            return;
+        }
+
         let updated_parameters = if let Some(handler) = server.listener() {
             let updated =
                 handler.on_set_parameters(Client::new(self), parameters, request_id.as_deref());
@@ -577,6 +707,13 @@ impl ConnectedClient {
             return;
         };
 
+        if !self.permissions.can_call_service(service.name()) {
+            let message = format!("Not permitted to call service: {}", service.name());
+            self.send_service_call_failure(service_id, call_id, &message);
+            self.send_error(message);
+            return;
+        }
+
         // If this service declared a request encoding, ensure that it matches. Otherwise, ensure
         // that the request encoding is in the server's global list of supported encodings.
         if !service
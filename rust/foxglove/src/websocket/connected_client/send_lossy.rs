@@ -12,7 +12,6 @@ static THROTTLER: Mutex<Throttler> = Mutex::new(Throttler::new(Duration::from_se
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum SendLossyResult {
     Sent,
-    #[allow(dead_code)]
     SentLossy(usize),
     ExhaustedRetries,
 }
@@ -1,43 +1,132 @@
+use std::net::SocketAddr;
+
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_tungstenite::tungstenite::handshake::server;
 use tokio_tungstenite::tungstenite::http::HeaderValue;
 use tokio_tungstenite::{tungstenite, WebSocketStream};
 
+use super::authorization::{ClientPermissions, ConnectionAuthorizer, ConnectionRequest};
+
 pub(crate) const SUBPROTOCOL: &str = "foxglove.sdk.v1";
+pub(crate) const SUBPROTOCOL_V2: &str = "foxglove.sdk.v2";
+
+/// A version of the Foxglove protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProtocolVersion {
+    V1,
+    V2,
+}
+
+impl ProtocolVersion {
+    /// Supported versions, from most to least preferred.
+    const PREFERENCE: [Self; 2] = [Self::V2, Self::V1];
+
+    /// Returns the websocket subprotocol for the version.
+    pub fn subprotocol(self) -> &'static str {
+        match self {
+            Self::V1 => SUBPROTOCOL,
+            Self::V2 => SUBPROTOCOL_V2,
+        }
+    }
+
+    /// Returns the most preferred version among the subprotocols offered by the client, regardless
+    /// of the order in which the client listed them.
+    fn negotiate(offered: &[&str]) -> Option<Self> {
+        Self::PREFERENCE
+            .into_iter()
+            .find(|version| offered.contains(&version.subprotocol()))
+    }
+}
 
-/// Add the subprotocol header to the response if the client requested it. If the client requests
-/// subprotocols which don't contain ours, or does not include the expected header, return a 400.
+/// The result of a successful handshake.
+pub(crate) struct Handshake<S> {
+    pub stream: WebSocketStream<S>,
+    pub protocol: ProtocolVersion,
+    pub permissions: ClientPermissions,
+}
+
+/// Negotiate the subprotocol and add it to the response. If the client does not offer any
+/// supported subprotocol, or does not include the expected header, return a 400.
+///
+/// If an authorizer is provided, it is consulted once the subprotocol has been negotiated. If it
+/// rejects the client, the response carries the authorizer's status code and message. Otherwise,
+/// the client's permissions are returned along with the stream.
 pub(crate) async fn do_handshake<S: AsyncRead + AsyncWrite + Unpin>(
     stream: S,
-) -> Result<WebSocketStream<S>, tungstenite::Error> {
-    tokio_tungstenite::accept_hdr_async(
+    addr: SocketAddr,
+    peer_subject: Option<&str>,
+    authorizer: Option<&dyn ConnectionAuthorizer>,
+) -> Result<Handshake<S>, tungstenite::Error> {
+    let mut protocol = ProtocolVersion::V1;
+    let mut permissions = ClientPermissions::all();
+    let stream = tokio_tungstenite::accept_hdr_async(
         stream,
         |req: &server::Request, mut res: server::Response| {
-            let protocol_headers = req.headers().get_all("sec-websocket-protocol");
-            for header in &protocol_headers {
-                if header
-                    .to_str()
-                    .unwrap_or_default()
-                    .split(',')
-                    .any(|v| v.trim() == SUBPROTOCOL)
-                {
-                    res.headers_mut().insert(
-                        "sec-websocket-protocol",
-                        HeaderValue::from_static(SUBPROTOCOL),
-                    );
-                    return Ok(res);
+            let offered = req
+                .headers()
+                .get_all("sec-websocket-protocol")
+                .iter()
+                .flat_map(|header| header.to_str().unwrap_or_default().split(','))
+                .map(str::trim)
+                .collect::<Vec<_>>();
+            let Some(version) = ProtocolVersion::negotiate(&offered) else {
+                let resp = server::Response::builder()
+                    .status(400)
+                    .body(Some(
+                        "Missing expected sec-websocket-protocol header".into(),
+                    ))
+                    .unwrap();
+                return Err(resp);
+            };
+            protocol = version;
+
+            if let Some(authorizer) = authorizer {
+                match authorizer.authorize(&ConnectionRequest::new(req, addr, peer_subject)) {
+                    Ok(p) => permissions = p,
+                    Err(e) => {
+                        tracing::info!("Rejected client {addr}: {e}");
+                        let resp = server::Response::builder()
+                            .status(e.status())
+                            .body(Some(e.message().to_string()))
+                            .unwrap();
+                        return Err(resp);
+                    }
                 }
             }
 
-            let resp = server::Response::builder()
-                .status(400)
-                .body(Some(
-                    "Missing expected sec-websocket-protocol header".into(),
-                ))
-                .unwrap();
-
-            Err(resp)
+            res.headers_mut().insert(
+                "sec-websocket-protocol",
+                HeaderValue::from_static(protocol.subprotocol()),
+            );
+            Ok(res)
         },
     )
-    .await
+    .await?;
+    Ok(Handshake {
+        stream,
+        protocol,
+        permissions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        use ProtocolVersion::*;
+        assert_eq!(ProtocolVersion::negotiate(&[SUBPROTOCOL]), Some(V1));
+        assert_eq!(ProtocolVersion::negotiate(&[SUBPROTOCOL_V2]), Some(V2));
+        assert_eq!(
+            ProtocolVersion::negotiate(&[SUBPROTOCOL, SUBPROTOCOL_V2]),
+            Some(V2)
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(&["unknown", SUBPROTOCOL]),
+            Some(V1)
+        );
+        assert_eq!(ProtocolVersion::negotiate(&["unknown"]), None);
+        assert_eq!(ProtocolVersion::negotiate(&[]), None);
+    }
 }
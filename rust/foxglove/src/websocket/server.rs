@@ -14,10 +14,12 @@ use tokio_util::sync::CancellationToken;
 
 use crate::library_version::get_library_version;
 use crate::sink_channel_filter::SinkChannelFilter;
+use crate::sink_message_filter::SinkMessageFilter;
 use crate::websocket::connected_client::ShutdownReason;
-use crate::websocket::streams::{Acceptor, StreamConfiguration, TlsIdentity};
+use crate::websocket::streams::{Acceptor, StreamConfiguration, TlsClientAuth, TlsIdentity};
 use crate::{Context, FoxgloveError};
 
+use super::authorization::ConnectionAuthorizer;
 use super::connected_client::ConnectedClient;
 use super::cow_vec::CowVec;
 use super::service::{Service, ServiceId, ServiceMap};
@@ -46,9 +48,13 @@ pub(crate) struct ServerOptions {
     pub runtime: Option<Handle>,
     pub fetch_asset_handler: Option<Box<dyn AssetHandler>>,
     pub tls_identity: Option<TlsIdentity>,
+    pub tls_client_auth: Option<TlsClientAuth>,
     pub channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+    pub message_filter: Option<Arc<dyn SinkMessageFilter>>,
     pub server_info: Option<HashMap<String, String>>,
     pub playback_time_range: Option<(u64, u64)>,
+    pub clock_broadcast_interval: Option<Duration>,
+    pub connection_authorizer: Option<Arc<dyn ConnectionAuthorizer>>,
 }
 
 impl std::fmt::Debug for ServerOptions {
@@ -119,7 +125,8 @@ pub(crate) fn create_server(
     opts: ServerOptions,
 ) -> Result<Arc<Server>, FoxgloveError> {
     // TLS configuration is fallible, so build it prior to allocating the Arc with the weak ref
-    let stream_config = StreamConfiguration::new(opts.tls_identity.as_ref())?;
+    let stream_config =
+        StreamConfiguration::new(opts.tls_identity.as_ref(), opts.tls_client_auth.as_ref())?;
 
     Ok(Arc::new_cyclic(|weak_self| {
         Server::new(weak_self.clone(), ctx, opts, stream_config)
@@ -143,6 +150,8 @@ pub(crate) struct Server {
     clients: CowVec<Arc<ConnectedClient>>,
     /// Channel subscription filter
     channel_filter: Option<Arc<dyn SinkChannelFilter>>,
+    /// Per-message filter
+    message_filter: Option<Arc<dyn SinkMessageFilter>>,
     /// Callbacks for handling client messages, etc.
     listener: Option<Arc<dyn ServerListener>>,
     /// Capabilities advertised to clients
@@ -169,6 +178,10 @@ pub(crate) struct Server {
     /// Time range of data being played back, in absolute nanoseconds.
     /// Implies the [`RangedPlayback`](crate::websocket::Capability::RangedPlayback) capability if set.
     playback_time_range: Option<(u64, u64)>,
+    /// Interval at which to broadcast the context's clock time, if enabled.
+    clock_broadcast_interval: Option<Duration>,
+    /// Decides whether clients may connect, and what they may do.
+    connection_authorizer: Option<Arc<dyn ConnectionAuthorizer>>,
 }
 
 impl Server {
@@ -209,6 +222,11 @@ impl Server {
             panic!("Server declared the RangedPlayback capability but did not provide a playback time range");
         }
 
+        // If the server broadcasts the context's clock, automatically add the "time" capability.
+        if opts.clock_broadcast_interval.is_some() {
+            capabilities.insert(Capability::Time);
+        }
+
         // If the server was declared with fetch asset handler, automatically add the "assets" capability
         if opts.fetch_asset_handler.is_some() {
             capabilities.insert(Capability::Assets);
@@ -222,6 +240,7 @@ impl Server {
                 .unwrap_or(DEFAULT_MESSAGE_BACKLOG_SIZE) as u32,
             runtime: opts.runtime.unwrap_or_else(crate::get_runtime_handle),
             channel_filter: opts.channel_filter.clone(),
+            message_filter: opts.message_filter.clone(),
             listener: opts.listener,
             session_id: parking_lot::RwLock::new(
                 opts.session_id.unwrap_or_else(Self::generate_session_id),
@@ -239,6 +258,8 @@ impl Server {
             stream_config,
             server_info: opts.server_info.unwrap_or_default(),
             playback_time_range: opts.playback_time_range,
+            clock_broadcast_interval: opts.clock_broadcast_interval,
+            connection_authorizer: opts.connection_authorizer,
         }
     }
 
@@ -249,7 +270,7 @@ impl Server {
     }
 
     /// Returns true if the server supports the capability.
-    pub(super) fn has_capability(&self, cap: Capability) -> bool {
+    pub(crate) fn has_capability(&self, cap: Capability) -> bool {
         self.capabilities.contains(&cap)
     }
 
@@ -290,6 +311,7 @@ impl Server {
             tokio::select! {
                 () = server.clone().accept_connections(listener) => (),
                 () = server.clone().reap_completed_tasks() => (),
+                () = server.clone().broadcast_clock_time() => (),
                 () = cancellation_token.cancelled() => (),
             }
         });
@@ -331,6 +353,22 @@ impl Server {
         }
     }
 
+    /// Periodically broadcasts the time from the context's clock, if enabled.
+    async fn broadcast_clock_time(self: Arc<Self>) {
+        let Some(period) = self.clock_broadcast_interval else {
+            return std::future::pending().await;
+        };
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            let Some(context) = self.context.upgrade() else {
+                return std::future::pending().await;
+            };
+            self.broadcast_time(context.now());
+        }
+    }
+
     /// Stops the server.
     ///
     /// Returns a handle that can be used to wait for the graceful shutdown to complete. If the
@@ -592,11 +630,20 @@ impl Server {
             }
         };
 
-        let Ok(mut ws_stream) = handshake::do_handshake(stream).await else {
+        let peer_subject = self.stream_config.peer_certificate_subject(&stream);
+        let Ok(handshake) = handshake::do_handshake(
+            stream,
+            addr,
+            peer_subject.as_deref(),
+            self.connection_authorizer.as_deref(),
+        )
+        .await
+        else {
             tracing::error!("Dropping client {addr}: handshake failed");
             return;
         };
 
+        let mut ws_stream = handshake.stream;
         let message = Message::from(&self.server_info());
         if let Err(err) = ws_stream.send(message).await {
             // ServerInfo is required; do not store this client.
@@ -611,6 +658,10 @@ impl Server {
             addr,
             self.message_backlog_size as usize,
             self.channel_filter.clone(),
+            self.message_filter.clone(),
+            handshake.protocol,
+            handshake.permissions,
+            peer_subject,
         );
         self.register_client_and_advertise(&client);
         client.run().await;
@@ -818,4 +869,12 @@ impl Server {
     pub(crate) fn is_tls_configured(&self) -> bool {
         self.stream_config.accepts_tls()
     }
+
+    /// Replaces the server's TLS identity, without affecting existing connections.
+    #[cfg(feature = "tls")]
+    pub(crate) fn set_tls_identity(&self, identity: &TlsIdentity) -> Result<(), FoxgloveError> {
+        self.stream_config.set_identity(identity)?;
+        tracing::info!("Updated TLS identity");
+        Ok(())
+    }
 }
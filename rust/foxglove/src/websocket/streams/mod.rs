@@ -20,6 +20,8 @@ pub(crate) type ServerStream<S> = Either<S, TlsStream<S>>;
 pub(crate) trait Acceptor {
     async fn accept(&self, stream: TcpStream) -> Result<ServerStream<TcpStream>, FoxgloveError>;
     fn accepts_tls(&self) -> bool;
+    /// Returns the subject of the verified client certificate, if the client presented one.
+    fn peer_certificate_subject(&self, stream: &ServerStream<TcpStream>) -> Option<String>;
 }
 
 /// TLS configuration for a server
@@ -30,3 +32,15 @@ pub struct TlsIdentity {
     /// A PEM-encoded PKCS8 private key.
     pub key: Vec<u8>,
 }
+
+/// Client certificate verification for a TLS server.
+#[doc(hidden)]
+pub struct TlsClientAuth {
+    /// PEM-encoded X.509 CA certificates which are trusted to issue client certificates.
+    pub ca_certs: Vec<u8>,
+    /// Whether clients must present a certificate.
+    ///
+    /// If false, clients may connect without a certificate, but a certificate that is presented
+    /// must still be issued by a trusted CA.
+    pub required: bool,
+}
@@ -1,7 +1,7 @@
 //! Facades for TLS support when the "tls" feature is disabled.
 
 use crate::{
-    websocket::streams::{Acceptor, ServerStream, TlsIdentity},
+    websocket::streams::{Acceptor, ServerStream, TlsClientAuth, TlsIdentity},
     FoxgloveError,
 };
 use tokio_util::either::Either;
@@ -11,9 +11,12 @@ pub(crate) type TlsStream<S> = S;
 pub struct StreamConfiguration {}
 
 impl StreamConfiguration {
-    /// Returns an error if a TlsIdentity is provided.
-    pub fn new(identity: Option<&TlsIdentity>) -> Result<Self, FoxgloveError> {
-        if identity.is_some() {
+    /// Returns an error if a TlsIdentity or TlsClientAuth is provided.
+    pub fn new(
+        identity: Option<&TlsIdentity>,
+        client_auth: Option<&TlsClientAuth>,
+    ) -> Result<Self, FoxgloveError> {
+        if identity.is_some() || client_auth.is_some() {
             return Err(FoxgloveError::ConfigurationError(
                 "TLS is not enabled".to_string(),
             ));
@@ -35,4 +38,11 @@ impl Acceptor for StreamConfiguration {
     fn accepts_tls(&self) -> bool {
         false
     }
+
+    fn peer_certificate_subject(
+        &self,
+        _stream: &ServerStream<tokio::net::TcpStream>,
+    ) -> Option<String> {
+        None
+    }
 }
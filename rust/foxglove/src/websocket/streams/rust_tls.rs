@@ -2,51 +2,118 @@
 
 use std::sync::Arc;
 
+use arc_swap::ArcSwap;
 use tokio::net::TcpStream;
 use tokio_rustls::{
     rustls::{
         self,
         pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        server::{danger::ClientCertVerifier, WebPkiClientVerifier},
+        RootCertStore,
     },
     TlsAcceptor,
 };
 use tokio_util::either::Either;
 
 use crate::{
-    websocket::streams::{Acceptor, ServerStream, TlsIdentity},
+    websocket::streams::{Acceptor, ServerStream, TlsClientAuth, TlsIdentity},
     FoxgloveError,
 };
 
 pub(crate) type TlsStream<S> = tokio_rustls::server::TlsStream<S>;
 
 pub struct StreamConfiguration {
-    tls_acceptor: Option<TlsAcceptor>,
+    tls: Option<TlsConfiguration>,
 }
 
-fn build_tls_acceptor(tls_identity: &TlsIdentity) -> Result<TlsAcceptor, FoxgloveError> {
-    let cert = CertificateDer::from_pem_slice(&tls_identity.cert)
-        .map_err(|e| FoxgloveError::ConfigurationError(format!("TLS configuration: {e}")))?;
+/// The TLS state of a server with an identity.
+struct TlsConfiguration {
+    /// The current acceptor, which is replaced when the identity changes.
+    acceptor: ArcSwap<TlsAcceptor>,
+    /// Verifier for client certificates, if client authentication is enabled.
+    client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+}
+
+fn config_error(e: impl std::fmt::Display) -> FoxgloveError {
+    FoxgloveError::ConfigurationError(format!("TLS configuration: {e}"))
+}
+
+fn build_client_verifier(
+    client_auth: &TlsClientAuth,
+) -> Result<Arc<dyn ClientCertVerifier>, FoxgloveError> {
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_slice_iter(&client_auth.ca_certs) {
+        roots
+            .add(cert.map_err(config_error)?)
+            .map_err(config_error)?;
+    }
+
+    let builder = WebPkiClientVerifier::builder(Arc::new(roots));
+    let builder = if client_auth.required {
+        builder
+    } else {
+        builder.allow_unauthenticated()
+    };
+    builder.build().map_err(config_error)
+}
+
+fn build_tls_acceptor(
+    tls_identity: &TlsIdentity,
+    client_verifier: Option<&Arc<dyn ClientCertVerifier>>,
+) -> Result<TlsAcceptor, FoxgloveError> {
+    let certs = CertificateDer::pem_slice_iter(&tls_identity.cert)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(config_error)?;
 
-    let key = PrivateKeyDer::from_pem_slice(&tls_identity.key)
-        .map_err(|e| FoxgloveError::ConfigurationError(format!("TLS configuration: {e}")))?;
+    let key = PrivateKeyDer::from_pem_slice(&tls_identity.key).map_err(config_error)?;
 
-    let config = rustls::ServerConfig::builder()
-        .with_no_client_auth()
-        .with_single_cert(vec![cert], key)
-        .map_err(|e| FoxgloveError::ConfigurationError(format!("TLS configuration: {e}")))?;
+    let builder = rustls::ServerConfig::builder();
+    let builder = match client_verifier {
+        Some(verifier) => builder.with_client_cert_verifier(verifier.clone()),
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder.with_single_cert(certs, key).map_err(config_error)?;
 
     Ok(TlsAcceptor::from(Arc::new(config)))
 }
 
 impl StreamConfiguration {
-    pub fn new(identity: Option<&TlsIdentity>) -> Result<Self, FoxgloveError> {
-        let tls_acceptor = if let Some(identity) = identity {
-            let acceptor = build_tls_acceptor(identity)?;
-            Some(acceptor)
-        } else {
-            None
+    pub fn new(
+        identity: Option<&TlsIdentity>,
+        client_auth: Option<&TlsClientAuth>,
+    ) -> Result<Self, FoxgloveError> {
+        let Some(identity) = identity else {
+            if client_auth.is_some() {
+                return Err(FoxgloveError::ConfigurationError(
+                    "TLS client authentication requires a TLS identity".to_string(),
+                ));
+            }
+            return Ok(Self { tls: None });
+        };
+
+        let client_verifier = client_auth.map(build_client_verifier).transpose()?;
+        let acceptor = build_tls_acceptor(identity, client_verifier.as_ref())?;
+        Ok(Self {
+            tls: Some(TlsConfiguration {
+                acceptor: ArcSwap::from_pointee(acceptor),
+                client_verifier,
+            }),
+        })
+    }
+
+    /// Replaces the server's identity.
+    ///
+    /// New connections are accepted with the new identity. Existing connections are unaffected.
+    /// Returns an error if the server was not configured with TLS, or if the identity is invalid.
+    pub fn set_identity(&self, identity: &TlsIdentity) -> Result<(), FoxgloveError> {
+        let Some(tls) = &self.tls else {
+            return Err(FoxgloveError::ConfigurationError(
+                "TLS is not enabled".to_string(),
+            ));
         };
-        Ok(Self { tls_acceptor })
+        let acceptor = build_tls_acceptor(identity, tls.client_verifier.as_ref())?;
+        tls.acceptor.store(Arc::new(acceptor));
+        Ok(())
     }
 }
 
@@ -55,8 +122,9 @@ impl Acceptor for StreamConfiguration {
         &self,
         stream: TcpStream,
     ) -> Result<ServerStream<TcpStream>, crate::FoxgloveError> {
-        let stream = if let Some(tls_acceptor) = &self.tls_acceptor {
-            let stream = tls_acceptor.accept(stream).await?;
+        let stream = if let Some(tls) = &self.tls {
+            let acceptor = tls.acceptor.load_full();
+            let stream = acceptor.accept(stream).await?;
             Either::Right(stream)
         } else {
             Either::Left(stream)
@@ -65,6 +133,20 @@ impl Acceptor for StreamConfiguration {
     }
 
     fn accepts_tls(&self) -> bool {
-        self.tls_acceptor.is_some()
+        self.tls.is_some()
+    }
+
+    fn peer_certificate_subject(&self, stream: &ServerStream<TcpStream>) -> Option<String> {
+        let Either::Right(stream) = stream else {
+            return None;
+        };
+        let cert = stream.get_ref().1.peer_certificates()?.first()?;
+        match x509_parser::parse_x509_certificate(cert) {
+            Ok((_, cert)) => Some(cert.subject().to_string()),
+            Err(e) => {
+                tracing::warn!("Failed to parse client certificate: {e}");
+                None
+            }
+        }
     }
 }
@@ -1,9 +1,12 @@
 use assert_matches::assert_matches;
 use bytes::{BufMut, Bytes, BytesMut};
-use futures_util::FutureExt;
+use futures_util::{FutureExt, SinkExt, StreamExt};
 use maplit::hashmap;
 #[cfg(feature = "tls")]
-use rcgen::{CertificateParams, Issuer, KeyPair};
+use rcgen::{
+    BasicConstraints, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa,
+    Issuer, KeyPair,
+};
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -31,21 +34,22 @@ use super::ws_protocol::server::{
 };
 use crate::library_version::get_library_version;
 use crate::testutil::{assert_eventually, RecordingServerListener};
-use crate::websocket::handshake::SUBPROTOCOL;
+use crate::websocket::handshake::{SUBPROTOCOL, SUBPROTOCOL_V2};
 use crate::websocket::server::{create_server as do_create_server, ServerOptions};
 use crate::websocket::service::{CallId, Service, ServiceSchema};
-#[cfg(feature = "tls")]
-use crate::websocket::TlsIdentity;
 use crate::websocket::{
-    BlockingAssetHandlerFn, Capability, ClientChannelId, ConnectionGraph, Parameter, Server,
+    AuthorizationError, BlockingAssetHandlerFn, Capability, ClientChannelId, ClientPermissions,
+    ConnectionAuthorizerFn, ConnectionGraph, ConnectionRequest, Parameter, Server,
 };
 use crate::websocket::{
     PlaybackCommand, PlaybackControlRequest, PlaybackState, PlaybackStatus, ServerListener,
 };
+#[cfg(feature = "tls")]
+use crate::websocket::{TlsClientAuth, TlsIdentity};
 use crate::websocket_client::WebSocketClient;
 use crate::{
-    ChannelBuilder, ChannelDescriptor, Context, FoxgloveError, PartialMetadata, RawChannel, Schema,
-    SinkChannelFilter, WebSocketClientError,
+    ChannelBuilder, ChannelDescriptor, Context, FoxgloveError, Metadata, PartialMetadata,
+    RawChannel, Schema, SinkChannelFilter, SinkMessageFilter, WebSocketClientError,
 };
 
 macro_rules! expect_recv {
@@ -189,6 +193,167 @@ async fn test_invalid_tls_config() {
     assert!(error.unwrap().to_string().contains("KeyMismatch"));
 }
 
+/// Generates a CA certificate which can issue server and client certificates.
+#[cfg(feature = "tls")]
+fn new_test_ca() -> (rcgen::Certificate, Issuer<'static, KeyPair>) {
+    let mut params = CertificateParams::default();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let key = KeyPair::generate().expect("default keygen will succeed");
+    let cert = params.self_signed(&key).expect("failed to sign CA cert");
+    (cert, Issuer::new(params, key))
+}
+
+/// Issues a server identity for 127.0.0.1.
+#[cfg(feature = "tls")]
+fn new_test_identity(issuer: &Issuer<'static, KeyPair>) -> TlsIdentity {
+    let params = CertificateParams::new(vec!["127.0.0.1".to_string()]).expect("SAN is valid");
+    let key = KeyPair::generate().expect("default keygen will succeed");
+    let cert = params.signed_by(&key, issuer).expect("failed to sign cert");
+    TlsIdentity {
+        cert: cert.pem().as_bytes().to_vec(),
+        key: key.serialize_pem().as_bytes().to_vec(),
+    }
+}
+
+#[cfg(feature = "tls")]
+#[traced_test]
+#[tokio::test]
+async fn test_mutual_tls() {
+    struct SubjectListener(Mutex<Vec<Option<String>>>);
+    impl ServerListener for SubjectListener {
+        fn on_get_parameters(
+            &self,
+            client: super::Client,
+            _param_names: Vec<String>,
+            _request_id: Option<&str>,
+        ) -> Vec<Parameter> {
+            self.0
+                .lock()
+                .unwrap()
+                .push(client.peer_certificate_subject());
+            vec![]
+        }
+    }
+
+    let (server_ca, server_issuer) = new_test_ca();
+    let (client_ca, client_issuer) = new_test_ca();
+
+    let mut params = CertificateParams::default();
+    params.distinguished_name = DistinguishedName::new();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "robot-1");
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    let client_key = KeyPair::generate().expect("default keygen will succeed");
+    let client_cert = params
+        .signed_by(&client_key, &client_issuer)
+        .expect("failed to sign cert");
+
+    let listener = Arc::new(SubjectListener(Mutex::default()));
+    let ctx = Context::new();
+    let server = create_server(
+        &ctx,
+        ServerOptions {
+            capabilities: Some(HashSet::from([Capability::Parameters])),
+            listener: Some(listener.clone()),
+            tls_identity: Some(new_test_identity(&server_issuer)),
+            tls_client_auth: Some(TlsClientAuth {
+                ca_certs: client_ca.pem().as_bytes().to_vec(),
+                required: true,
+            }),
+            connection_authorizer: Some(Arc::new(ConnectionAuthorizerFn(
+                |req: &ConnectionRequest<'_>| match req.peer_certificate_subject() {
+                    Some("CN=robot-1") => Ok(ClientPermissions::all()),
+                    _ => Err(AuthorizationError::forbidden("Unknown client")),
+                },
+            ))),
+            ..Default::default()
+        },
+    );
+    let addr = server
+        .start("127.0.0.1", 0)
+        .await
+        .expect("Failed to start server");
+
+    // A client without a certificate is rejected.
+    let result = WebSocketClient::connect_secure(addr.to_string(), server_ca.clone()).await;
+    assert!(result.is_err());
+
+    let mut client = WebSocketClient::connect_secure_with_client_cert(
+        addr.to_string(),
+        server_ca,
+        &client_cert,
+        &client_key,
+    )
+    .await
+    .expect("Failed to connect");
+    expect_recv!(client, ServerMessage::ServerInfo);
+
+    client
+        .send(&GetParameters::new(["foo"]))
+        .await
+        .expect("Failed to send get parameters");
+    expect_recv!(client, ServerMessage::ParameterValues);
+    assert_eq!(
+        *listener.0.lock().unwrap(),
+        vec![Some("CN=robot-1".to_string())]
+    );
+
+    let _ = server.stop();
+}
+
+#[cfg(feature = "tls")]
+#[traced_test]
+#[tokio::test]
+async fn test_set_tls_identity() {
+    let (old_ca, old_issuer) = new_test_ca();
+    let (new_ca, new_issuer) = new_test_ca();
+
+    let ctx = Context::new();
+    let server = create_server(
+        &ctx,
+        ServerOptions {
+            tls_identity: Some(new_test_identity(&old_issuer)),
+            ..Default::default()
+        },
+    );
+    let addr = server
+        .start("127.0.0.1", 0)
+        .await
+        .expect("Failed to start server");
+
+    let mut old_client = WebSocketClient::connect_secure(addr.to_string(), old_ca.clone())
+        .await
+        .expect("Failed to connect");
+    expect_recv!(old_client, ServerMessage::ServerInfo);
+
+    // An invalid identity is rejected, and the previous identity remains in use.
+    let result = server.set_tls_identity(&TlsIdentity {
+        cert: b"invalid".to_vec(),
+        key: b"invalid".to_vec(),
+    });
+    assert_matches!(result, Err(FoxgloveError::ConfigurationError(_)));
+
+    server
+        .set_tls_identity(&new_test_identity(&new_issuer))
+        .expect("Failed to set identity");
+
+    // New connections use the new identity.
+    let result = WebSocketClient::connect_secure(addr.to_string(), old_ca).await;
+    assert!(result.is_err());
+    let mut new_client = WebSocketClient::connect_secure(addr.to_string(), new_ca)
+        .await
+        .expect("Failed to connect");
+    expect_recv!(new_client, ServerMessage::ServerInfo);
+
+    // Existing connections are unaffected.
+    server.clear_session(Some("new".to_string()));
+    let msg = expect_recv!(old_client, ServerMessage::ServerInfo);
+    assert_eq!(msg.session_id, Some("new".to_string()));
+
+    let _ = server.stop();
+}
+
 #[traced_test]
 #[tokio::test]
 async fn test_handshake_with_unknown_subprotocol_fails_on_client() {
@@ -259,7 +424,7 @@ async fn test_handshake_with_multiple_subprotocols() {
         .expect("Failed to build request");
 
     let mut req1 = request.clone();
-    let header = format!("{SUBPROTOCOL}, foxglove.sdk.v2");
+    let header = format!("{SUBPROTOCOL}, {SUBPROTOCOL_V2}");
     req1.headers_mut().insert(
         "sec-websocket-protocol",
         HeaderValue::from_str(&header).unwrap(),
@@ -269,9 +434,10 @@ async fn test_handshake_with_multiple_subprotocols() {
         .await
         .expect("Failed to connect");
 
+    // The server prefers the newest protocol offered by the client
     assert_eq!(
         response.headers().get("sec-websocket-protocol"),
-        Some(&HeaderValue::from_static(SUBPROTOCOL))
+        Some(&HeaderValue::from_static(SUBPROTOCOL_V2))
     );
 
     // In req2, the client's preferred (initial) subprotocol is not valid
@@ -294,6 +460,82 @@ async fn test_handshake_with_multiple_subprotocols() {
     let _ = server.stop();
 }
 
+#[traced_test]
+#[tokio::test]
+async fn test_protocol_v2_session() {
+    use crate::protocol::v2;
+
+    async fn recv(
+        rx: &mut (impl futures_util::Stream<Item = Result<Message, tungstenite::Error>> + Unpin),
+    ) -> v2::server::ServerMessage<'static> {
+        let msg = rx.next().await.unwrap().expect("Failed to recv");
+        v2::server::ServerMessage::try_from(&msg)
+            .expect("Failed to parse")
+            .into_owned()
+    }
+
+    let recording_listener = Arc::new(RecordingServerListener::new());
+    let ctx = Context::new();
+    let server = create_server(
+        &ctx,
+        ServerOptions {
+            listener: Some(recording_listener.clone()),
+            ..Default::default()
+        },
+    );
+    let addr = server
+        .start("127.0.0.1", 0)
+        .await
+        .expect("Failed to start server");
+    let channel = new_channel("/foo", &ctx);
+    let channel_id = u32::try_from(u64::from(channel.id())).unwrap();
+
+    let mut request = format!("ws://{addr}/")
+        .into_client_request()
+        .expect("Failed to build request");
+    request.headers_mut().insert(
+        "sec-websocket-protocol",
+        HeaderValue::from_static(SUBPROTOCOL_V2),
+    );
+    let (stream, response) = tokio_tungstenite::connect_async(request)
+        .await
+        .expect("Failed to connect");
+    assert_eq!(
+        response.headers().get("sec-websocket-protocol"),
+        Some(&HeaderValue::from_static(SUBPROTOCOL_V2))
+    );
+    let (mut tx, mut rx) = stream.split();
+
+    assert_matches!(
+        recv(&mut rx).await,
+        v2::server::ServerMessage::ServerInfo(_)
+    );
+    assert_matches!(recv(&mut rx).await, v2::server::ServerMessage::Advertise(_));
+
+    // v2 clients subscribe by channel ID, and receive message data by channel ID.
+    tx.send(Message::from(&v2::client::Subscribe::new([channel_id])))
+        .await
+        .expect("Failed to send subscribe");
+    assert_eventually(|| recording_listener.take_subscribe().len() == 1).await;
+
+    channel.log_with_meta(b"hello", PartialMetadata::with_log_time(42));
+    assert_eq!(
+        recv(&mut rx).await,
+        v2::server::ServerMessage::MessageData(v2::server::MessageData::new(
+            channel_id,
+            42,
+            b"hello".to_vec()
+        ))
+    );
+
+    tx.send(Message::from(&v2::client::Unsubscribe::new([channel_id])))
+        .await
+        .expect("Failed to send unsubscribe");
+    assert_eventually(|| recording_listener.take_unsubscribe().len() == 1).await;
+
+    let _ = server.stop();
+}
+
 #[traced_test]
 #[tokio::test]
 async fn test_advertise_to_client() {
@@ -535,9 +777,7 @@ async fn test_log_only_to_subscribers() {
     assert_eq!(unsubscriptions[0].1.topic, ch1.topic());
     assert_eq!(unsubscriptions[1].1.topic, ch2.topic());
 
-    let metadata = PartialMetadata {
-        log_time: Some(123456),
-    };
+    let metadata = PartialMetadata::with_log_time(123456);
     ch3.log_with_meta(b"channel3", metadata);
     ch2.log_with_meta(b"channel2", metadata);
     ch1.log_with_meta(b"channel1", metadata);
@@ -1598,6 +1838,54 @@ async fn test_broadcast_time() {
     assert_eq!(msg.timestamp, 42);
 }
 
+#[tokio::test]
+async fn test_broadcast_clock_time() {
+    let ctx = Context::new();
+    ctx.set_clock(Arc::new(crate::ManualClock::new(1234u64)));
+    let server = create_server(
+        &ctx,
+        ServerOptions {
+            clock_broadcast_interval: Some(Duration::from_millis(10)),
+            ..Default::default()
+        },
+    );
+    let addr = server
+        .start("127.0.0.1", 0)
+        .await
+        .expect("Failed to start server");
+
+    let mut client = WebSocketClient::connect(format!("{addr}"))
+        .await
+        .expect("failed to connect");
+    expect_recv!(client, ServerMessage::ServerInfo);
+
+    // The time capability is implied, and the clock's time is broadcast periodically.
+    let msg = expect_recv!(client, ServerMessage::Time);
+    assert_eq!(msg.timestamp, 1234);
+}
+
+#[tokio::test]
+async fn test_broadcast_clock_time_zero_interval() {
+    let ctx = Context::new();
+    ctx.set_clock(Arc::new(crate::ManualClock::new(1234u64)));
+    // A zero interval would panic in the broadcast task; the builder clamps it.
+    let server = crate::WebSocketServer::new()
+        .context(&ctx)
+        .bind("127.0.0.1", 0)
+        .broadcast_clock_time(Duration::ZERO)
+        .start()
+        .await
+        .expect("Failed to start server");
+
+    let mut client = WebSocketClient::connect(format!("127.0.0.1:{}", server.port()))
+        .await
+        .expect("failed to connect");
+    expect_recv!(client, ServerMessage::ServerInfo);
+    let msg = expect_recv!(client, ServerMessage::Time);
+    assert_eq!(msg.timestamp, 1234);
+    server.stop().wait().await;
+}
+
 struct RecordingPlaybackControlListener {
     playback_request: Mutex<Option<PlaybackControlRequest>>,
 }
@@ -1772,7 +2060,56 @@ async fn test_channel_filter() {
 
     ch2.log(b"{}");
     let result = client.recv().await;
-    assert!(matches!(result, Err(WebSocketClientError::Timeout(_))));
+    assert!(matches!(result, Err(WebSocketClientError::Timeout)));
+}
+
+#[tokio::test]
+async fn test_message_filter() {
+    struct Filter;
+    impl SinkMessageFilter for Filter {
+        fn should_log(&self, _channel: &RawChannel, msg: &[u8], _metadata: &Metadata) -> bool {
+            msg != b"skip"
+        }
+    }
+
+    let ctx = Context::new();
+    let server = create_server(
+        &ctx,
+        ServerOptions {
+            message_filter: Some(Arc::new(Filter)),
+            ..Default::default()
+        },
+    );
+    let addr = server
+        .start("127.0.0.1", 0)
+        .await
+        .expect("Failed to start server");
+
+    let mut client = WebSocketClient::connect(format!("{addr}"))
+        .await
+        .expect("failed to connect");
+    expect_recv!(client, ServerMessage::ServerInfo);
+
+    let ch = new_channel("/1", &ctx);
+    expect_recv!(client, ServerMessage::Advertise);
+
+    client
+        .send(&Subscribe::new([Subscription {
+            id: 1,
+            channel_id: ch.id().into(),
+        }]))
+        .await
+        .expect("Failed to subscribe");
+    assert_eventually(|| dbg!(ch.num_sinks()) == 1).await;
+
+    // The filtered message is dropped.
+    ch.log(b"skip");
+    ch.log(b"{}");
+    let msg = expect_recv!(client, ServerMessage::MessageData);
+    assert_eq!(msg.data.as_ref(), b"{}");
+
+    let result = client.recv().await;
+    assert!(matches!(result, Err(WebSocketClientError::Timeout)));
 }
 
 #[tokio::test]
@@ -1899,3 +2236,170 @@ async fn test_ranged_playback_without_time_range() {
 
     create_server(&ctx, options);
 }
+
+#[traced_test]
+#[tokio::test]
+async fn test_connection_authorizer_rejects_client() {
+    let ctx = Context::new();
+    let server = create_server(
+        &ctx,
+        ServerOptions {
+            connection_authorizer: Some(Arc::new(ConnectionAuthorizerFn(
+                |req: &ConnectionRequest<'_>| match req.query_param("token").as_deref() {
+                    Some("secret") => Ok(ClientPermissions::all()),
+                    Some(_) => Err(AuthorizationError::forbidden("Invalid token")),
+                    None => Err(AuthorizationError::unauthorized("Missing token")),
+                },
+            ))),
+            ..Default::default()
+        },
+    );
+    let addr = server
+        .start("127.0.0.1", 0)
+        .await
+        .expect("Failed to start server");
+
+    let request = |query: &str| {
+        let mut request = format!("ws://{addr}/{query}")
+            .into_client_request()
+            .expect("Failed to build request");
+        request.headers_mut().insert(
+            "sec-websocket-protocol",
+            HeaderValue::from_static(SUBPROTOCOL),
+        );
+        request
+    };
+
+    let result = tokio_tungstenite::connect_async(request("")).await;
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "HTTP error: 401 Unauthorized"
+    );
+    let result = tokio_tungstenite::connect_async(request("?token=wrong")).await;
+    assert_eq!(result.unwrap_err().to_string(), "HTTP error: 403 Forbidden");
+    assert!(logs_contain("Rejected client"));
+
+    let (mut stream, _) = tokio_tungstenite::connect_async(request("?token=secret"))
+        .await
+        .expect("Failed to connect");
+    let msg = stream.next().await.unwrap().expect("Failed to recv");
+    assert_matches!(
+        ServerMessage::try_from(&msg),
+        Ok(ServerMessage::ServerInfo(_))
+    );
+
+    let _ = server.stop();
+}
+
+#[traced_test]
+#[tokio::test]
+async fn test_client_permissions_enforced() {
+    let recording_listener = Arc::new(RecordingServerListener::new());
+    let svc = Service::builder("/svc", ServiceSchema::new("plain")).handler_fn(svc_unreachable);
+    let svc_id = u32::from(svc.id());
+
+    let ctx = Context::new();
+    let allowed = new_channel("/allowed", &ctx);
+    let denied = new_channel("/denied", &ctx);
+    let server = create_server(
+        &ctx,
+        ServerOptions {
+            capabilities: Some(HashSet::from([
+                Capability::ClientPublish,
+                Capability::Parameters,
+            ])),
+            supported_encodings: Some(HashSet::from(["json".to_string(), "raw".to_string()])),
+            services: HashMap::from([(svc.name().to_string(), svc)]),
+            listener: Some(recording_listener.clone()),
+            connection_authorizer: Some(Arc::new(ConnectionAuthorizerFn(
+                |_: &ConnectionRequest<'_>| {
+                    Ok(ClientPermissions::all()
+                        .subscribe_topics(["/allowed"])
+                        .publish_topics(["/input/*"])
+                        .services(["/other"])
+                        .write_parameters(false))
+                },
+            ))),
+            ..Default::default()
+        },
+    );
+    let addr = server
+        .start("127.0.0.1", 0)
+        .await
+        .expect("Failed to start server");
+
+    let mut client = WebSocketClient::connect(format!("{addr}"))
+        .await
+        .expect("Failed to connect");
+    expect_recv!(client, ServerMessage::ServerInfo);
+    expect_recv!(client, ServerMessage::Advertise);
+    expect_recv!(client, ServerMessage::AdvertiseServices);
+
+    // Subscriptions to topics which are not permitted are rejected.
+    client
+        .send(&Subscribe::new([
+            Subscription::new(1, u64::from(allowed.id())),
+            Subscription::new(2, u64::from(denied.id())),
+        ]))
+        .await
+        .expect("Failed to send subscribe");
+    assert_eq!(
+        expect_recv!(client, ServerMessage::Status),
+        Status::error("Not permitted to subscribe to topic: /denied")
+    );
+    assert_eventually(|| recording_listener.take_subscribe().len() == 1).await;
+
+    // Advertisements on topics which are not permitted are rejected.
+    client
+        .send(&Advertise::new([
+            client::advertise::Channel::builder(1, "/input/a", "json")
+                .build()
+                .unwrap(),
+            client::advertise::Channel::builder(2, "/other", "json")
+                .build()
+                .unwrap(),
+        ]))
+        .await
+        .expect("Failed to send advertisement");
+    assert_eq!(
+        expect_recv!(client, ServerMessage::Status),
+        Status::error("Not permitted to publish to topic: /other; ignoring advertisement")
+    );
+    assert_eventually(|| recording_listener.client_advertise_len() == 1).await;
+
+    // Calls to services which are not permitted are rejected.
+    client
+        .send(&ServiceCallRequest {
+            service_id: svc_id,
+            call_id: 1,
+            encoding: "raw".into(),
+            payload: b"payload".into(),
+        })
+        .await
+        .expect("Failed to send service call");
+    assert_eq!(
+        expect_recv!(client, ServerMessage::ServiceCallFailure),
+        ServiceCallFailure {
+            service_id: svc_id,
+            call_id: 1,
+            message: "Not permitted to call service: /svc".into(),
+        }
+    );
+    assert_eq!(
+        expect_recv!(client, ServerMessage::Status),
+        Status::error("Not permitted to call service: /svc")
+    );
+
+    // Parameters may be read, but not written.
+    client
+        .send(&SetParameters::new([Parameter::bool("foo", true)]).with_id("1"))
+        .await
+        .expect("Failed to send set parameters");
+    assert_eq!(
+        expect_recv!(client, ServerMessage::Status),
+        Status::error("Not permitted to set parameters")
+    );
+    assert!(recording_listener.take_parameters_set().is_empty());
+
+    let _ = server.stop();
+}
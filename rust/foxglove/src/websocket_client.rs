@@ -1,37 +1,114 @@
+//! A client for Foxglove WebSocket servers.
+//!
+//! See [`WebSocketConnection`] for details.
+
 use std::time::Duration;
 
 use futures_util::{SinkExt, StreamExt};
 #[cfg(feature = "tls")]
-use rcgen::Certificate;
+use rcgen::{Certificate, KeyPair};
 use tokio::net::TcpStream;
 #[cfg(feature = "tls")]
-use tokio_rustls::rustls;
+use tokio_rustls::rustls::{self, pki_types::PrivatePkcs8KeyDer};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::tungstenite::http::HeaderValue;
 use tokio_tungstenite::tungstenite::{self, Message};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
 use crate::websocket::handshake::SUBPROTOCOL;
+use crate::websocket::Capability;
 
 use super::ws_protocol::server::ServerMessage;
 use super::ws_protocol::ParseError;
 
-#[doc(hidden)]
+mod connection;
+
+pub use connection::{
+    ClientPublisher, RemoteChannel, RemoteMessage, RemoteServerInfo, RemoteService,
+    RemoteSubscription, WebSocketConnection,
+};
+
+/// An error returned by a WebSocket client.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum WebSocketClientError {
+    /// The server closed the connection unexpectedly.
     #[error("unexpected end of stream")]
     UnexpectedEndOfStream,
+    /// The server did not accept a supported subprotocol.
     #[error("invalid subprotocol")]
     InvalidSubprotocol,
-    #[error(transparent)]
-    ParseError(#[from] ParseError),
-    #[error(transparent)]
-    Tungstenite(#[from] tungstenite::Error),
-    #[error(transparent)]
-    Timeout(#[from] tokio::time::error::Elapsed),
+    /// The server sent an unexpected message.
+    #[error("unexpected message")]
+    UnexpectedMessage,
+    /// The connection is closed.
+    #[error("disconnected")]
+    Disconnected,
+    /// The server does not advertise a channel with this topic.
+    #[error("unknown topic: {0}")]
+    UnknownTopic(String),
+    /// The server does not advertise a channel with this ID.
+    #[error("unknown channel: {0}")]
+    UnknownChannel(u64),
+    /// The client is already subscribed to this topic.
+    #[error("already subscribed to topic: {0}")]
+    AlreadySubscribed(String),
+    /// The client is not subscribed to this topic.
+    #[error("not subscribed to topic: {0}")]
+    NotSubscribed(String),
+    /// The server denied a subscription.
+    #[error("subscription denied: {0}")]
+    SubscriptionDenied(String),
+    /// The server does not advertise a service with this name.
+    #[error("unknown service: {0}")]
+    UnknownService(String),
+    /// The server does not support the capability required by the request.
+    #[error("server does not support capability: {0:?}")]
+    UnsupportedCapability(Capability),
+    /// The client channel could not be advertised.
+    #[error("invalid channel: {0}")]
+    InvalidChannel(String),
+    /// The server returned an error for a service call.
+    #[error("service call failed: {0}")]
+    ServiceCallFailed(String),
+    /// The server returned an error for a parameter request.
+    #[error("parameter request failed: {0}")]
+    ParameterRequestFailed(String),
+    /// The server returned an error for an asset request.
+    #[error("fetch asset failed: {0}")]
+    FetchAssetFailed(String),
+    /// A server message could not be parsed.
+    #[error("invalid server message: {0}")]
+    InvalidMessage(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// A WebSocket error.
+    #[error("{0}")]
+    WebSocket(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// A request timed out.
+    #[error("request timed out")]
+    Timeout,
+    /// A TLS error.
     #[cfg(feature = "tls")]
-    #[error(transparent)]
-    Rustls(#[from] rustls::Error),
+    #[error("{0}")]
+    Tls(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+impl From<ParseError> for WebSocketClientError {
+    fn from(err: ParseError) -> Self {
+        Self::InvalidMessage(Box::new(err))
+    }
+}
+
+impl From<tungstenite::Error> for WebSocketClientError {
+    fn from(err: tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(err))
+    }
+}
+
+#[cfg(feature = "tls")]
+impl From<rustls::Error> for WebSocketClientError {
+    fn from(err: rustls::Error) -> Self {
+        Self::Tls(Box::new(err))
+    }
 }
 
 #[doc(hidden)]
@@ -46,7 +123,29 @@ impl WebSocketClient {
         addr: impl AsRef<str>,
         trusted_cert: Certificate,
     ) -> Result<Self, WebSocketClientError> {
-        let mut request = format!("wss://{addr}/", addr = addr.as_ref())
+        Self::connect_secure_inner(addr.as_ref(), trusted_cert, None).await
+    }
+
+    /// Connects to a server presenting a client certificate, and validates the handshake
+    /// response.
+    #[cfg(feature = "tls")]
+    pub async fn connect_secure_with_client_cert(
+        addr: impl AsRef<str>,
+        trusted_cert: Certificate,
+        client_cert: &Certificate,
+        client_key: &KeyPair,
+    ) -> Result<Self, WebSocketClientError> {
+        Self::connect_secure_inner(addr.as_ref(), trusted_cert, Some((client_cert, client_key)))
+            .await
+    }
+
+    #[cfg(feature = "tls")]
+    async fn connect_secure_inner(
+        addr: &str,
+        trusted_cert: Certificate,
+        client_identity: Option<(&Certificate, &KeyPair)>,
+    ) -> Result<Self, WebSocketClientError> {
+        let mut request = format!("wss://{addr}/")
             .into_client_request()
             .expect("Failed to build request");
 
@@ -59,9 +158,14 @@ impl WebSocketClient {
         root_cert_store
             .add(trusted_cert.der().clone().into_owned())
             .map_err(WebSocketClientError::from)?;
-        let config = rustls::ClientConfig::builder()
-            .with_root_certificates(root_cert_store)
-            .with_no_client_auth();
+        let builder = rustls::ClientConfig::builder().with_root_certificates(root_cert_store);
+        let config = match client_identity {
+            Some((cert, key)) => builder.with_client_auth_cert(
+                vec![cert.der().clone().into_owned()],
+                PrivatePkcs8KeyDer::from(key.serialize_der()).into(),
+            )?,
+            None => builder.with_no_client_auth(),
+        };
 
         let connector = tokio_tungstenite::Connector::Rustls(std::sync::Arc::new(config));
 
@@ -112,7 +216,9 @@ impl WebSocketClient {
 
     /// Receives and parses a message from the server.
     pub async fn recv(&mut self) -> Result<ServerMessage<'_>, WebSocketClientError> {
-        let msg = tokio::time::timeout(Duration::from_secs(1), self.recv_msg()).await??;
+        let msg = tokio::time::timeout(Duration::from_secs(1), self.recv_msg())
+            .await
+            .map_err(|_| WebSocketClientError::Timeout)??;
         let msg = ServerMessage::try_from(&msg)?;
         Ok(msg.into_owned())
     }
@@ -0,0 +1,1347 @@
+//! A client connection to a Foxglove WebSocket server.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::protocol::v1::client::{
+    self, FetchAsset, GetParameters, ServiceCallRequest, SetParameters, Subscribe, Subscription,
+    Unadvertise, Unsubscribe,
+};
+use crate::protocol::v1::schema;
+use crate::protocol::v1::server::fetch_asset_response::Payload;
+use crate::protocol::v1::server::{
+    advertise, advertise_services, server_info, ServerInfo, ServerMessage,
+};
+use crate::throttler::Throttler;
+use crate::websocket::handshake::SUBPROTOCOL;
+use crate::websocket::{
+    Capability, Parameter, StatusLevel, SUBSCRIBE_DENIED, WRITE_PARAMETERS_DENIED,
+};
+use crate::{Decode, Schema};
+
+use super::WebSocketClientError;
+
+/// The subprotocol used by servers implementing the original ws-protocol specification.
+const LEGACY_SUBPROTOCOL: &str = "foxglove.websocket.v1";
+
+/// The number of messages that may be queued for each subscription.
+const SUBSCRIPTION_QUEUE_CAPACITY: usize = 1024;
+
+/// The default time to wait for the server to answer a parameter request.
+const DEFAULT_PARAMETER_TIMEOUT: Duration = Duration::from_secs(5);
+
+static THROTTLER: Mutex<Throttler> = Mutex::new(Throttler::new(Duration::from_secs(30)));
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A channel advertised by a remote server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteChannel {
+    id: u64,
+    topic: String,
+    message_encoding: String,
+    schema: Option<Schema>,
+}
+
+impl RemoteChannel {
+    /// Returns the server's ID for this channel.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the topic of the channel.
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Returns the message encoding of the channel.
+    pub fn message_encoding(&self) -> &str {
+        &self.message_encoding
+    }
+
+    /// Returns the schema of the channel, if it has one.
+    pub fn schema(&self) -> Option<&Schema> {
+        self.schema.as_ref()
+    }
+}
+
+impl From<advertise::Channel<'_>> for RemoteChannel {
+    fn from(channel: advertise::Channel<'_>) -> Self {
+        let schema = match channel.schema_encoding.as_deref() {
+            Some(encoding) if !encoding.is_empty() => match channel.decode_schema() {
+                Ok(data) => Some(Schema::new(channel.schema_name.as_ref(), encoding, data)),
+                Err(err) => {
+                    tracing::warn!("Failed to decode schema for {}: {err}", channel.topic);
+                    None
+                }
+            },
+            _ => None,
+        };
+        Self {
+            id: channel.id,
+            topic: channel.topic.into_owned(),
+            message_encoding: channel.encoding.into_owned(),
+            schema,
+        }
+    }
+}
+
+/// Information about a remote server.
+#[derive(Debug, Clone)]
+pub struct RemoteServerInfo {
+    name: String,
+    capabilities: Vec<Capability>,
+    supported_encodings: Vec<String>,
+    metadata: HashMap<String, String>,
+    session_id: Option<String>,
+}
+
+impl RemoteServerInfo {
+    /// Returns the name of the server.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the capabilities advertised by the server.
+    pub fn capabilities(&self) -> &[Capability] {
+        &self.capabilities
+    }
+
+    /// Returns true if the server advertises the capability.
+    pub fn has_capability(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Returns the encodings supported for client publishing and service calls.
+    pub fn supported_encodings(&self) -> &[String] {
+        &self.supported_encodings
+    }
+
+    /// Returns the server's metadata.
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+
+    /// Returns the server's session ID, if it has one.
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+}
+
+/// Returns the capability corresponding to a capability advertised by a server, if any.
+///
+/// `ParametersSubscribe` is folded into [`Capability::Parameters`].
+fn capability_from_protocol(capability: &server_info::Capability) -> Option<Capability> {
+    match capability {
+        server_info::Capability::ClientPublish => Some(Capability::ClientPublish),
+        server_info::Capability::Parameters => Some(Capability::Parameters),
+        server_info::Capability::ParametersSubscribe => None,
+        server_info::Capability::Time => Some(Capability::Time),
+        server_info::Capability::Services => Some(Capability::Services),
+        server_info::Capability::ConnectionGraph => Some(Capability::ConnectionGraph),
+        server_info::Capability::Assets => Some(Capability::Assets),
+        server_info::Capability::RangedPlayback => Some(Capability::RangedPlayback),
+    }
+}
+
+impl From<ServerInfo> for RemoteServerInfo {
+    fn from(info: ServerInfo) -> Self {
+        let mut capabilities = vec![];
+        for capability in info
+            .capabilities
+            .iter()
+            .filter_map(capability_from_protocol)
+        {
+            if !capabilities.contains(&capability) {
+                capabilities.push(capability);
+            }
+        }
+        Self {
+            name: info.name,
+            capabilities,
+            supported_encodings: info.supported_encodings,
+            metadata: info.metadata,
+            session_id: info.session_id,
+        }
+    }
+}
+
+/// A service advertised by a remote server.
+#[derive(Debug, Clone)]
+pub struct RemoteService {
+    id: u32,
+    name: String,
+    service_type: String,
+}
+
+impl RemoteService {
+    /// Returns the server's ID for this service.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the name of the service.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of the service.
+    pub fn service_type(&self) -> &str {
+        &self.service_type
+    }
+}
+
+impl From<advertise_services::Service<'_>> for RemoteService {
+    fn from(service: advertise_services::Service<'_>) -> Self {
+        Self {
+            id: service.id,
+            name: service.name.into_owned(),
+            service_type: service.r#type.into_owned(),
+        }
+    }
+}
+
+/// A message received on a [`RemoteSubscription`].
+#[derive(Debug, Clone)]
+pub struct RemoteMessage {
+    log_time: u64,
+    data: Bytes,
+}
+
+impl RemoteMessage {
+    /// Returns the log time of the message, in nanoseconds since the epoch.
+    pub fn log_time(&self) -> u64 {
+        self.log_time
+    }
+
+    /// Returns the encoded message data.
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// Decodes the message data.
+    pub fn decode<T: Decode>(&self) -> Result<T, T::Error> {
+        T::decode(self.data.clone())
+    }
+}
+
+/// A subscription to a channel on a remote server.
+///
+/// Created by [`WebSocketConnection::subscribe`]. Up to 1024 messages are queued until they are
+/// received; once the queue is full, newly arriving messages are dropped. Dropping the
+/// subscription unsubscribes from the channel.
+#[derive(Debug)]
+pub struct RemoteSubscription {
+    inner: Weak<Inner>,
+    id: u32,
+    channel: RemoteChannel,
+    receiver: mpsc::Receiver<RemoteMessage>,
+    end: oneshot::Receiver<EndReason>,
+    reason: Option<EndReason>,
+}
+
+impl RemoteSubscription {
+    /// Returns the subscribed channel.
+    pub fn channel(&self) -> &RemoteChannel {
+        &self.channel
+    }
+
+    /// Receives the next message.
+    ///
+    /// Returns `None` once the subscription has ended and its queued messages have been received.
+    /// [`RemoteSubscription::error`] then describes why it ended.
+    pub async fn recv(&mut self) -> Option<RemoteMessage> {
+        let msg = self.receiver.recv().await;
+        if msg.is_none() && self.reason.is_none() {
+            self.reason = Some(self.end.try_recv().unwrap_or(EndReason::Disconnected));
+        }
+        msg
+    }
+
+    /// Receives and decodes the next message.
+    ///
+    /// Returns `None` once the subscription has ended. See [`RemoteSubscription::recv`].
+    pub async fn recv_decoded<T: Decode>(&mut self) -> Option<Result<T, T::Error>> {
+        self.recv().await.map(|msg| msg.decode())
+    }
+
+    /// Returns why the subscription ended, once [`RemoteSubscription::recv`] has returned `None`:
+    ///
+    /// - [`WebSocketClientError::NotSubscribed`] if the client unsubscribed.
+    /// - [`WebSocketClientError::UnknownTopic`] if the server unadvertised the channel.
+    /// - [`WebSocketClientError::SubscriptionDenied`] if the server did not permit the
+    ///   subscription.
+    /// - [`WebSocketClientError::Disconnected`] if the connection was closed.
+    pub fn error(&self) -> Option<WebSocketClientError> {
+        self.reason
+            .as_ref()
+            .map(|reason| reason.to_error(&self.channel.topic))
+    }
+}
+
+impl Drop for RemoteSubscription {
+    fn drop(&mut self) {
+        let Some(inner) = self.inner.upgrade() else {
+            return;
+        };
+        // If the subscription has already ended, there is nothing to unsubscribe.
+        if inner.state.lock().subscriptions.remove(&self.id).is_some() {
+            inner.send_in_background((&Unsubscribe::new([self.id])).into());
+        }
+    }
+}
+
+/// The reason a subscription ended.
+#[derive(Debug, Clone)]
+enum EndReason {
+    Unsubscribed,
+    Unadvertised,
+    Denied(String),
+    Disconnected,
+}
+
+impl EndReason {
+    fn to_error(&self, topic: &str) -> WebSocketClientError {
+        match self {
+            Self::Unsubscribed => WebSocketClientError::NotSubscribed(topic.to_string()),
+            Self::Unadvertised => WebSocketClientError::UnknownTopic(topic.to_string()),
+            Self::Denied(message) => WebSocketClientError::SubscriptionDenied(message.clone()),
+            Self::Disconnected => WebSocketClientError::Disconnected,
+        }
+    }
+}
+
+/// A channel advertised by the client, for publishing messages to a remote server.
+///
+/// Created by [`WebSocketConnection::advertise`].
+#[derive(Debug)]
+pub struct ClientPublisher {
+    inner: Arc<Inner>,
+    id: u32,
+    topic: String,
+}
+
+impl ClientPublisher {
+    /// Returns the client's ID for this channel.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the topic of the channel.
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Publishes an encoded message on the channel.
+    pub async fn publish(&self, data: &[u8]) -> Result<(), WebSocketClientError> {
+        let msg = client::MessageData::new(self.id, data);
+        self.inner.send((&msg).into()).await
+    }
+
+    /// Unadvertises the channel.
+    pub async fn unadvertise(self) -> Result<(), WebSocketClientError> {
+        self.inner.send((&Unadvertise::new([self.id])).into()).await
+    }
+}
+
+struct SubscriptionState {
+    channel_id: u64,
+    topic: String,
+    sender: mpsc::Sender<RemoteMessage>,
+    end: oneshot::Sender<EndReason>,
+}
+
+impl SubscriptionState {
+    /// Ends the subscription, once its queued messages have been received.
+    ///
+    /// A subscription which is dropped without being ended reports that it was disconnected.
+    fn end(self, reason: EndReason) {
+        self.end.send(reason).ok();
+    }
+}
+
+type Reply<T> = oneshot::Sender<Result<T, String>>;
+
+struct ParameterRequest {
+    reply: Reply<Vec<Parameter>>,
+    write: bool,
+}
+
+struct State {
+    connected: bool,
+    server_info: RemoteServerInfo,
+    channels: HashMap<u64, RemoteChannel>,
+    services: HashMap<u32, RemoteService>,
+    subscriptions: HashMap<u32, SubscriptionState>,
+    service_calls: HashMap<u32, Reply<Bytes>>,
+    parameter_requests: HashMap<String, ParameterRequest>,
+    asset_requests: HashMap<u32, Reply<Bytes>>,
+}
+
+impl State {
+    fn new(server_info: RemoteServerInfo) -> Self {
+        Self {
+            connected: true,
+            server_info,
+            channels: HashMap::new(),
+            services: HashMap::new(),
+            subscriptions: HashMap::new(),
+            service_calls: HashMap::new(),
+            parameter_requests: HashMap::new(),
+            asset_requests: HashMap::new(),
+        }
+    }
+
+    fn channel_by_topic(&self, topic: &str) -> Option<&RemoteChannel> {
+        self.channels.values().find(|c| c.topic == topic)
+    }
+
+    fn service_by_name(&self, name: &str) -> Option<&RemoteService> {
+        self.services.values().find(|s| s.name == name)
+    }
+
+    fn require(&self, capability: Capability) -> Result<(), WebSocketClientError> {
+        if self.server_info.has_capability(capability) {
+            Ok(())
+        } else {
+            Err(WebSocketClientError::UnsupportedCapability(capability))
+        }
+    }
+
+    /// Ends the subscriptions matching the predicate, and returns their IDs.
+    fn end_subscriptions(
+        &mut self,
+        reason: &EndReason,
+        f: impl Fn(&SubscriptionState) -> bool,
+    ) -> Vec<u32> {
+        let ids: Vec<u32> = self
+            .subscriptions
+            .iter()
+            .filter(|(_, sub)| f(sub))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &ids {
+            if let Some(sub) = self.subscriptions.remove(id) {
+                sub.end(reason.clone());
+            }
+        }
+        ids
+    }
+
+    /// Handles an error status from the server, which may deny a request.
+    ///
+    /// The server does not say which request an error refers to, so denials are recognized by
+    /// their message.
+    fn on_error_status(&mut self, message: &str) {
+        if let Some(topic) = message.strip_prefix(SUBSCRIBE_DENIED) {
+            let reason = EndReason::Denied(message.to_string());
+            self.end_subscriptions(&reason, |sub| sub.topic == topic);
+        } else if message == WRITE_PARAMETERS_DENIED {
+            let ids: Vec<String> = self
+                .parameter_requests
+                .iter()
+                .filter(|(_, req)| req.write)
+                .map(|(id, _)| id.clone())
+                .collect();
+            for id in ids {
+                if let Some(req) = self.parameter_requests.remove(&id) {
+                    req.reply.send(Err(message.to_string())).ok();
+                }
+            }
+        }
+    }
+
+    /// Marks the connection as closed, ending subscriptions and failing pending requests.
+    fn disconnect(&mut self) {
+        self.connected = false;
+        self.subscriptions.clear();
+        self.service_calls.clear();
+        self.parameter_requests.clear();
+        self.asset_requests.clear();
+    }
+}
+
+struct Inner {
+    sink: tokio::sync::Mutex<SplitSink<WsStream, Message>>,
+    state: Mutex<State>,
+    changed: watch::Sender<()>,
+    next_id: AtomicU32,
+}
+
+impl std::fmt::Debug for Inner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Inner").finish_non_exhaustive()
+    }
+}
+
+impl Inner {
+    fn next_id(&self) -> u32 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    async fn send(&self, msg: Message) -> Result<(), WebSocketClientError> {
+        if !self.state.lock().connected {
+            return Err(WebSocketClientError::Disconnected);
+        }
+        self.sink.lock().await.send(msg).await?;
+        Ok(())
+    }
+
+    fn disconnect(&self) {
+        self.state.lock().disconnect();
+        self.changed.send_replace(());
+    }
+
+    /// Sends a message from a background task, so that the caller does not wait for the sink.
+    ///
+    /// The message is discarded if there is no runtime to run the task on.
+    fn send_in_background(self: &Arc<Self>, msg: Message) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let inner = self.clone();
+        runtime.spawn(async move { inner.send(msg).await.ok() });
+    }
+
+    async fn run(self: Arc<Self>, mut stream: SplitStream<WsStream>) {
+        while let Some(msg) = stream.next().await {
+            let msg = match msg {
+                Ok(msg) => msg,
+                Err(err) => {
+                    tracing::debug!("Connection error: {err}");
+                    break;
+                }
+            };
+            if msg.is_close() {
+                break;
+            }
+            if !msg.is_text() && !msg.is_binary() {
+                continue;
+            }
+            match ServerMessage::try_from(&msg) {
+                Ok(msg) => self.handle_message(msg),
+                Err(err) => tracing::warn!("Failed to parse server message: {err}"),
+            }
+        }
+        self.disconnect();
+    }
+
+    /// Handles a message from the server.
+    ///
+    /// This runs on the reader task, so it must not wait for the sink.
+    fn handle_message(self: &Arc<Self>, msg: ServerMessage<'_>) {
+        let mut dropped = vec![];
+        let mut changed = false;
+        {
+            let mut state = self.state.lock();
+            match msg {
+                ServerMessage::ServerInfo(info) => {
+                    state.server_info = info.into();
+                    changed = true;
+                }
+                ServerMessage::Advertise(msg) => {
+                    for channel in msg.channels {
+                        state.channels.insert(channel.id, channel.into());
+                    }
+                    changed = true;
+                }
+                ServerMessage::Unadvertise(msg) => {
+                    for id in msg.channel_ids {
+                        state.channels.remove(&id);
+                        state.end_subscriptions(&EndReason::Unadvertised, |sub| {
+                            sub.channel_id == id
+                        });
+                    }
+                    changed = true;
+                }
+                ServerMessage::MessageData(msg) => {
+                    if let Some(sub) = state.subscriptions.get(&msg.subscription_id) {
+                        let message = RemoteMessage {
+                            log_time: msg.log_time,
+                            data: Bytes::copy_from_slice(&msg.data),
+                        };
+                        match sub.sender.try_send(message) {
+                            Ok(()) => (),
+                            Err(TrySendError::Full(_)) => {
+                                if THROTTLER.lock().try_acquire() {
+                                    tracing::warn!(
+                                        "Subscription queue for {} is full, dropping messages",
+                                        sub.topic
+                                    );
+                                }
+                            }
+                            Err(TrySendError::Closed(_)) => {
+                                state.subscriptions.remove(&msg.subscription_id);
+                                dropped.push(msg.subscription_id);
+                            }
+                        }
+                    }
+                }
+                ServerMessage::AdvertiseServices(msg) => {
+                    for service in msg.services {
+                        state.services.insert(service.id, service.into());
+                    }
+                    changed = true;
+                }
+                ServerMessage::UnadvertiseServices(msg) => {
+                    for id in msg.service_ids {
+                        state.services.remove(&id);
+                    }
+                    changed = true;
+                }
+                ServerMessage::ServiceCallResponse(msg) => {
+                    if let Some(reply) = state.service_calls.remove(&msg.call_id) {
+                        reply.send(Ok(Bytes::copy_from_slice(&msg.payload))).ok();
+                    }
+                }
+                ServerMessage::ServiceCallFailure(msg) => {
+                    if let Some(reply) = state.service_calls.remove(&msg.call_id) {
+                        reply.send(Err(msg.message)).ok();
+                    }
+                }
+                ServerMessage::ParameterValues(msg) => {
+                    if let Some(req) = msg.id.and_then(|id| state.parameter_requests.remove(&id)) {
+                        req.reply.send(Ok(msg.parameters)).ok();
+                    }
+                }
+                ServerMessage::FetchAssetResponse(msg) => {
+                    if let Some(reply) = state.asset_requests.remove(&msg.request_id) {
+                        let result = match msg.payload {
+                            Payload::AssetData(data) => Ok(Bytes::copy_from_slice(&data)),
+                            Payload::ErrorMessage(message) => Err(message.into_owned()),
+                        };
+                        reply.send(result).ok();
+                    }
+                }
+                ServerMessage::Status(msg) => {
+                    tracing::debug!("Server status ({:?}): {}", msg.level, msg.message);
+                    if msg.level == StatusLevel::Error {
+                        state.on_error_status(&msg.message);
+                    }
+                }
+                _ => (),
+            }
+        }
+        if changed {
+            self.changed.send_replace(());
+        }
+        if !dropped.is_empty() {
+            self.send_in_background((&Unsubscribe::new(dropped)).into());
+        }
+    }
+}
+
+/// An async client for a Foxglove WebSocket server.
+///
+/// The connection tracks the channels and services advertised by the server, and supports
+/// subscribing to channels, calling services, reading and writing parameters, publishing on
+/// client channels, and fetching assets. It is useful for integration tests and headless tools.
+///
+/// Server messages are processed by a background task on the current tokio runtime, which is
+/// stopped when the connection is dropped.
+///
+/// ### Example
+///
+/// ```no_run
+/// # async fn example() -> Result<(), foxglove::WebSocketClientError> {
+/// use foxglove::WebSocketConnection;
+///
+/// let conn = WebSocketConnection::connect("ws://127.0.0.1:8765").await?;
+/// conn.wait_for_channel("/log").await?;
+/// let mut sub = conn.subscribe("/log").await?;
+/// while let Some(msg) = sub.recv().await {
+///     println!("{}: {} bytes", msg.log_time(), msg.data().len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WebSocketConnection {
+    inner: Arc<Inner>,
+    task: JoinHandle<()>,
+    parameter_timeout: Duration,
+}
+
+impl WebSocketConnection {
+    /// Connects to the server at the given URL, such as `ws://127.0.0.1:8765`.
+    ///
+    /// Returns once the server has sent its info. Channel and service advertisements may arrive
+    /// afterwards; use [`WebSocketConnection::wait_for_channel`] and
+    /// [`WebSocketConnection::wait_for_service`] to wait for them.
+    pub async fn connect(url: impl AsRef<str>) -> Result<Self, WebSocketClientError> {
+        let mut request = url.as_ref().into_client_request()?;
+        request.headers_mut().insert(
+            "sec-websocket-protocol",
+            HeaderValue::from_str(&format!("{SUBPROTOCOL}, {LEGACY_SUBPROTOCOL}"))
+                .expect("valid header value"),
+        );
+
+        let (mut stream, response) = tokio_tungstenite::connect_async(request).await?;
+        let subprotocol = response
+            .headers()
+            .get("sec-websocket-protocol")
+            .and_then(|v| v.to_str().ok());
+        if !matches!(subprotocol, Some(SUBPROTOCOL | LEGACY_SUBPROTOCOL)) {
+            return Err(WebSocketClientError::InvalidSubprotocol);
+        }
+
+        // The server sends its info before any other message.
+        let server_info = loop {
+            let msg = stream
+                .next()
+                .await
+                .ok_or(WebSocketClientError::UnexpectedEndOfStream)??;
+            if msg.is_close() {
+                return Err(WebSocketClientError::UnexpectedEndOfStream);
+            }
+            if !msg.is_text() && !msg.is_binary() {
+                continue;
+            }
+            match ServerMessage::try_from(&msg)? {
+                ServerMessage::ServerInfo(info) => break info,
+                _ => return Err(WebSocketClientError::UnexpectedMessage),
+            }
+        };
+
+        let (sink, stream) = stream.split();
+        let inner = Arc::new(Inner {
+            sink: tokio::sync::Mutex::new(sink),
+            state: Mutex::new(State::new(server_info.into())),
+            changed: watch::Sender::new(()),
+            next_id: AtomicU32::new(1),
+        });
+        let task = tokio::spawn(inner.clone().run(stream));
+        Ok(Self {
+            inner,
+            task,
+            parameter_timeout: DEFAULT_PARAMETER_TIMEOUT,
+        })
+    }
+
+    /// Sets how long to wait for the server to answer a parameter request.
+    ///
+    /// A server does not answer parameter requests unless it has a listener for them, so requests
+    /// fail with [`WebSocketClientError::Timeout`] after this time. The default is 5 seconds.
+    #[must_use]
+    pub fn with_parameter_timeout(mut self, timeout: Duration) -> Self {
+        self.parameter_timeout = timeout;
+        self
+    }
+
+    /// Returns the most recent info sent by the server.
+    pub fn server_info(&self) -> RemoteServerInfo {
+        self.inner.state.lock().server_info.clone()
+    }
+
+    /// Returns true if the connection is still open.
+    pub fn is_connected(&self) -> bool {
+        self.inner.state.lock().connected
+    }
+
+    /// Returns the channels currently advertised by the server.
+    pub fn channels(&self) -> Vec<RemoteChannel> {
+        self.inner.state.lock().channels.values().cloned().collect()
+    }
+
+    /// Returns the services currently advertised by the server.
+    pub fn services(&self) -> Vec<RemoteService> {
+        self.inner.state.lock().services.values().cloned().collect()
+    }
+
+    /// Returns a receiver which is notified when the server's info or advertisements change, or the
+    /// connection is closed.
+    pub(crate) fn watch_changes(&self) -> watch::Receiver<()> {
+        self.inner.changed.subscribe()
+    }
+
+    /// Waits until the server advertises a channel with the given topic.
+    pub async fn wait_for_channel(
+        &self,
+        topic: &str,
+    ) -> Result<RemoteChannel, WebSocketClientError> {
+        self.wait_for(|state| state.channel_by_topic(topic).cloned())
+            .await
+    }
+
+    /// Waits until the server advertises a service with the given name.
+    pub async fn wait_for_service(
+        &self,
+        name: &str,
+    ) -> Result<RemoteService, WebSocketClientError> {
+        self.wait_for(|state| state.service_by_name(name).cloned())
+            .await
+    }
+
+    async fn wait_for<T>(
+        &self,
+        f: impl Fn(&State) -> Option<T>,
+    ) -> Result<T, WebSocketClientError> {
+        let mut changed = self.watch_changes();
+        loop {
+            {
+                let state = self.inner.state.lock();
+                if let Some(value) = f(&state) {
+                    return Ok(value);
+                }
+                if !state.connected {
+                    return Err(WebSocketClientError::Disconnected);
+                }
+            }
+            changed
+                .changed()
+                .await
+                .map_err(|_| WebSocketClientError::Disconnected)?;
+        }
+    }
+
+    /// Subscribes to the channel with the given topic.
+    ///
+    /// The channel must already be advertised by the server. If several channels share the topic,
+    /// one of them is chosen; use [`WebSocketConnection::subscribe_channel`] to subscribe to a
+    /// specific channel. The server does not acknowledge subscriptions, so if it does not permit
+    /// this one, the subscription ends, and [`RemoteSubscription::error`] returns
+    /// [`WebSocketClientError::SubscriptionDenied`].
+    pub async fn subscribe(&self, topic: &str) -> Result<RemoteSubscription, WebSocketClientError> {
+        self.subscribe_with(|state| {
+            state
+                .channel_by_topic(topic)
+                .cloned()
+                .ok_or_else(|| WebSocketClientError::UnknownTopic(topic.to_string()))
+        })
+        .await
+    }
+
+    /// Subscribes to the channel with the given ID.
+    ///
+    /// The channel must already be advertised by the server. See
+    /// [`WebSocketConnection::subscribe`].
+    pub async fn subscribe_channel(
+        &self,
+        channel_id: u64,
+    ) -> Result<RemoteSubscription, WebSocketClientError> {
+        self.subscribe_with(|state| {
+            state
+                .channels
+                .get(&channel_id)
+                .cloned()
+                .ok_or(WebSocketClientError::UnknownChannel(channel_id))
+        })
+        .await
+    }
+
+    /// Subscribes to the channel selected by `f`.
+    async fn subscribe_with(
+        &self,
+        f: impl FnOnce(&State) -> Result<RemoteChannel, WebSocketClientError>,
+    ) -> Result<RemoteSubscription, WebSocketClientError> {
+        let id = self.inner.next_id();
+        let (sender, receiver) = mpsc::channel(SUBSCRIPTION_QUEUE_CAPACITY);
+        let (end_sender, end) = oneshot::channel();
+        let channel = {
+            let mut state = self.inner.state.lock();
+            let channel = f(&state)?;
+            if state
+                .subscriptions
+                .values()
+                .any(|sub| sub.channel_id == channel.id)
+            {
+                return Err(WebSocketClientError::AlreadySubscribed(channel.topic));
+            }
+            state.subscriptions.insert(
+                id,
+                SubscriptionState {
+                    channel_id: channel.id,
+                    topic: channel.topic.clone(),
+                    sender,
+                    end: end_sender,
+                },
+            );
+            channel
+        };
+        let msg = Subscribe::new([Subscription::new(id, channel.id)]);
+        if let Err(err) = self.inner.send((&msg).into()).await {
+            self.inner.state.lock().subscriptions.remove(&id);
+            return Err(err);
+        }
+        Ok(RemoteSubscription {
+            inner: Arc::downgrade(&self.inner),
+            id,
+            channel,
+            receiver,
+            end,
+            reason: None,
+        })
+    }
+
+    /// Unsubscribes from the channels with the given topic.
+    ///
+    /// The corresponding [`RemoteSubscription`]s end once their queued messages have been
+    /// received.
+    pub async fn unsubscribe(&self, topic: &str) -> Result<(), WebSocketClientError> {
+        let ids = self
+            .inner
+            .state
+            .lock()
+            .end_subscriptions(&EndReason::Unsubscribed, |sub| sub.topic == topic);
+        if ids.is_empty() {
+            return Err(WebSocketClientError::NotSubscribed(topic.to_string()));
+        }
+        self.inner.send((&Unsubscribe::new(ids)).into()).await
+    }
+
+    /// Unsubscribes from the channel with the given ID.
+    ///
+    /// See [`WebSocketConnection::unsubscribe`].
+    pub async fn unsubscribe_channel(&self, channel_id: u64) -> Result<(), WebSocketClientError> {
+        let ids = {
+            let mut state = self.inner.state.lock();
+            let ids = state
+                .end_subscriptions(&EndReason::Unsubscribed, |sub| sub.channel_id == channel_id);
+            if ids.is_empty() {
+                let topic = state
+                    .channels
+                    .get(&channel_id)
+                    .map_or_else(|| channel_id.to_string(), |c| c.topic.clone());
+                return Err(WebSocketClientError::NotSubscribed(topic));
+            }
+            ids
+        };
+        self.inner.send((&Unsubscribe::new(ids)).into()).await
+    }
+
+    /// Calls the service with the given name, and returns the response payload.
+    ///
+    /// The service must already be advertised by the server.
+    pub async fn call_service(
+        &self,
+        name: &str,
+        encoding: &str,
+        payload: &[u8],
+    ) -> Result<Bytes, WebSocketClientError> {
+        let call_id = self.inner.next_id();
+        let (reply, response) = oneshot::channel();
+        let service_id = {
+            let mut state = self.inner.state.lock();
+            state.require(Capability::Services)?;
+            let service_id = state
+                .service_by_name(name)
+                .map(|s| s.id)
+                .ok_or_else(|| WebSocketClientError::UnknownService(name.to_string()))?;
+            state.service_calls.insert(call_id, reply);
+            service_id
+        };
+        let msg = ServiceCallRequest {
+            service_id,
+            call_id,
+            encoding: encoding.into(),
+            payload: payload.into(),
+        };
+        if let Err(err) = self.inner.send((&msg).into()).await {
+            self.inner.state.lock().service_calls.remove(&call_id);
+            return Err(err);
+        }
+        response
+            .await
+            .map_err(|_| WebSocketClientError::Disconnected)?
+            .map_err(WebSocketClientError::ServiceCallFailed)
+    }
+
+    /// Gets the values of the named parameters.
+    ///
+    /// If no names are provided, the server returns all of its parameters. See
+    /// [`WebSocketConnection::with_parameter_timeout`] for servers that do not answer.
+    pub async fn get_parameters(
+        &self,
+        names: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<Vec<Parameter>, WebSocketClientError> {
+        let id = self.inner.next_id().to_string();
+        let msg = GetParameters::new(names).with_id(&id);
+        self.parameter_request(id, false, (&msg).into()).await
+    }
+
+    /// Sets parameter values, and returns the updated parameters.
+    ///
+    /// Fails with [`WebSocketClientError::ParameterRequestFailed`] if the server does not permit
+    /// the client to write parameters.
+    pub async fn set_parameters(
+        &self,
+        parameters: Vec<Parameter>,
+    ) -> Result<Vec<Parameter>, WebSocketClientError> {
+        let id = self.inner.next_id().to_string();
+        let msg = SetParameters::new(parameters).with_id(&id);
+        self.parameter_request(id, true, (&msg).into()).await
+    }
+
+    async fn parameter_request(
+        &self,
+        id: String,
+        write: bool,
+        msg: Message,
+    ) -> Result<Vec<Parameter>, WebSocketClientError> {
+        let (reply, response) = oneshot::channel();
+        {
+            let mut state = self.inner.state.lock();
+            state.require(Capability::Parameters)?;
+            state
+                .parameter_requests
+                .insert(id.clone(), ParameterRequest { reply, write });
+        }
+        if let Err(err) = self.inner.send(msg).await {
+            self.inner.state.lock().parameter_requests.remove(&id);
+            return Err(err);
+        }
+        let Ok(response) = tokio::time::timeout(self.parameter_timeout, response).await else {
+            self.inner.state.lock().parameter_requests.remove(&id);
+            return Err(WebSocketClientError::Timeout);
+        };
+        response
+            .map_err(|_| WebSocketClientError::Disconnected)?
+            .map_err(WebSocketClientError::ParameterRequestFailed)
+    }
+
+    /// Advertises a client channel, for publishing messages to the server.
+    ///
+    /// A schema is required for some message encodings, such as `protobuf`.
+    pub async fn advertise(
+        &self,
+        topic: &str,
+        message_encoding: &str,
+        schema: Option<&Schema>,
+    ) -> Result<ClientPublisher, WebSocketClientError> {
+        self.inner.state.lock().require(Capability::ClientPublish)?;
+        let id = self.inner.next_id();
+        let mut builder = client::advertise::Channel::builder(id, topic, message_encoding);
+        if let Some(schema) = schema {
+            builder = builder.with_schema(schema::Schema::new(
+                &schema.name,
+                &schema.encoding,
+                schema.data.as_ref(),
+            ));
+        }
+        let channel = builder
+            .build()
+            .map_err(|err| WebSocketClientError::InvalidChannel(err.to_string()))?;
+        let msg = client::Advertise::new([channel]);
+        self.inner.send((&msg).into()).await?;
+        Ok(ClientPublisher {
+            inner: self.inner.clone(),
+            id,
+            topic: topic.to_string(),
+        })
+    }
+
+    /// Fetches an asset from the server.
+    pub async fn fetch_asset(&self, uri: &str) -> Result<Bytes, WebSocketClientError> {
+        let request_id = self.inner.next_id();
+        let (reply, response) = oneshot::channel();
+        {
+            let mut state = self.inner.state.lock();
+            state.require(Capability::Assets)?;
+            state.asset_requests.insert(request_id, reply);
+        }
+        let msg = FetchAsset::new(request_id, uri);
+        if let Err(err) = self.inner.send((&msg).into()).await {
+            self.inner.state.lock().asset_requests.remove(&request_id);
+            return Err(err);
+        }
+        response
+            .await
+            .map_err(|_| WebSocketClientError::Disconnected)?
+            .map_err(WebSocketClientError::FetchAssetFailed)
+    }
+
+    /// Closes the connection.
+    pub async fn close(self) -> Result<(), WebSocketClientError> {
+        let result = self.inner.sink.lock().await.close().await;
+        self.inner.disconnect();
+        Ok(result?)
+    }
+}
+
+impl Drop for WebSocketConnection {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.inner.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::schemas::Color;
+    use crate::testutil::{assert_eventually, RecordingServerListener};
+    use crate::websocket::service::{Service, ServiceSchema};
+    use crate::websocket::ClientPermissions;
+    use crate::{ChannelBuilder, Context, WebSocketServer, WebSocketServerHandle};
+
+    async fn start_server(ctx: &Arc<Context>, listener: Arc<RecordingServerListener>) -> String {
+        let echo = Service::builder("/echo", ServiceSchema::new("echo"))
+            .handler_fn(|req| -> Result<Bytes, String> { Ok(req.into_payload()) });
+        let fail = Service::builder("/fail", ServiceSchema::new("fail"))
+            .handler_fn(|_| -> Result<Bytes, String> { Err("oops".into()) });
+        let handle: WebSocketServerHandle = WebSocketServer::new()
+            .bind("127.0.0.1", 0)
+            .context(ctx)
+            .capabilities([Capability::ClientPublish, Capability::Parameters])
+            .supported_encodings(["raw"])
+            .services([echo, fail])
+            .listener(listener)
+            .fetch_asset_handler_blocking_fn(|_, uri| match uri.as_str() {
+                "package://a" => Ok(b"asset".to_vec()),
+                _ => Err("not found"),
+            })
+            .start()
+            .await
+            .expect("Failed to start server");
+        format!("ws://127.0.0.1:{}", handle.port())
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let ctx = Context::new();
+        let listener = Arc::new(RecordingServerListener::new());
+        let url = start_server(&ctx, listener.clone()).await;
+        let channel = ChannelBuilder::new("/color").context(&ctx).build::<Color>();
+
+        let conn = WebSocketConnection::connect(&url).await.unwrap();
+        assert!(conn.is_connected());
+        let remote = conn.wait_for_channel("/color").await.unwrap();
+        assert_eq!(remote.id(), u64::from(channel.id()));
+        assert_eq!(remote.message_encoding(), "protobuf");
+        assert_eq!(
+            remote.schema().map(|s| s.name.as_str()),
+            Some("foxglove.Color")
+        );
+
+        let mut sub = conn.subscribe("/color").await.unwrap();
+        assert!(matches!(
+            conn.subscribe("/color").await,
+            Err(WebSocketClientError::AlreadySubscribed(_))
+        ));
+        assert_eventually(|| listener.take_subscribe().len() == 1).await;
+        channel.log(&Color {
+            r: 1.0,
+            ..Default::default()
+        });
+        let color: Color = tokio::time::timeout(Duration::from_secs(1), sub.recv_decoded())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(color.r, 1.0);
+
+        conn.unsubscribe("/color").await.unwrap();
+        assert!(matches!(
+            conn.unsubscribe("/color").await,
+            Err(WebSocketClientError::NotSubscribed(_))
+        ));
+        // Drain any queued messages; the subscription then ends.
+        while sub.recv().await.is_some() {}
+        assert!(matches!(
+            sub.error(),
+            Some(WebSocketClientError::NotSubscribed(_))
+        ));
+        assert!(matches!(
+            conn.subscribe("/missing").await,
+            Err(WebSocketClientError::UnknownTopic(_))
+        ));
+        conn.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_drop_subscription() {
+        let ctx = Context::new();
+        let listener = Arc::new(RecordingServerListener::new());
+        let url = start_server(&ctx, listener.clone()).await;
+        let channel = ChannelBuilder::new("/color").context(&ctx).build::<Color>();
+
+        let conn = WebSocketConnection::connect(&url).await.unwrap();
+        conn.wait_for_channel("/color").await.unwrap();
+        let sub = conn.subscribe("/color").await.unwrap();
+        assert_eventually(|| listener.take_subscribe().len() == 1).await;
+
+        // Dropping the subscription unsubscribes, without waiting for another message.
+        drop(sub);
+        assert_eventually(|| listener.take_unsubscribe().len() == 1).await;
+        assert!(matches!(
+            conn.unsubscribe("/color").await,
+            Err(WebSocketClientError::NotSubscribed(_))
+        ));
+
+        let mut sub = conn.subscribe("/color").await.unwrap();
+        assert_eventually(|| listener.take_subscribe().len() == 1).await;
+        channel.log(&Color {
+            g: 1.0,
+            ..Default::default()
+        });
+        let color: Color = tokio::time::timeout(Duration::from_secs(1), sub.recv_decoded())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(color.g, 1.0);
+        conn.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_requests() {
+        let ctx = Context::new();
+        let listener = Arc::new(RecordingServerListener::new());
+        listener.set_parameters_get_result(vec![Parameter::integer("a", 1)]);
+        let url = start_server(&ctx, listener.clone()).await;
+
+        let conn = WebSocketConnection::connect(&url).await.unwrap();
+        let info = conn.server_info();
+        assert_eq!(
+            info.capabilities()
+                .iter()
+                .filter(|c| **c == Capability::Parameters)
+                .count(),
+            1
+        );
+        assert!(info.has_capability(Capability::Services));
+        assert_eq!(info.supported_encodings(), ["raw"]);
+        conn.wait_for_service("/fail").await.unwrap();
+        let mut names: Vec<_> = conn
+            .services()
+            .iter()
+            .map(|s| s.name().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["/echo", "/fail"]);
+
+        let response = conn.call_service("/echo", "raw", b"hi").await.unwrap();
+        assert_eq!(response.as_ref(), b"hi");
+        assert!(matches!(
+            conn.call_service("/fail", "raw", b"hi").await,
+            Err(WebSocketClientError::ServiceCallFailed(msg)) if msg == "oops"
+        ));
+        assert!(matches!(
+            conn.call_service("/missing", "raw", b"hi").await,
+            Err(WebSocketClientError::UnknownService(_))
+        ));
+
+        let params = conn.get_parameters(["a"]).await.unwrap();
+        assert_eq!(params, vec![Parameter::integer("a", 1)]);
+        let params = conn
+            .set_parameters(vec![Parameter::string("b", "x")])
+            .await
+            .unwrap();
+        assert_eq!(params, vec![Parameter::string("b", "x")]);
+
+        let asset = conn.fetch_asset("package://a").await.unwrap();
+        assert_eq!(asset.as_ref(), b"asset");
+        assert!(matches!(
+            conn.fetch_asset("package://b").await,
+            Err(WebSocketClientError::FetchAssetFailed(msg)) if msg == "not found"
+        ));
+
+        let publisher = conn.advertise("/cmd", "json", None).await.unwrap();
+        publisher.publish(b"{}").await.unwrap();
+        assert_eventually(|| listener.message_data_len() == 1).await;
+        let data = listener.take_message_data();
+        assert_eq!(data[0].channel.topic, "/cmd");
+        assert_eq!(data[0].data, b"{}");
+        publisher.unadvertise().await.unwrap();
+        assert_eventually(|| listener.client_unadvertise_len() == 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_missing_capability() {
+        let ctx = Context::new();
+        let handle = WebSocketServer::new()
+            .bind("127.0.0.1", 0)
+            .context(&ctx)
+            .start()
+            .await
+            .expect("Failed to start server");
+        let conn = WebSocketConnection::connect(format!("ws://127.0.0.1:{}", handle.port()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            conn.get_parameters(["a"]).await,
+            Err(WebSocketClientError::UnsupportedCapability(
+                Capability::Parameters
+            ))
+        ));
+        assert!(matches!(
+            conn.advertise("/cmd", "json", None).await,
+            Err(WebSocketClientError::UnsupportedCapability(
+                Capability::ClientPublish
+            ))
+        ));
+
+        handle.stop().wait().await;
+        assert_eventually(|| !conn.is_connected()).await;
+        assert!(matches!(
+            conn.wait_for_channel("/any").await,
+            Err(WebSocketClientError::Disconnected)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_denied_requests() {
+        let ctx = Context::new();
+        let handle = WebSocketServer::new()
+            .bind("127.0.0.1", 0)
+            .context(&ctx)
+            .capabilities([Capability::Parameters])
+            .connection_authorizer_fn(|_| {
+                Ok(ClientPermissions::all()
+                    .subscribe_topics(["/allowed"])
+                    .write_parameters(false))
+            })
+            .start()
+            .await
+            .expect("Failed to start server");
+        let _channel = ChannelBuilder::new("/denied")
+            .context(&ctx)
+            .build::<Color>();
+
+        let conn = WebSocketConnection::connect(format!("ws://127.0.0.1:{}", handle.port()))
+            .await
+            .unwrap()
+            .with_parameter_timeout(Duration::from_millis(100));
+        conn.wait_for_channel("/denied").await.unwrap();
+
+        let mut sub = conn.subscribe("/denied").await.unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(1), sub.recv())
+            .await
+            .unwrap();
+        assert!(result.is_none());
+        assert!(matches!(
+            sub.error(),
+            Some(WebSocketClientError::SubscriptionDenied(msg)) if msg.ends_with("/denied")
+        ));
+        // The subscription was removed, so it can be retried.
+        assert!(conn.subscribe("/denied").await.is_ok());
+
+        assert!(matches!(
+            conn.set_parameters(vec![Parameter::integer("a", 1)]).await,
+            Err(WebSocketClientError::ParameterRequestFailed(_))
+        ));
+        // The server has no listener, so it does not answer.
+        assert!(matches!(
+            conn.get_parameters(["a"]).await,
+            Err(WebSocketClientError::Timeout)
+        ));
+        assert!(conn.inner.state.lock().parameter_requests.is_empty());
+    }
+}
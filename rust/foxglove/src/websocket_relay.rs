@@ -0,0 +1,624 @@
+//! WebSocket relay
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+use crate::websocket_client::{RemoteChannel, RemoteSubscription, WebSocketConnection};
+use crate::{
+    get_runtime_handle, Context, ContextObserver, PartialMetadata, RawChannel, SinkId,
+    WebSocketClientError,
+};
+
+/// The delay before the first attempt to reconnect to an upstream server.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// The maximum delay between attempts to reconnect to an upstream server.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// A relay which mirrors the channels of upstream Foxglove WebSocket servers into a [`Context`].
+///
+/// The relay connects as a client to each upstream server, and re-creates each advertised channel
+/// as a [`RawChannel`] in its context, with the same message encoding and schema. Messages are
+/// logged to the local channels with their original log times, so that a single
+/// [`WebSocketServer`][crate::WebSocketServer] or [`McapWriter`][crate::McapWriter] can serve or
+/// record all of them.
+///
+/// The relay only subscribes to an upstream channel while a sink is subscribed to the local
+/// channel. A local channel is closed when the upstream server unadvertises it, or when the
+/// connection to the upstream server is lost. Lost connections are retried with exponential
+/// backoff, up to 5 seconds between attempts.
+///
+/// If the context already has a matching channel on a relayed topic, the relay logs to that
+/// channel instead of creating one, and leaves it open when the upstream channel goes away.
+///
+/// ### Example
+///
+/// ```no_run
+/// # fn example() -> Result<(), foxglove::WebSocketClientError> {
+/// use foxglove::WebSocketRelay;
+///
+/// let relay = WebSocketRelay::new()
+///     .upstream_with_topic_prefix("ws://127.0.0.1:8766", "/camera")
+///     .upstream_with_topic_prefix("ws://127.0.0.1:8767", "/lidar")
+///     .start()?;
+/// # Ok(())
+/// # }
+/// ```
+#[must_use]
+#[derive(Debug)]
+pub struct WebSocketRelay {
+    upstreams: Vec<Upstream>,
+    context: Arc<Context>,
+}
+
+#[derive(Debug)]
+struct Upstream {
+    url: String,
+    topic_prefix: String,
+}
+
+impl Default for WebSocketRelay {
+    fn default() -> Self {
+        Self {
+            upstreams: Vec::new(),
+            context: Context::get_default(),
+        }
+    }
+}
+
+impl WebSocketRelay {
+    /// Creates a new relay with no upstream servers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an upstream server at the given URL, such as `ws://127.0.0.1:8766`.
+    ///
+    /// Channels are re-created with their original topics.
+    pub fn upstream(self, url: impl Into<String>) -> Self {
+        self.upstream_with_topic_prefix(url, "")
+    }
+
+    /// Adds an upstream server at the given URL, with a prefix for its topics.
+    ///
+    /// The prefix is prepended to each topic as-is. For example, with the prefix `/robot1`, the
+    /// upstream topic `/imu` is re-created as `/robot1/imu`.
+    pub fn upstream_with_topic_prefix(
+        mut self,
+        url: impl Into<String>,
+        topic_prefix: impl Into<String>,
+    ) -> Self {
+        self.upstreams.push(Upstream {
+            url: url.into(),
+            topic_prefix: topic_prefix.into(),
+        });
+        self
+    }
+
+    /// Sets the context in which channels are created.
+    ///
+    /// By default, channels are created in the default context.
+    pub fn context(mut self, ctx: &Arc<Context>) -> Self {
+        self.context = ctx.clone();
+        self
+    }
+
+    /// Starts relaying the channels of the upstream servers.
+    ///
+    /// Returns an error if an upstream URL is invalid. Upstream servers which cannot be reached
+    /// are retried in the background. The relay runs on the current tokio runtime, or on the SDK's
+    /// own runtime if there is none, until it is stopped with [`WebSocketRelayHandle::stop`].
+    pub fn start(self) -> Result<WebSocketRelayHandle, WebSocketClientError> {
+        for upstream in &self.upstreams {
+            upstream.url.as_str().into_client_request()?;
+        }
+        let observer = Arc::new(DemandObserver {
+            changed: watch::Sender::new(()),
+        });
+        self.context.add_observer(observer.clone());
+        let runtime = get_runtime_handle();
+        let tasks = self
+            .upstreams
+            .into_iter()
+            .map(|upstream| {
+                runtime.spawn(run_upstream(
+                    upstream,
+                    self.context.clone(),
+                    observer.changed.subscribe(),
+                ))
+            })
+            .collect();
+        Ok(WebSocketRelayHandle {
+            tasks,
+            context: self.context,
+            observer,
+        })
+    }
+}
+
+/// A handle to a running [`WebSocketRelay`].
+///
+/// Dropping the handle does not stop the relay.
+#[derive(Debug)]
+pub struct WebSocketRelayHandle {
+    tasks: Vec<JoinHandle<()>>,
+    context: Arc<Context>,
+    observer: Arc<DemandObserver>,
+}
+
+impl WebSocketRelayHandle {
+    /// Disconnects from the upstream servers, and closes the relayed channels.
+    pub fn stop(self) {
+        for task in self.tasks {
+            task.abort();
+        }
+        let observer: Arc<dyn ContextObserver> = self.observer;
+        self.context.remove_observer(&observer);
+    }
+}
+
+/// Notifies the relay when sinks subscribe to or unsubscribe from channels.
+#[derive(Debug)]
+struct DemandObserver {
+    changed: watch::Sender<()>,
+}
+
+impl ContextObserver for DemandObserver {
+    fn on_subscribe(&self, _channel: &Arc<RawChannel>, _sink_id: SinkId) {
+        self.changed.send_replace(());
+    }
+
+    fn on_unsubscribe(&self, _channel: &Arc<RawChannel>, _sink_id: SinkId) {
+        self.changed.send_replace(());
+    }
+}
+
+/// Relays channels from an upstream server, reconnecting whenever the connection is lost.
+async fn run_upstream(upstream: Upstream, ctx: Arc<Context>, demand: watch::Receiver<()>) {
+    let mut delay = MIN_RECONNECT_DELAY;
+    loop {
+        match WebSocketConnection::connect(&upstream.url).await {
+            Ok(conn) => {
+                delay = MIN_RECONNECT_DELAY;
+                relay_upstream(conn, &ctx, &upstream.topic_prefix, demand.clone()).await;
+                tracing::info!("Lost connection to upstream server {}", upstream.url);
+            }
+            Err(err) => {
+                tracing::debug!(
+                    "Failed to connect to upstream server {}: {err}",
+                    upstream.url
+                );
+            }
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+/// Relays channels from an upstream server until the connection is lost.
+async fn relay_upstream(
+    conn: WebSocketConnection,
+    ctx: &Arc<Context>,
+    topic_prefix: &str,
+    mut demand: watch::Receiver<()>,
+) {
+    let mut changes = conn.watch_changes();
+    let mut relayed: HashMap<u64, RelayedChannel> = HashMap::new();
+    loop {
+        let channels: HashMap<u64, RemoteChannel> =
+            conn.channels().into_iter().map(|c| (c.id(), c)).collect();
+        // Stop relaying channels which were unadvertised or re-advertised, before replacing them.
+        let stale: Vec<u64> = relayed
+            .iter()
+            .filter(|(id, relayed)| channels.get(id) != Some(&relayed.remote))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in stale {
+            if let Some(relayed) = relayed.remove(&id) {
+                relayed.stop(&conn).await;
+            }
+        }
+        for channel in channels.into_values() {
+            let relayed = relayed
+                .entry(channel.id())
+                .or_insert_with(|| RelayedChannel::new(ctx, topic_prefix, channel));
+            relayed.update_subscription(&conn).await;
+        }
+        tokio::select! {
+            result = changes.changed() => {
+                if result.is_err() || !conn.is_connected() {
+                    break;
+                }
+            }
+            Ok(()) = demand.changed() => (),
+        }
+    }
+    // Dropping the relayed channels closes them.
+}
+
+/// An upstream channel which is relayed to a local channel.
+struct RelayedChannel {
+    remote: RemoteChannel,
+    local: Arc<RawChannel>,
+    /// Whether the relay created the local channel, and is therefore responsible for closing it.
+    owned: bool,
+    /// The task forwarding messages to the local channel, while subscribed.
+    forward: Option<JoinHandle<()>>,
+}
+
+impl RelayedChannel {
+    fn new(ctx: &Arc<Context>, topic_prefix: &str, remote: RemoteChannel) -> Self {
+        let created = RawChannel::new(
+            ctx,
+            format!("{topic_prefix}{}", remote.topic()),
+            remote.message_encoding().to_string(),
+            remote.schema().cloned(),
+            BTreeMap::new(),
+            0,
+        );
+        // The context returns an existing channel instead, if it has a matching one.
+        let local = ctx.add_channel(created.clone());
+        let owned = Arc::ptr_eq(&local, &created);
+        Self {
+            remote,
+            local,
+            owned,
+            forward: None,
+        }
+    }
+
+    /// Subscribes to the upstream channel if a sink is subscribed to the local channel, and
+    /// unsubscribes otherwise.
+    ///
+    /// If the upstream subscription has ended, for example because the server denied it, this
+    /// subscribes again.
+    async fn update_subscription(&mut self, conn: &WebSocketConnection) {
+        if self.forward.as_ref().is_some_and(JoinHandle::is_finished) {
+            self.forward = None;
+        }
+        let topic = self.remote.topic();
+        match (&self.forward, self.local.has_sinks()) {
+            (None, true) => match conn.subscribe_channel(self.remote.id()).await {
+                Ok(subscription) => {
+                    let local = self.local.clone();
+                    self.forward = Some(tokio::spawn(forward(subscription, local)));
+                }
+                Err(err) => tracing::warn!("Failed to subscribe to {topic}: {err}"),
+            },
+            (Some(_), false) => self.unsubscribe(conn).await,
+            _ => (),
+        }
+    }
+
+    /// Stops forwarding messages, and unsubscribes from the upstream channel.
+    async fn unsubscribe(&mut self, conn: &WebSocketConnection) {
+        if let Some(task) = self.forward.take() {
+            task.abort();
+            if let Err(err) = conn.unsubscribe_channel(self.remote.id()).await {
+                tracing::debug!("Failed to unsubscribe from {}: {err}", self.remote.topic());
+            }
+        }
+    }
+
+    /// Stops relaying the channel, and closes the local channel if the relay created it.
+    async fn stop(mut self, conn: &WebSocketConnection) {
+        self.unsubscribe(conn).await;
+    }
+}
+
+impl Drop for RelayedChannel {
+    fn drop(&mut self) {
+        if let Some(task) = self.forward.take() {
+            task.abort();
+        }
+        if self.owned {
+            self.local.close();
+        }
+    }
+}
+
+async fn forward(mut subscription: RemoteSubscription, channel: Arc<RawChannel>) {
+    while let Some(msg) = subscription.recv().await {
+        channel.log_with_meta(msg.data(), PartialMetadata::with_log_time(msg.log_time()));
+    }
+    if let Some(WebSocketClientError::SubscriptionDenied(message)) = subscription.error() {
+        tracing::warn!("Failed to relay {}: {message}", channel.topic());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::testutil::{
+        assert_eventually, assert_eventually_within, RecordingServerListener, RecordingSink,
+    };
+    use crate::websocket::ClientPermissions;
+    use crate::{ChannelBuilder, ChannelId, Schema, Sink, WebSocketServer, WebSocketServerHandle};
+
+    fn schema() -> Schema {
+        Schema::new("s", "jsonschema", br#"{"type":"object"}"#)
+    }
+
+    fn build_channel(ctx: &Arc<Context>, topic: &str) -> Arc<RawChannel> {
+        ChannelBuilder::new(topic)
+            .context(ctx)
+            .message_encoding("json")
+            .schema(schema())
+            .build_raw()
+            .unwrap()
+    }
+
+    async fn start_upstream(ctx: &Arc<Context>, port: u16) -> WebSocketServerHandle {
+        WebSocketServer::new()
+            .bind("127.0.0.1", port)
+            .context(ctx)
+            .start()
+            .await
+            .expect("Failed to start server")
+    }
+
+    fn start_relay(ctx: &Arc<Context>, port: u16) -> WebSocketRelayHandle {
+        WebSocketRelay::new()
+            .context(ctx)
+            .upstream_with_topic_prefix(format!("ws://127.0.0.1:{port}"), "/up")
+            .start()
+            .unwrap()
+    }
+
+    /// Waits for the sink to record a message, and returns its channel ID, data, and log time.
+    async fn recv_message(sink: &RecordingSink) -> (ChannelId, Vec<u8>, u64) {
+        let messages = Mutex::new(vec![]);
+        assert_eventually(|| {
+            let mut messages = messages.lock();
+            messages.extend(
+                sink.take_messages()
+                    .into_iter()
+                    .map(|m| (m.channel_id, m.msg, m.metadata.log_time)),
+            );
+            !messages.is_empty()
+        })
+        .await;
+        messages.into_inner().remove(0)
+    }
+
+    #[tokio::test]
+    async fn test_relay() {
+        let upstream_ctx = Context::new();
+        let upstream = start_upstream(&upstream_ctx, 0).await;
+        let channel = build_channel(&upstream_ctx, "/a");
+
+        let ctx = Context::new();
+        let sink = Arc::new(RecordingSink::new());
+        ctx.add_sink(sink.clone());
+        let relay = start_relay(&ctx, upstream.port());
+
+        assert_eventually(|| ctx.get_channel_by_topic("/up/a").is_some()).await;
+        let local = ctx.get_channel_by_topic("/up/a").unwrap();
+        assert_eq!(local.message_encoding(), "json");
+        assert_eq!(local.schema(), Some(&schema()));
+
+        assert_eventually(|| channel.has_sinks()).await;
+        channel.log_with_meta(b"{}", PartialMetadata::with_log_time(42));
+        assert_eq!(recv_message(&sink).await, (local.id(), b"{}".to_vec(), 42));
+
+        channel.close();
+        assert_eventually(|| ctx.get_channel_by_topic("/up/a").is_none()).await;
+        relay.stop();
+        upstream.stop().wait().await;
+    }
+
+    #[test]
+    fn test_invalid_url() {
+        let result = WebSocketRelay::new()
+            .context(&Context::new())
+            .upstream("not a url")
+            .start();
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_on_demand() {
+        let upstream_ctx = Context::new();
+        let listener = Arc::new(RecordingServerListener::new());
+        let upstream = WebSocketServer::new()
+            .bind("127.0.0.1", 0)
+            .context(&upstream_ctx)
+            .listener(listener.clone())
+            .start()
+            .await
+            .expect("Failed to start server");
+        let channel = build_channel(&upstream_ctx, "/a");
+
+        let ctx = Context::new();
+        let relay = start_relay(&ctx, upstream.port());
+        assert_eventually(|| ctx.get_channel_by_topic("/up/a").is_some()).await;
+
+        // Without a local sink, the relay does not subscribe upstream.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(listener.take_subscribe().is_empty());
+
+        let sink = Arc::new(RecordingSink::new());
+        ctx.add_sink(sink.clone());
+        assert_eventually(|| channel.has_sinks()).await;
+        assert_eq!(listener.take_subscribe().len(), 1);
+
+        ctx.remove_sink(sink.id());
+        assert_eventually(|| !channel.has_sinks()).await;
+        assert_eq!(listener.take_unsubscribe().len(), 1);
+
+        relay.stop();
+        upstream.stop().wait().await;
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_after_upstream_loss() {
+        // Find a free port, so that the relay can start before the upstream server.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let upstream_ctx = Context::new();
+        let channel = build_channel(&upstream_ctx, "/a");
+
+        let ctx = Context::new();
+        let sink = Arc::new(RecordingSink::new());
+        ctx.add_sink(sink.clone());
+        let relay = start_relay(&ctx, port);
+
+        let upstream = start_upstream(&upstream_ctx, port).await;
+        assert_eventually_within(Duration::from_secs(5), || {
+            ctx.get_channel_by_topic("/up/a").is_some()
+        })
+        .await;
+
+        // The local channel is closed when the upstream server goes away.
+        upstream.stop().wait().await;
+        assert_eventually(|| ctx.get_channel_by_topic("/up/a").is_none()).await;
+
+        // It is re-created when the relay reconnects.
+        let upstream = start_upstream(&upstream_ctx, port).await;
+        assert_eventually_within(Duration::from_secs(5), || {
+            ctx.get_channel_by_topic("/up/a").is_some()
+        })
+        .await;
+        let local = ctx.get_channel_by_topic("/up/a").unwrap();
+        assert_eventually(|| channel.has_sinks()).await;
+        channel.log_with_meta(b"{}", PartialMetadata::with_log_time(1));
+        assert_eq!(recv_message(&sink).await.0, local.id());
+
+        relay.stop();
+        upstream.stop().wait().await;
+    }
+
+    #[tokio::test]
+    async fn test_readvertise() {
+        let upstream_ctx = Context::new();
+        let upstream = start_upstream(&upstream_ctx, 0).await;
+        let channel = build_channel(&upstream_ctx, "/a");
+
+        let ctx = Context::new();
+        let sink = Arc::new(RecordingSink::new());
+        ctx.add_sink(sink.clone());
+        let relay = start_relay(&ctx, upstream.port());
+        assert_eventually(|| ctx.get_channel_by_topic("/up/a").is_some()).await;
+        let old_local = ctx.get_channel_by_topic("/up/a").unwrap();
+
+        // Re-advertise the channel with a new ID. The matching local channel must be replaced,
+        // not closed out from under its replacement.
+        channel.close();
+        let channel = build_channel(&upstream_ctx, "/a");
+        assert_eventually(|| {
+            ctx.get_channel_by_topic("/up/a")
+                .is_some_and(|local| local.id() != old_local.id())
+        })
+        .await;
+        assert_eventually(|| channel.has_sinks()).await;
+        let local = ctx.get_channel_by_topic("/up/a").unwrap();
+        assert!(!local.is_closed());
+
+        channel.log_with_meta(b"{}", PartialMetadata::with_log_time(1));
+        assert_eq!(recv_message(&sink).await.0, local.id());
+
+        relay.stop();
+        assert_eventually(|| local.is_closed()).await;
+        upstream.stop().wait().await;
+    }
+
+    #[tokio::test]
+    async fn test_channels_sharing_a_topic() {
+        let upstream_ctx = Context::new();
+        let upstream = start_upstream(&upstream_ctx, 0).await;
+        let json = build_channel(&upstream_ctx, "/a");
+        let raw = ChannelBuilder::new("/a")
+            .context(&upstream_ctx)
+            .message_encoding("raw")
+            .build_raw()
+            .unwrap();
+
+        let ctx = Context::new();
+        let sink = Arc::new(RecordingSink::new());
+        ctx.add_sink(sink.clone());
+        let relay = start_relay(&ctx, upstream.port());
+
+        // Each upstream channel is subscribed separately.
+        assert_eventually(|| json.has_sinks() && raw.has_sinks()).await;
+        json.log(b"{}");
+        let (json_id, data, _) = recv_message(&sink).await;
+        assert_eq!(data, b"{}");
+        raw.log(b"raw");
+        let (raw_id, data, _) = recv_message(&sink).await;
+        assert_eq!(data, b"raw");
+        assert_ne!(json_id, raw_id);
+
+        ctx.remove_sink(sink.id());
+        assert_eventually(|| !json.has_sinks() && !raw.has_sinks()).await;
+
+        relay.stop();
+        upstream.stop().wait().await;
+    }
+
+    #[tokio::test]
+    async fn test_resubscribe_after_denial() {
+        let upstream_ctx = Context::new();
+        let upstream = WebSocketServer::new()
+            .bind("127.0.0.1", 0)
+            .context(&upstream_ctx)
+            .connection_authorizer_fn(|_| Ok(ClientPermissions::all().subscribe_topics(["/b"])))
+            .start()
+            .await
+            .expect("Failed to start server");
+        let _channel = build_channel(&upstream_ctx, "/a");
+
+        let conn = WebSocketConnection::connect(format!("ws://127.0.0.1:{}", upstream.port()))
+            .await
+            .unwrap();
+        let remote = conn.wait_for_channel("/a").await.unwrap();
+        let ctx = Context::new();
+        let mut relayed = RelayedChannel::new(&ctx, "/up", remote);
+        ctx.add_sink(Arc::new(RecordingSink::new()));
+
+        relayed.update_subscription(&conn).await;
+        assert!(relayed.forward.is_some());
+
+        // The server denies the subscription, which ends the forwarding task. The next update
+        // subscribes again.
+        assert_eventually(|| relayed.forward.as_ref().unwrap().is_finished()).await;
+        relayed.update_subscription(&conn).await;
+        assert!(relayed.forward.as_ref().is_some_and(|t| !t.is_finished()));
+
+        drop(relayed);
+        upstream.stop().wait().await;
+    }
+
+    #[tokio::test]
+    async fn test_shared_channel_not_closed() {
+        let upstream_ctx = Context::new();
+        let upstream = start_upstream(&upstream_ctx, 0).await;
+        let shared_upstream = build_channel(&upstream_ctx, "/a");
+        let owned_upstream = build_channel(&upstream_ctx, "/b");
+
+        // The local context already has a channel matching "/up/a".
+        let ctx = Context::new();
+        let shared = build_channel(&ctx, "/up/a");
+        let relay = start_relay(&ctx, upstream.port());
+        assert_eventually(|| ctx.get_channel_by_topic("/up/b").is_some()).await;
+
+        shared_upstream.close();
+        owned_upstream.close();
+        assert_eventually(|| ctx.get_channel_by_topic("/up/b").is_none()).await;
+        assert!(!shared.is_closed());
+
+        relay.stop();
+        upstream.stop().wait().await;
+    }
+}
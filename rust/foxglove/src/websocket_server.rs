@@ -5,17 +5,25 @@ use std::fmt::{Debug, Display};
 use std::future::Future;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::sink_channel_filter::{SinkChannelFilter, SinkChannelFilterFn};
+use crate::sink_message_filter::{SinkMessageFilter, SinkMessageFilterFn};
 use crate::websocket::service::Service;
 use crate::websocket::PlaybackState;
-#[cfg(feature = "tls")]
-use crate::websocket::TlsIdentity;
 use crate::websocket::{
-    create_server, AssetHandler, AsyncAssetHandlerFn, BlockingAssetHandlerFn, Capability, Client,
-    ConnectionGraph, Parameter, Server, ServerOptions, ShutdownHandle, Status,
+    create_server, AssetHandler, AsyncAssetHandlerFn, AuthorizationError, BlockingAssetHandlerFn,
+    Capability, Client, ClientPermissions, ConnectionAuthorizer, ConnectionAuthorizerFn,
+    ConnectionGraph, ConnectionRequest, Parameter, Server, ServerOptions, ShutdownHandle, Status,
+};
+#[cfg(feature = "tls")]
+use crate::websocket::{TlsClientAuth, TlsIdentity};
+use crate::{
+    get_runtime_handle, AppUrl, ChannelDescriptor, Context, FoxgloveError, Metadata, RawChannel,
 };
-use crate::{get_runtime_handle, AppUrl, ChannelDescriptor, Context, FoxgloveError};
+
+/// The minimum interval for [`WebSocketServer::broadcast_clock_time`].
+const MIN_CLOCK_BROADCAST_INTERVAL: Duration = Duration::from_millis(1);
 
 /// A WebSocket server for live visualization in Foxglove.
 ///
@@ -100,6 +108,24 @@ impl WebSocketServer {
         self
     }
 
+    /// Sets a [`SinkMessageFilter`] for connected clients.
+    ///
+    /// The filter is consulted for each message on a subscribed channel, and returns a boolean
+    /// indicating whether the message should be sent to the client.
+    pub fn message_filter(mut self, filter: Arc<dyn SinkMessageFilter>) -> Self {
+        self.options.message_filter = Some(filter);
+        self
+    }
+
+    /// Sets a message filter for connected clients. See [`SinkMessageFilter`] for more information.
+    pub fn message_filter_fn(
+        mut self,
+        filter: impl Fn(&RawChannel, &[u8], &Metadata) -> bool + Sync + Send + 'static,
+    ) -> Self {
+        self.options.message_filter = Some(Arc::new(SinkMessageFilterFn(filter)));
+        self
+    }
+
     /// Configure TLS with a PEM-formatted x509 certificate chain and pkcs8 private key.
     /// If enabled, the server will only accept connections using wss://.
     /// If TLS configuration fails, starting the server will result in an error.
@@ -110,6 +136,17 @@ impl WebSocketServer {
         self
     }
 
+    /// Configure verification of client certificates against a trusted CA bundle.
+    /// Requires a TLS identity to be configured with [`WebSocketServer::tls`].
+    /// The subject of a verified client certificate is available from
+    /// [`Client::peer_certificate_subject`].
+    #[doc(hidden)]
+    #[cfg(feature = "tls")]
+    pub fn tls_client_auth(mut self, client_auth: TlsClientAuth) -> Self {
+        self.options.tls_client_auth = Some(client_auth);
+        self
+    }
+
     /// Sets the server capabilities to advertise to the client.
     ///
     /// By default, the server does not advertise any capabilities.
@@ -118,6 +155,27 @@ impl WebSocketServer {
         self
     }
 
+    /// Adds a capability to the set of capabilities that the server advertises.
+    pub(crate) fn add_capability(mut self, capability: Capability) -> Self {
+        self.options
+            .capabilities
+            .get_or_insert_with(Default::default)
+            .insert(capability);
+        self
+    }
+
+    /// Periodically broadcasts the time from the context's [`Clock`][crate::Clock] to clients.
+    ///
+    /// This implies the [`Time`](crate::websocket::Capability::Time) capability. It is useful
+    /// when the context's clock follows a simulation or replay, so that the Foxglove app's
+    /// playback time tracks the clock.
+    ///
+    /// The interval is clamped to a minimum of one millisecond.
+    pub fn broadcast_clock_time(mut self, interval: Duration) -> Self {
+        self.options.clock_broadcast_interval = Some(interval.max(MIN_CLOCK_BROADCAST_INTERVAL));
+        self
+    }
+
     /// Sets server metadata.
     #[doc(hidden)]
     pub fn server_info(mut self, info: HashMap<String, String>) -> Self {
@@ -138,6 +196,30 @@ impl WebSocketServer {
         self
     }
 
+    /// Sets a [`ConnectionAuthorizer`] to decide whether clients may connect.
+    ///
+    /// The authorizer inspects each client's HTTP upgrade request, and either rejects the client,
+    /// or returns the [`ClientPermissions`] that the server enforces for the client's session.
+    ///
+    /// By default, all clients are accepted with full permissions.
+    pub fn connection_authorizer(mut self, authorizer: Arc<dyn ConnectionAuthorizer>) -> Self {
+        self.options.connection_authorizer = Some(authorizer);
+        self
+    }
+
+    /// Sets a function to decide whether clients may connect. See [`ConnectionAuthorizer`] for more
+    /// information.
+    pub fn connection_authorizer_fn(
+        mut self,
+        authorizer: impl Fn(&ConnectionRequest<'_>) -> Result<ClientPermissions, AuthorizationError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.options.connection_authorizer = Some(Arc::new(ConnectionAuthorizerFn(authorizer)));
+        self
+    }
+
     /// Configure the handler for fetching assets.
     /// There can only be one asset handler, exclusive with the other fetch_asset_handler methods.
     pub fn fetch_asset_handler(mut self, handler: Box<dyn AssetHandler>) -> Self {
@@ -198,6 +280,15 @@ impl WebSocketServer {
         self
     }
 
+    /// Adds a service to the set of services to advertise to clients.
+    pub(crate) fn add_service(mut self, service: Service) -> Self {
+        let name = service.name().to_string();
+        if let Some(s) = self.options.services.insert(name, service) {
+            tracing::warn!("Redefining service {}", s.name());
+        }
+        self
+    }
+
     /// Configure the set of supported encodings for client requests.
     ///
     /// This is used for both client-side publishing as well as service call request/responses.
@@ -335,6 +426,19 @@ impl WebSocketServerHandle {
         self.0.broadcast_playback_state(playback_state);
     }
 
+    /// Replaces the server's TLS identity.
+    ///
+    /// New connections are accepted with the new certificate; existing connections are not
+    /// affected. This allows certificates to be rotated without restarting the server.
+    ///
+    /// Returns an error if the server was not configured with TLS, or if the identity is invalid,
+    /// in which case the previous identity remains in use.
+    #[doc(hidden)]
+    #[cfg(feature = "tls")]
+    pub fn set_tls_identity(&self, tls_identity: TlsIdentity) -> Result<(), FoxgloveError> {
+        self.0.set_tls_identity(&tls_identity)
+    }
+
     /// Sets a new session ID and notifies all clients, causing them to reset their state.
     /// If no session ID is provided, generates a new one based on the current timestamp.
     pub fn clear_session(&self, new_session_id: Option<String>) {
@@ -346,6 +450,11 @@ impl WebSocketServerHandle {
         self.0.publish_parameter_values(parameters);
     }
 
+    /// Returns a weak reference to the server.
+    pub(crate) fn weak_server(&self) -> std::sync::Weak<Server> {
+        Arc::downgrade(&self.0)
+    }
+
     /// Publishes a status message to all clients.
     ///
     /// This can be used to communicate information, warnings, and errors to the Foxglove app. An
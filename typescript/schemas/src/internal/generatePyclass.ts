@@ -749,7 +749,7 @@ impl ${channelClass} {
         log_time: Option<u64>,
         sink_id: Option<u64>,
     ) {
-        let metadata = PartialMetadata{ log_time };
+        let metadata = PartialMetadata { log_time, ..Default::default() };
         let sink_id = sink_id.and_then(NonZero::new).map(SinkId::new);
 
         self.0.log_with_meta_to_sink(&msg.0, metadata, sink_id);